@@ -0,0 +1,151 @@
+// ============================================================================
+// Render / update hot path benchmarks
+//
+// Measures the per-tick cost of turning the current state into a UI frame and
+// of mapping the pressed keys to a target speed. Rendering goes through a
+// ratatui TestBackend, so no real terminal is needed.
+//
+// Usage:
+//   cargo bench --bench render
+// ============================================================================
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use crossterm::event::KeyCode;
+use ratatui::{backend::TestBackend, Terminal};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use robot_examples::camera::CameraMount;
+use robot_examples::keyboard_input::{KeyBindings, KeyState, KeyboardInput, SpeedData, SpeedMode};
+use robot_examples::kinematics::{BaseGeometry, BaseType};
+use robot_examples::log_buffer::LogBuffer;
+use robot_examples::motion_queue::{MotionQueue, Step};
+use robot_examples::odometer::Odometer;
+use robot_examples::robot_ui::{
+    ControlState, ErrorMessage, FollowIndicator, ProtocolIndicator, RobotUi, Theme, UiView,
+};
+use robot_examples::smoothing::SmoothingProfile;
+use robot_examples::speed_history::SpeedHistory;
+use robot_examples::units::UnitSystem;
+
+/// Every key a keyboard can plausibly report at once
+fn full_pressed_keys() -> HashMap<KeyCode, KeyState> {
+    let now = Instant::now();
+    let mut keys: HashMap<KeyCode, KeyState> = ('a'..='z')
+        .chain('0'..='9')
+        .map(|c| (KeyCode::Char(c), KeyState::pressed_at(now)))
+        .collect();
+    for key in [
+        KeyCode::Up,
+        KeyCode::Down,
+        KeyCode::Left,
+        KeyCode::Right,
+        KeyCode::Enter,
+        KeyCode::Esc,
+    ] {
+        keys.insert(key, KeyState::pressed_at(now));
+    }
+    keys
+}
+
+fn bench_render(c: &mut Criterion) {
+    let pressed_keys = full_pressed_keys();
+    let error_message = ErrorMessage::new("Control in hands of another user".to_string());
+    let mut motion_queue = MotionQueue::new(0.1, 0.5);
+    for _ in 0..4 {
+        motion_queue.push(Step::Forward(0.5));
+        motion_queue.push(Step::TurnLeft(std::f32::consts::FRAC_PI_2));
+    }
+    let mut log = LogBuffer::default();
+    for i in 0..50 {
+        log.push(format!("-> move x=+0.100 y=+0.000 z=+0.{:03}", i));
+    }
+    let wheel_speeds = BaseGeometry {
+        base_type: BaseType::Mecanum,
+        wheel_radius: 0.05,
+        track_width: 0.3,
+        wheel_base: 0.3,
+    }
+    .wheel_speeds(SpeedData::new(0.1, -0.1, 0.5));
+    let mut speed_history = SpeedHistory::default();
+    for i in 0..250 {
+        speed_history.push(0.1 * (i as f32 / 25.0).sin());
+    }
+    let mut terminal = Terminal::new(TestBackend::new(120, 50)).unwrap();
+
+    c.bench_function("render_frame", |b| {
+        b.iter(|| {
+            let view = UiView {
+                control_state: ControlState::CanMove,
+                target_speed: SpeedData::new(0.1, -0.1, 0.5),
+                actual_speed: Some(SpeedData::new(0.098, -0.101, 0.49)),
+                actual_speed_stale: false,
+                pressed_keys: &pressed_keys,
+                error_message: &error_message,
+                emergency_stop: false,
+                motion_queue: &motion_queue,
+                odometer: Odometer::default(),
+                smoothing: SmoothingProfile::None,
+                cruise: Some(SpeedData::new(0.1, 0.0, 0.0)),
+                log: &log,
+                wheel_speeds: Some(&wheel_speeds),
+                monochrome: false,
+                url: Some("ws://localhost:8439"),
+                session_id: Some(42),
+                profile: Some("warehouse"),
+                camera: CameraMount::Left,
+                exit_hold_progress: None,
+                speed_history: &speed_history,
+                log_scroll: 0,
+                latency: Some(std::time::Duration::from_millis(12)),
+                battery: Some(64.0),
+                protocol: Some(ProtocolIndicator {
+                    version: 1,
+                    expected: "1".to_string(),
+                    blocked: false,
+                }),
+                takeover: None,
+                paused: false,
+                confirm_exit: false,
+                speed_mode: SpeedMode::Turbo,
+                deadman: Some(true),
+                max_speed: Some(SpeedData::new(0.1, 0.1, 0.5)),
+                follow: Some(FollowIndicator {
+                    engaged: true,
+                    link: "connected",
+                    leader_speed: Some(SpeedData::new(0.1, 0.0, 0.0)),
+                }),
+                units: UnitSystem::Si,
+                key_speeds: (0.1, 0.5),
+                dimmed: false,
+                show_help: false,
+                bases: &[],
+            };
+            terminal
+                .draw(|f| RobotUi::render(f, black_box(&view), &Theme::DARK))
+                .unwrap();
+        })
+    });
+}
+
+fn bench_target_speed(c: &mut Criterion) {
+    let keys = full_pressed_keys();
+
+    let bindings = KeyBindings::default();
+
+    c.bench_function("target_speed_full_keys", |b| {
+        b.iter(|| {
+            KeyboardInput::target_speed(
+                black_box(&keys),
+                &bindings,
+                false,
+                black_box(0.1),
+                black_box(0.5),
+                1.0,
+            )
+        })
+    });
+}
+
+criterion_group!(benches, bench_render, bench_target_speed);
+criterion_main!(benches);
@@ -5,41 +5,541 @@
 // It demonstrates:
 //   - WebSocket connection to the robot
 //   - API initialization and session management
+//   - Reconnecting with backoff when the connection drops
 //   - Real-time speed control with keyboard
 //   - Live feedback display with terminal UI
 //
+// The connection, reconnects, receiver and command pipeline are the library's
+// `connection` and `receiver` modules; this file wires the command line, the
+// UI and the input to them.
+//
 // Usage:
-//   cargo run --example base-advanced-control ws://localhost:8439
+//   cargo run --bin base-advanced-control-websocket ws://localhost:8439
+//   cargo run --bin base-advanced-control-websocket -- --preview   (UI preview without a robot)
+//   cargo run --bin base-advanced-control-websocket ws://base-a:8439 ws://base-b:8439
+//   cargo run --bin base-advanced-control-websocket -- --replay session.jsonl ws://localhost:8439
+//   cargo run --bin base-advanced-control-websocket -- --log-file demo.log --log-level debug ws://localhost:8439
+//   cargo run --features tracing --bin base-advanced-control-websocket -- --log-file demo.log ws://localhost:8439
+//   cargo run --features tls --bin base-advanced-control-websocket -- --ca-cert robot.pem wss://robot:8439
 //
 // Controls:
-//   W/S - Move forward/backward (X axis)
+//   W/S - Move forward/backward (X axis), hold Shift for turbo or Ctrl for precision
 //   A/D - Move left/right (Y axis)
+//   Arrow keys - Move like W/A/S/D
 //   Q/E - Rotate left/right (Z axis)
-//   ESC/C - Exit
+//   +/- - Raise/lower the speed of the movement keys for this session
+//   ]/[ - Raise/lower the speed of the rotation keys for this session
+//   I/K - Queue a one grid step move forward/backward
+//   J/L - Queue a 90 degree turn left/right
+//   U/O - Queue a fixed distance strafe left/right
+//   X   - Clear the motion queue and disengage cruise control
+//   0   - Reset the odometer
+//   R   - Recover: re-init if needed and send a burst of the current command
+//   F   - Cycle the command smoothing profile
+//   V   - Cruise control: hold the current commanded velocity hands-free
+//   M   - Cycle the camera orientation movement input is relative to
+//   P   - Switch to the next profile of the --config file (stops the robot first)
+//   N   - Cycle the color theme, the one picked is kept for the next session
+//   B   - Follow the --follow leader's velocity (movement keys or estop break it)
+//   T   - Take control held by another session (press twice to confirm)
+//   Z   - Pause: keep showing the base's status but send zero until pressed again
+//   G   - Graceful exit: stop, wait until the base stands still, then release and exit
+//   PgUp/PgDn/End - Scroll back through the log / forward / back to the newest
+//   Tab - Dead-man enable key, must be held to move with --deadman. With several
+//         URLs: switch the base the keys drive, the others are held standing still
+//   Space - Emergency stop: send zero at once and keep it latched
+//   Backspace - Release the emergency stop
+//   Enter - Acknowledge a protocol version mismatch and allow moves anyway
+//   H/?   - Show/hide every key binding, movement keys are ignored while shown
+//   ESC/C - Exit (with --exit-hold-ms, C has to be held; with --confirm-exit, pressed twice)
+//
+// With `--input gamepad` (build with `--features gilrs`) the left stick moves,
+// the right stick rotates and the south face button exits.
+//
+// With `--headless`, or when stdout isn't a terminal, a status line is printed
+// every second instead of drawing the UI. Ctrl-C exits. `--json` is headless
+// too, with a JSON object per BaseStatus on stdout and everything else on stderr.
 // ============================================================================
 
-use clap::Parser;
-use futures_util::{SinkExt, StreamExt};
-use prost::Message;
+use clap::{Parser, ValueEnum};
+use crossterm::event::KeyCode;
+use log::warn;
+use std::collections::HashMap;
+use std::io::IsTerminal;
 use std::sync::Arc;
 use std::sync::Mutex;
-use tokio_tungstenite::MaybeTlsStream;
-
-const ACCEPTABLE_PROTOCOL_MAJOR_VERSION: u32 = 1;
+use std::time::{Duration, Instant};
 
-// Import our UI and keyboard modules
-#[path = "lib/keyboard_input.rs"]
-mod keyboard_input;
-#[path = "lib/robot_ui.rs"]
-mod robot_ui;
-
-use crate::keyboard_input::{KeyboardInput, SpeedData};
-use crate::robot_ui::{ControlState, ErrorMessage, RobotUi};
+use robot_examples::accel_limit::AccelLimits;
+use robot_examples::base_backend;
+use robot_examples::breakaway::{AxisBoost, Breakaway};
+use robot_examples::camera::CameraMount;
+use robot_examples::client::{
+    create_move_msg, move_speed, report_frequency_from_hz, SpeedLimit, DEFAULT_MAX_ANGULAR,
+    DEFAULT_MAX_LINEAR, SUPPORTED_REPORT_HZ,
+};
+use robot_examples::config::{Config, Settings, StartupOverrides, StartupSettings};
+use robot_examples::connection::{
+    describe_connect_error, Command, Drive, Endpoint, LinkSettings, RobotConnection,
+};
+use robot_examples::headless::HeadlessUi;
+use robot_examples::input_source::InputSource;
+use robot_examples::keyboard_input::{
+    AxisInvert, KeyBindings, KeyboardInput, KeyboardOptions, RampConfig, SpeedData, SpeedModifiers,
+    SpeedSteps, DEFAULT_CONFIRM_EXIT_WINDOW, DEFAULT_FIRST_PRESS_TIMEOUT, DEFAULT_MAX_KEY_HOLD,
+    DEFAULT_RELEASE_TIMEOUT,
+};
+use robot_examples::kinematics::BaseType;
+use robot_examples::leader::{spawn_leader_receiver, LeaderState};
+use robot_examples::lock_recover::{recovered_from_poison, LockRecover};
+use robot_examples::log_buffer::{LogBuffer, DEFAULT_LOG_CAPACITY};
+use robot_examples::mirror::spawn_mirror;
+use robot_examples::motion_queue::{MotionQueue, Step};
+use robot_examples::odometry_csv::OdometryLogger;
+use robot_examples::protocol::{AcceptedVersions, DEFAULT_ACCEPTABLE_PROTOCOL_MAJOR_VERSIONS};
+use robot_examples::receiver::{
+    process_base_status, MissingOdometry, ReceiverOptions, Recording, SharedState,
+};
+use robot_examples::robot_ui::{
+    terminal_supports_color, BaseSummary, ControlState, ErrorMessage, ErrorSeverity,
+    FollowIndicator, RobotUi, Takeover, ThemeName, Ui, UiView,
+};
+use robot_examples::script::ScriptRunner;
+use robot_examples::session_log::{load_replay, SessionRecorder};
+use robot_examples::session_state::{
+    load_session_state, save_session_state, session_state_path, SessionState,
+};
+use robot_examples::smoothing::{SmoothingProfile, SpeedFilter};
+use robot_examples::speed_history::{SpeedHistory, DEFAULT_SPEED_HISTORY_WINDOW};
+use robot_examples::status_json::StatusJsonWriter;
+use robot_examples::status_line::{format_status_line, StatusLineFile};
+use robot_examples::units::UnitSystem;
 
 #[derive(Parser)]
 struct Args {
-    #[arg(help = "WebSocket URL to connect to (e.g. ws://localhost:8439)")]
-    url: String,
+    #[arg(
+        required_unless_present = "preview",
+        help = "WebSocket URLs to connect to (e.g. ws://localhost:8439), wss:// connects over TLS (build with --features tls). With several, Tab switches the base the keys drive"
+    )]
+    urls: Vec<String>,
+    #[arg(
+        long = "accept-protocol",
+        default_value = DEFAULT_ACCEPTABLE_PROTOCOL_MAJOR_VERSIONS,
+        help = "Accepted protocol major versions, as a list of versions or ranges (e.g. 1,2 or 1-3)"
+    )]
+    accepted_protocol_versions: AcceptedVersions,
+    #[arg(
+        long,
+        default_value_t = 0.5,
+        help = "Distance in meters covered by one queued grid step (I/K keys)"
+    )]
+    grid_step: f32,
+    #[arg(
+        long,
+        default_value_t = 0.2,
+        help = "Lateral distance in meters covered by one queued strafe (U/O keys)"
+    )]
+    strafe_distance: f32,
+    #[arg(
+        long,
+        default_value_t = 3000,
+        help = "Warn if no BaseStatus arrives within this many milliseconds after sending init"
+    )]
+    init_status_timeout_ms: u64,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = MissingOdometry::Clear,
+        help = "What the Actual Speed panel shows when we have control but a status frame carries no odometry"
+    )]
+    missing_odometry: MissingOdometry,
+    #[arg(
+        long,
+        default_value_t = 1000,
+        help = "On exit, wait up to this many milliseconds for the base to confirm releasing control"
+    )]
+    close_timeout_ms: u64,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = SmoothingProfile::None,
+        help = "Initial smoothing applied to the commanded speed (F cycles at runtime)"
+    )]
+    smoothing: SmoothingProfile,
+    #[arg(
+        long,
+        help = "Verbose: write every outgoing command to the log pane (repeats are throttled)"
+    )]
+    log_commands: bool,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_LINEAR,
+        help = "Hard ceiling on the X/Y speed of every move command sent, in m/s"
+    )]
+    max_linear: f32,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_ANGULAR,
+        help = "Hard ceiling on the rotation speed of every move command sent, in rad/s"
+    )]
+    max_angular: f32,
+    #[arg(
+        long,
+        value_enum,
+        help = "Base wheel layout; when given, the estimated wheel speeds of each command are shown"
+    )]
+    base_type: Option<BaseType>,
+    #[arg(
+        long,
+        default_value_t = 0.05,
+        help = "Wheel radius in meters, for the wheel speed display"
+    )]
+    wheel_radius: f32,
+    #[arg(
+        long,
+        default_value_t = 0.3,
+        help = "Distance between left and right wheels in meters, for the wheel speed display"
+    )]
+    track_width: f32,
+    #[arg(
+        long,
+        default_value_t = 0.3,
+        help = "Distance between front and rear axles in meters (mecanum), for the wheel speed display"
+    )]
+    wheel_base: f32,
+    #[arg(
+        long,
+        help = "Debug: log the length and a truncated hex dump of frames that fail to decode"
+    )]
+    debug_frames: bool,
+    #[arg(
+        long,
+        help = "Don't use colors, show state with text markers and bold/reversed/underline only (implied by NO_COLOR or TERM=dumb)"
+    )]
+    no_color: bool,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = UnitSystem::Si,
+        help = "Units of the speed panels, control stays in m/s and rad/s"
+    )]
+    units: UnitSystem,
+    #[arg(long, help = "Show the UI draw rate in the top right corner")]
+    show_fps: bool,
+    #[arg(
+        long,
+        help = "Print status lines instead of the terminal UI, the default when stdout isn't a terminal"
+    )]
+    headless: bool,
+    #[arg(
+        long,
+        help = "Headless, printing one JSON object per BaseStatus to stdout. Status lines and messages go to stderr."
+    )]
+    json: bool,
+    #[arg(
+        long,
+        default_value_t = 0.01,
+        help = "Graceful exit: speed (per axis, m/s or rad/s) below which the base counts as stopped"
+    )]
+    stop_tolerance: f32,
+    #[arg(
+        long,
+        default_value_t = 3000,
+        help = "Graceful exit: give up waiting for the base to stop after this many milliseconds"
+    )]
+    stop_timeout_ms: u64,
+    #[arg(
+        long,
+        help = "TOML file with startup values and named settings profiles, P switches between profiles"
+    )]
+    config: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Neither restore nor save the key speeds and theme of the last session"
+    )]
+    no_session_state: bool,
+    #[arg(
+        long,
+        help = "Profile to start with, instead of the config's default_profile"
+    )]
+    profile: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = CameraMount::Front,
+        help = "Degrees counter-clockwise the camera is turned from the base's forward axis; movement keys follow the camera view (M cycles)"
+    )]
+    camera: CameraMount,
+    #[arg(
+        long,
+        help = "Also forward every received telemetry frame unmodified to this WebSocket URL (best effort)"
+    )]
+    mirror: Option<String>,
+    #[arg(
+        long,
+        default_value = "0:0",
+        help = "Breakaway boost for X as THRESHOLD:MINIMUM m/s: nonzero commands below the threshold are sent as the minimum"
+    )]
+    breakaway_x: AxisBoost,
+    #[arg(
+        long,
+        default_value = "0:0",
+        help = "Breakaway boost for Y as THRESHOLD:MINIMUM m/s"
+    )]
+    breakaway_y: AxisBoost,
+    #[arg(
+        long,
+        default_value = "0:0",
+        help = "Breakaway boost for Z as THRESHOLD:MINIMUM rad/s"
+    )]
+    breakaway_z: AxisBoost,
+    #[arg(
+        long,
+        help = "Keep a one line status summary (state, speeds, estop) in this file, e.g. for a tmux status bar"
+    )]
+    status_line_file: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Hold-to-exit: C must be held this many milliseconds before exiting, releasing early cancels"
+    )]
+    exit_hold_ms: Option<u64>,
+    #[arg(
+        long,
+        conflicts_with = "exit_hold_ms",
+        help = "Exiting takes a second press of C within 2 seconds, the first press stops the robot"
+    )]
+    confirm_exit: bool,
+    #[arg(
+        long,
+        help = "Dead-man switch: only move while Tab is held with the movement keys, releasing it stops at once"
+    )]
+    deadman: bool,
+    #[arg(
+        long,
+        help = "Also show each speed as a percentage of the current maximum for its axis"
+    )]
+    show_percent: bool,
+    #[arg(
+        long,
+        help = "Preview the UI without a robot, cycling through synthetic states (estop, no control, moving, ...)"
+    )]
+    preview: bool,
+    #[arg(
+        long,
+        default_value_t = 3000,
+        help = "Preview: milliseconds each state is shown"
+    )]
+    preview_scene_ms: u64,
+    #[arg(
+        long,
+        help = "Preview: exit after this many milliseconds instead of waiting for a key"
+    )]
+    preview_duration_ms: Option<u64>,
+    #[arg(
+        long,
+        help = "Hard limit on X/Y acceleration of the commands sent, m/s^2"
+    )]
+    max_linear_accel: Option<f32>,
+    #[arg(
+        long,
+        help = "Hard limit on X/Y deceleration of the commands sent, m/s^2"
+    )]
+    max_linear_decel: Option<f32>,
+    #[arg(
+        long,
+        help = "Hard limit on Z acceleration of the commands sent, rad/s^2"
+    )]
+    max_angular_accel: Option<f32>,
+    #[arg(
+        long,
+        help = "Hard limit on Z deceleration of the commands sent, rad/s^2"
+    )]
+    max_angular_decel: Option<f32>,
+    #[arg(
+        long,
+        help = "Follow-leader: read another robot's telemetry from this WebSocket URL (read-only), B follows its velocity"
+    )]
+    follow: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        help = "Follow-leader: factor applied to the leader's velocity"
+    )]
+    follow_scale: f32,
+    #[arg(
+        long,
+        default_value = "0,0,0",
+        value_parser = parse_speed,
+        help = "Follow-leader: X,Y,Z added to the scaled leader velocity"
+    )]
+    follow_offset: SpeedData,
+    #[arg(
+        long,
+        help = "Dim the UI after this many milliseconds without input or estop, e.g. for kiosk displays"
+    )]
+    dim_after_ms: Option<u64>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = InputKind::Keyboard,
+        help = "Where the movement input comes from; the gamepad needs a build with the gilrs feature"
+    )]
+    input: InputKind,
+    #[arg(
+        long,
+        help = "Ramp the movement keys up at this many m/s^2 instead of jumping to full speed"
+    )]
+    key_accel: Option<f32>,
+    #[arg(
+        long,
+        help = "Ramp the movement keys down at this many m/s^2 on release instead of stopping at once"
+    )]
+    key_decel: Option<f32>,
+    #[arg(
+        long,
+        default_value_t = 2.0,
+        help = "Speed multiplier while Shift is held with the movement keys"
+    )]
+    turbo_multiplier: f32,
+    #[arg(
+        long,
+        default_value_t = 0.25,
+        help = "Speed multiplier while Ctrl is held with the movement keys"
+    )]
+    precision_multiplier: f32,
+    #[arg(
+        long,
+        help = "Speed of the X/Y movement keys in m/s [default: 0.1, or linear_speed from --config]"
+    )]
+    linear_speed: Option<f32>,
+    #[arg(
+        long,
+        help = "Speed of the rotation keys in rad/s [default: 0.5, or angular_speed from --config]"
+    )]
+    angular_speed: Option<f32>,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "A held key counts as released after this many milliseconds without a repeat event; below the OS key repeat interval motion stutters [default: 100]"
+    )]
+    release_timeout_ms: Option<u64>,
+    #[arg(
+        long,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Like --release-timeout-ms, before a new key's first repeat; below the OS key repeat delay motion stutters after pressing [default: 500]"
+    )]
+    first_press_timeout_ms: Option<u64>,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_MAX_KEY_HOLD.as_millis() as u64,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "A key held this many milliseconds is released as stuck and has to be pressed again, in case the terminal lost its release"
+    )]
+    max_key_hold_ms: u64,
+    #[arg(long, value_enum, help = "UI color scheme [default: dark]")]
+    theme: Option<ThemeName>,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_LOG_CAPACITY,
+        help = "Number of lines kept in the log panel, PgUp/PgDn scroll through them"
+    )]
+    log_lines: usize,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_SPEED_HISTORY_WINDOW.as_secs_f32(),
+        help = "Seconds of actual forward speed shown in the speed history sparkline"
+    )]
+    speed_history_secs: f32,
+    #[arg(
+        long,
+        help = "Skip TLS certificate verification of wss:// URLs, for robots with self-signed certificates"
+    )]
+    insecure: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Additional root certificate (PEM) trusted for wss:// URLs"
+    )]
+    ca_cert: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        env = "ROBOT_API_TOKEN",
+        hide_env_values = true,
+        help = "Bearer token sent in the Authorization header of the WebSocket handshake"
+    )]
+    token: Option<String>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Append every message sent and received to this JSON lines file"
+    )]
+    record: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with = "preview",
+        help = "Send the outgoing messages of a --record file again with their original timing, instead of keyboard control"
+    )]
+    replay: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write commanded and actual speed of every BaseStatus to this CSV file"
+    )]
+    log_csv: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Append diagnostics (connections, control state changes, send failures) to this file"
+    )]
+    log_file: Option<std::path::PathBuf>,
+    #[arg(
+        long,
+        help = "Level of the diagnostics: error, warn, info, debug or trace [default: info]. Without --log-file they go to stderr, in headless mode only"
+    )]
+    log_level: Option<log::LevelFilter>,
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Run the maneuvers in this file (lines like 'move 0.1 0 0 2.0' or 'wait 1.0') while in control"
+    )]
+    script: Option<std::path::PathBuf>,
+    #[arg(long, help = "Negate the X speed, for a base mounted facing backward")]
+    invert_x: bool,
+    #[arg(long, help = "Negate the Y speed, swapping left and right")]
+    invert_y: bool,
+    #[arg(long, help = "Negate the Z speed, swapping the rotation directions")]
+    invert_z: bool,
+    #[arg(
+        long,
+        default_value_t = 0.3,
+        help = "Weight of each new sample in the displayed actual speed, 1 shows the raw odometry"
+    )]
+    actual_speed_alpha: f32,
+    #[arg(
+        long,
+        value_name = "HZ",
+        help = "BaseStatus report rate to request: 1, 50, 100, 250, 500 or 1000 [default: 50]"
+    )]
+    report_hz: Option<u32>,
+    #[arg(
+        long,
+        value_name = "HZ",
+        default_value_t = DEFAULT_COMMAND_HZ,
+        value_parser = clap::value_parser!(u32).range(1..=100),
+        help = "Rate move commands are sent at, independent of how often the UI redraws"
+    )]
+    command_hz: u32,
+}
+
+/// Device the operator drives with
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum InputKind {
+    /// WASD/QE on the terminal
+    Keyboard,
+    /// Left stick moves, right stick rotates, south face button exits
+    Gamepad,
 }
 
 // Speed Configuration - Modify these values to change robot speed
@@ -49,296 +549,1444 @@ const LINEAR_SPEED: f32 = 0.1; // m/s
 /// Angular speed for Z axis (rotation)
 const ANGULAR_SPEED: f32 = 0.5; // rad/s
 
-pub mod base_backend {
-    include!(concat!(env!("OUT_DIR"), "/_.rs"));
-}
+/// BaseStatus report rate requested when neither the command line nor --config sets one
+const DEFAULT_REPORT_FREQUENCY_HZ: u32 = 50;
+
+/// Move commands per second when --command-hz isn't given
+const DEFAULT_COMMAND_HZ: u32 = 20;
+
+/// Interval of the loop reading input and drawing the UI
+const UI_TICK: Duration = Duration::from_millis(10);
+
+/// Log entries moved per PgUp/PgDn
+const LOG_SCROLL_STEP: usize = 5;
+
+/// A takeover prompt not confirmed within this time is dropped
+const TAKEOVER_CONFIRM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A takeover request still not answered with control after this long failed
+const TAKEOVER_TIMEOUT: Duration = Duration::from_secs(3);
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
 
-    // Initialize UI and keyboard input
-    let mut ui = RobotUi::new().expect("Failed to initialize UI");
-    let keyboard = KeyboardInput::new(LINEAR_SPEED, ANGULAR_SPEED)
-        .expect("Failed to initialize keyboard input");
+    let config = match &args.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return;
+            }
+        },
+        None => Config::default(),
+    };
+    // The last session's choices replace the built-in defaults only
+    let session_state_path = session_state_path().filter(|_| !args.no_session_state);
+    let session = session_state_path
+        .as_deref()
+        .map(load_session_state)
+        .unwrap_or_default();
+    let startup = config.merge(
+        StartupSettings {
+            linear_speed: session.linear_speed.unwrap_or(LINEAR_SPEED),
+            angular_speed: session.angular_speed.unwrap_or(ANGULAR_SPEED),
+            report_frequency: DEFAULT_REPORT_FREQUENCY_HZ,
+            release_timeout_ms: DEFAULT_RELEASE_TIMEOUT.as_millis() as u64,
+            first_press_timeout_ms: DEFAULT_FIRST_PRESS_TIMEOUT.as_millis() as u64,
+            theme: session.theme.unwrap_or_default(),
+        },
+        StartupOverrides {
+            linear_speed: args.linear_speed,
+            angular_speed: args.angular_speed,
+            report_frequency: args.report_hz,
+            release_timeout_ms: args.release_timeout_ms,
+            first_press_timeout_ms: args.first_press_timeout_ms,
+            theme: args.theme,
+        },
+    );
+    let Some(report_frequency) = report_frequency_from_hz(startup.report_frequency) else {
+        let supported: Vec<String> = SUPPORTED_REPORT_HZ.iter().map(u32::to_string).collect();
+        eprintln!(
+            "Error: unsupported report frequency {} Hz, the robot supports {}",
+            startup.report_frequency,
+            supported.join(", ")
+        );
+        return;
+    };
+
+    if !(args.speed_history_secs > 0.0 && args.speed_history_secs.is_finite()) {
+        eprintln!("Error: --speed-history-secs must be a positive number of seconds");
+        return;
+    }
+    if !(args.actual_speed_alpha > 0.0 && args.actual_speed_alpha <= 1.0) {
+        eprintln!("Error: --actual-speed-alpha must be greater than 0 and at most 1");
+        return;
+    }
+    let valid_limit = |limit: f32| limit >= 0.0 && limit.is_finite();
+    if !(valid_limit(args.max_linear) && valid_limit(args.max_angular)) {
+        eprintln!("Error: --max-linear and --max-angular must be non-negative numbers");
+        return;
+    }
+    let speed_limit = SpeedLimit {
+        max_linear: args.max_linear,
+        max_angular: args.max_angular,
+    };
+    if args.urls.len() > 1 && (args.deadman || args.replay.is_some()) {
+        eprintln!(
+            "Error: --deadman and --replay take a single URL, Tab switches between several bases"
+        );
+        return;
+    }
+    // Parsed before the UI is up, so a bad line is reported right away
+    let mut script = match &args.script {
+        Some(path) => match ScriptRunner::load(path) {
+            Ok(runner) => Some(runner),
+            Err(e) => {
+                eprintln!("Error: script {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // Settings from the command line and config file, which the active profile builds on
+    let base_settings = Settings {
+        linear_speed: startup.linear_speed,
+        angular_speed: startup.angular_speed,
+        grid_step: args.grid_step,
+        strafe_distance: args.strafe_distance,
+        smoothing: args.smoothing,
+        base_type: args.base_type,
+        wheel_radius: args.wheel_radius,
+        track_width: args.track_width,
+        wheel_base: args.wheel_base,
+    };
+    let mut active_profile = args.profile.clone().or(config.default_profile.clone());
+    let mut settings = match &active_profile {
+        Some(name) => match config.profile(name) {
+            Ok(profile) => profile.apply(&base_settings),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return;
+            }
+        },
+        None => base_settings,
+    };
+
+    // Without a terminal there is nothing to draw the UI on
+    let headless = args.json || args.headless || !std::io::stdout().is_terminal();
+    if let Err(e) = init_logging(args.log_file.as_deref(), args.log_level, headless) {
+        eprintln!("Error: {}", e);
+        return;
+    }
+
+    // Initialize UI and input
+    let mut ui: Box<dyn Ui> = if args.json {
+        // Keep stdout for the JSON lines only
+        Box::new(HeadlessUi::new(std::io::stderr()))
+    } else if headless {
+        Box::new(HeadlessUi::stdout())
+    } else {
+        let mut ui = RobotUi::new(startup.theme.theme()).expect("Failed to initialize UI");
+        ui.set_show_fps(args.show_fps);
+        Box::new(ui)
+    };
+    let invert = AxisInvert {
+        x: args.invert_x,
+        y: args.invert_y,
+        z: args.invert_z,
+    };
+    let bindings = KeyBindings::default();
+    let keyboard: Box<dyn InputSource> = match args.input {
+        InputKind::Keyboard => match KeyboardInput::with_options(
+            settings.linear_speed,
+            settings.angular_speed,
+            KeyboardOptions {
+                bindings,
+                ramp: RampConfig {
+                    accel: args.key_accel,
+                    decel: args.key_decel,
+                },
+                modifiers: SpeedModifiers {
+                    turbo: args.turbo_multiplier,
+                    precision: args.precision_multiplier,
+                },
+                exit_hold: args.exit_hold_ms.map(Duration::from_millis),
+                confirm_exit: args.confirm_exit.then_some(DEFAULT_CONFIRM_EXIT_WINDOW),
+                release_timeout: Duration::from_millis(startup.release_timeout_ms),
+                first_press_timeout: Duration::from_millis(startup.first_press_timeout_ms),
+                max_hold: Some(Duration::from_millis(args.max_key_hold_ms)),
+                invert,
+                require_deadman: args.deadman,
+                // The speed keys stop at the speed limit, moves above it would be capped anyway
+                speed_steps: SpeedSteps {
+                    max_linear: args.max_linear,
+                    max_angular: args.max_angular,
+                    ..Default::default()
+                },
+            },
+        ) {
+            Ok(keyboard) => Box::new(keyboard),
+            Err(e) => {
+                drop(ui);
+                eprintln!("Error: failed to initialize keyboard input: {}", e);
+                return;
+            }
+        },
+        #[cfg(feature = "gilrs")]
+        InputKind::Gamepad => {
+            match robot_examples::gamepad_input::GamepadInput::new(
+                settings.linear_speed,
+                settings.angular_speed,
+                invert,
+            ) {
+                Ok(gamepad) => Box::new(gamepad),
+                Err(e) => {
+                    drop(ui);
+                    eprintln!("Error: failed to initialize gamepad input: {}", e);
+                    return;
+                }
+            }
+        }
+        #[cfg(not(feature = "gilrs"))]
+        InputKind::Gamepad => {
+            drop(ui);
+            eprintln!("Error: built without gamepad support, rebuild with --features gilrs");
+            return;
+        }
+    };
+
+    // Log entries scrolled back past with PgUp, 0 follows new entries
+    let mut log_scroll = 0;
 
-    // Connect to WebSocket
-    let res = tokio_tungstenite::connect_async(&args.url).await;
-    let ws_stream = match res {
-        Ok((ws, _)) => ws,
+    let is_monochrome = |theme: ThemeName| {
+        args.no_color || theme == ThemeName::Monochrome || !terminal_supports_color()
+    };
+    let mut monochrome = is_monochrome(startup.theme);
+
+    if args.urls.is_empty() {
+        run_preview(&args, &settings, monochrome, ui.as_mut(), keyboard.as_ref()).await;
+        return;
+    }
+    let endpoints: Result<Vec<Endpoint>, String> = args
+        .urls
+        .iter()
+        .map(|url| {
+            Endpoint::new(url.clone(), args.insecure, args.ca_cert.as_deref())
+                .and_then(|endpoint| endpoint.with_token(args.token.as_deref()))
+        })
+        .collect();
+    let mut endpoints = match endpoints {
+        Ok(endpoints) => endpoints.into_iter(),
         Err(e) => {
-            ui.cleanup().ok();
-            eprintln!("Error during websocket handshake: {}", e);
+            drop(ui);
+            eprintln!("Error: {}", e);
+            return;
+        }
+    };
+    let replay = match args.replay.as_deref().map(load_replay).transpose() {
+        Ok(replay) => replay,
+        Err(e) => {
+            drop(ui);
+            eprintln!("Error: {}", e);
             return;
         }
     };
 
-    // Set TCP nodelay for better performance
-    if let MaybeTlsStream::Plain(stream) = ws_stream.get_ref() {
-        stream.set_nodelay(true).unwrap();
+    //Initialize shared state, one per base
+    let new_shared_state = || {
+        let shared = SharedState::default();
+        *shared.log.lock_recover() = LogBuffer::new(args.log_lines);
+        *shared.displayed_speed.lock_recover() = SpeedFilter::new(args.actual_speed_alpha);
+        *shared.speed_history.lock_recover() = SpeedHistory::new(
+            Duration::from_secs_f32(args.speed_history_secs),
+            startup.report_frequency,
+        );
+        shared
+    };
+    let shared = new_shared_state();
+    if let Some(runner) = &script {
+        shared
+            .log
+            .lock_recover()
+            .push(format!("Script loaded, {} steps", runner.step_count()));
+    }
+    let recording = match &args.record {
+        Some(path) => match SessionRecorder::create(path) {
+            Ok(recorder) => Some(Recording::new(recorder, shared.log.clone())),
+            Err(e) => {
+                drop(ui);
+                eprintln!("Error: can't record to {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+    let odometry_csv = match &args.log_csv {
+        Some(path) => match OdometryLogger::create(path) {
+            Ok(logger) => Some(Arc::new(Mutex::new(logger))),
+            Err(e) => {
+                drop(ui);
+                eprintln!("Error: can't write {}: {}", path.display(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+    let receiver_options = ReceiverOptions {
+        accepted_protocol_versions: args.accepted_protocol_versions.clone(),
+        missing_odometry: args.missing_odometry,
+        debug_frames: args.debug_frames,
+        mirror: args
+            .mirror
+            .clone()
+            .map(|url| spawn_mirror(url, shared.log.clone())),
+        recording,
+        odometry_csv: odometry_csv.clone(),
+        status_json: args
+            .json
+            .then(|| Arc::new(Mutex::new(StatusJsonWriter::stdout()))),
+    };
+
+    let link_settings = LinkSettings {
+        report_frequency,
+        log_commands: args.log_commands,
+        speed_limit,
+        breakaway: Breakaway {
+            x: args.breakaway_x,
+            y: args.breakaway_y,
+            z: args.breakaway_z,
+        },
+        accel_limits: AccelLimits {
+            linear_accel: args.max_linear_accel,
+            linear_decel: args.max_linear_decel,
+            angular_accel: args.max_angular_accel,
+            angular_decel: args.max_angular_decel,
+        },
+        init_status_timeout: Duration::from_millis(args.init_status_timeout_ms),
+    };
+
+    // Connect to WebSocket. A failure stays on screen until the operator
+    // retries or quits, robots can take a while to boot.
+    let first = endpoints.next().expect("at least one URL");
+    let Some(connection) = connect_with_retry(
+        &first,
+        &shared,
+        &receiver_options,
+        link_settings,
+        ui.as_mut(),
+        keyboard.as_ref(),
+    )
+    .await
+    else {
+        return;
+    };
+
+    if let Some(messages) = &replay {
+        run_replay(
+            messages,
+            connection,
+            &args,
+            &settings,
+            monochrome,
+            ui.as_mut(),
+            keyboard.as_ref(),
+        )
+        .await;
+        flush_odometry_csv(&odometry_csv);
+        return;
     }
-    let (mut ws_sink, ws_stream) = ws_stream.split();
-
-    //Initialize shared state
-    let control_state = Arc::new(Mutex::new(ControlState::Uninitialized));
-    let odometry_data = Arc::new(Mutex::new(None));
-    let emergency_stop = Arc::new(Mutex::new(false));
-    let error_message = Arc::new(Mutex::new(ErrorMessage::default()));
-
-    //Spawn WebSocket receiver task
-    spawn_websocket_receiver(
-        ws_stream,
-        control_state.clone(),
-        odometry_data.clone(),
-        emergency_stop.clone(),
-        error_message.clone(),
-    );
 
-    //Spawn Ctrl-C handler
+    // Every base of the session, the keys drive the active one. The outputs
+    // describing a single base's frames (--record, --mirror, --log-csv and
+    // --json) are only fed by the first.
+    let mut robots = vec![connection];
+    for endpoint in endpoints {
+        let Some(connection) = connect_with_retry(
+            &endpoint,
+            &new_shared_state(),
+            &receiver_options.without_outputs(),
+            link_settings,
+            ui.as_mut(),
+            keyboard.as_ref(),
+        )
+        .await
+        else {
+            return;
+        };
+        robots.push(connection);
+    }
+    let base_count = robots.len();
+    let mut active = 0;
+    // Set by Tab, the switch happens at the start of the next tick
+    let mut switch_requested = false;
+
+    // Ctrl-C only asks the main loop to exit, which releases control and
+    // restores the terminal on the way out like any other exit
     let keyboard_clone = Arc::new(keyboard);
+    let interrupted = Arc::new(Mutex::new(false));
+    let interrupted_clone = interrupted.clone();
     tokio::spawn(async move {
         tokio::signal::ctrl_c().await.ok();
-        std::process::exit(0);
+        *interrupted_clone.lock_recover() = true;
     });
 
+    // Discrete moves queued with the grid step keys, executed one at a time
+    let mut motion_queue = MotionQueue::new(settings.linear_speed, settings.angular_speed);
+
+    // Set by the recover key, handled once in the next loop iteration
+    let mut recover_requested = false;
+
+    // Taking control from another session: the first T press asks, a second one
+    // within TAKEOVER_CONFIRM_TIMEOUT sets takeover_requested to send it
+    let mut takeover: Option<(Takeover, Instant)> = None;
+    let mut takeover_requested = false;
+
+    // Commanded speed after smoothing, follows the keyboard target
+    let mut smoothing = settings.smoothing;
+    let mut smoothed_speed = SpeedData::ZERO;
+    let mut last_tick = Instant::now();
+    let mut had_conflicting_keys = false;
+    let mut had_operator_stop = false;
+    let mut reported_poison = false;
+
+    // Velocity latched by cruise control, commanded while no movement key is held
+    let mut cruise: Option<SpeedData> = None;
+
+    // Set by the graceful exit key: zero is commanded from then on until the
+    // base stands still or the stop timeout passes, then we exit
+    let mut graceful_stop_at: Option<Instant> = None;
+    let stop_timeout = Duration::from_millis(args.stop_timeout_ms);
+
+    // The last speed sent is shown as wheel speeds if the geometry is known
+    let mut geometry = settings.geometry();
+
+    // Movement input is relative to this camera view
+    let mut camera = args.camera;
+
+    // Only a theme picked with the theme key is saved for the next session,
+    // not one given on the command line
+    let mut theme = startup.theme;
+    let mut chosen_theme: Option<ThemeName> = None;
+
+    // Follow-leader mode: the leader's telemetry is read on its own connection
+    let leader = args.follow.clone().map(|url| {
+        let leader = Arc::new(Mutex::new(LeaderState::default()));
+        spawn_leader_receiver(url, leader.clone(), shared.log.clone());
+        leader
+    });
+    let mut following = false;
+
+    // Kiosk displays dim after a while without input; an estop counts as activity
+    let dim_after = args.dim_after_ms.map(Duration::from_millis);
+    let mut last_activity = Instant::now();
+    let mut show_help = false;
+
+    // One line summary for status bars, and whether writing it is failing
+    let mut status_line = args.status_line_file.clone().map(StatusLineFile::new);
+    let mut status_line_failing = false;
+
+    // Commands go out on their own interval, the UI draws every tick in between
+    let mut command_interval = tokio::time::interval(Duration::from_secs(1) / args.command_hz);
+    command_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+    // Spans the control loop; main isn't spawned, so the guard stays on its thread
+    #[cfg(feature = "tracing")]
+    let _command_loop = tracing::info_span!("command_loop", bases = robots.len()).entered();
     loop {
-        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let command_tick = tokio::select! {
+            biased;
+            _ = command_interval.tick() => true,
+            _ = tokio::time::sleep(UI_TICK) => false,
+        };
+
+        // Everything driving the base is reset, the next one starts from standstill
+        if std::mem::take(&mut switch_requested) {
+            active = (active + 1) % base_count;
+            motion_queue.abort();
+            cruise = None;
+            following = false;
+            smoothed_speed = SpeedData::ZERO;
+            takeover = None;
+            takeover_requested = false;
+            recover_requested = false;
+            log_scroll = 0;
+            let robot = &mut robots[active];
+            robot.reset_drive();
+            let message = format!(
+                "Driving base {} of {}: {}",
+                active + 1,
+                base_count,
+                robot.url()
+            );
+            robot.shared().log.lock_recover().push(message.clone());
+            *robot.shared().error_message.lock_recover() =
+                ErrorMessage::with_severity(message, ErrorSeverity::Info);
+        }
+        for (index, robot) in robots.iter_mut().enumerate() {
+            if index != active && command_tick {
+                robot.idle().await;
+            }
+        }
+        let bases: Vec<BaseSummary> = if base_count > 1 {
+            robots
+                .iter()
+                .enumerate()
+                .map(|(index, robot)| BaseSummary {
+                    url: robot.url().to_string(),
+                    control_state: *robot.shared().control_state.lock_recover(),
+                    emergency_stop: *robot.shared().emergency_stop.lock_recover(),
+                    actual_speed: robot.shared().displayed_speed.lock_recover().value(),
+                    active: index == active,
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let robot = &mut robots[active];
+        let shared = robot.shared();
 
         // Get current state
-        let current_state = *control_state.lock().unwrap();
-        let target_speed = keyboard_clone.get_speed();
-        let actual_speed = *odometry_data.lock().unwrap();
-        let pressed_keys = keyboard_clone.get_pressed_keys();
-        let error_msg = error_message.lock().unwrap().clone();
-        let emergency = *emergency_stop.lock().unwrap();
+        let current_state = *shared.control_state.lock_recover();
+        let input = keyboard_clone.snapshot();
+        // Nobody drives blind behind the help overlay
+        let target_speed = if show_help {
+            SpeedData::ZERO
+        } else {
+            camera.to_base_frame(input.speed)
+        };
+        let actual_speed = *shared.odometry_data.lock_recover();
+        let displayed_speed = shared.displayed_speed.lock_recover().value();
+        let actual_speed_stale = *shared.odometry_stale.lock_recover();
+        let pressed_keys = input.pressed_keys;
+        let error_msg = shared.error_message.lock_recover().clone();
+        // The operator's latched stop is handled like one reported by the base
+        let operator_stop = input.emergency;
+        let emergency = *shared.emergency_stop.lock_recover() || operator_stop;
+        // The zero of a fresh stop goes out right away, not on the next command tick
+        let operator_stop_latched = operator_stop && !had_operator_stop;
+        if operator_stop != had_operator_stop {
+            let message = if operator_stop {
+                "Operator stop latched, Backspace releases it"
+            } else {
+                "Operator stop released"
+            };
+            shared.log.lock_recover().push(message);
+            *shared.error_message.lock_recover() = ErrorMessage::new(message.to_string());
+        }
+        had_operator_stop = operator_stop;
+        if !reported_poison && recovered_from_poison() {
+            reported_poison = true;
+            let message = "A thread panicked, running degraded, restart when convenient";
+            shared.log.lock_recover().push(message);
+            *shared.error_message.lock_recover() =
+                ErrorMessage::with_severity(message.to_string(), ErrorSeverity::Error);
+        }
 
-        // Draw UI
-        let _ = ui
-            .draw(
-                current_state,
-                &target_speed,
-                actual_speed,
-                &pressed_keys,
-                &error_msg,
-                emergency,
-            )
-            .is_err();
+        for key in keyboard_clone.take_stuck_keys() {
+            let message = format!(
+                "Key {} held over {:?}, released it as stuck, press it again to move",
+                key,
+                Duration::from_millis(args.max_key_hold_ms)
+            );
+            shared.log.lock_recover().push(&message);
+            *shared.error_message.lock_recover() = ErrorMessage::new(message);
+        }
+        let key_presses = keyboard_clone.take_key_presses();
+        if !key_presses.is_empty() || !pressed_keys.is_empty() || emergency {
+            last_activity = Instant::now();
+        }
+        for key in key_presses {
+            if matches!(key, KeyCode::Char('h') | KeyCode::Char('?')) {
+                show_help = !show_help;
+                continue;
+            }
+            if show_help {
+                continue;
+            }
+            // Any other key cancels a pending confirmation
+            if key != KeyCode::Char('t') && matches!(takeover, Some((Takeover::Confirm, _))) {
+                takeover = None;
+                *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+                    "Takeover cancelled".to_string(),
+                    ErrorSeverity::Info,
+                );
+            }
+            match key {
+                KeyCode::Char('i') => motion_queue.push(Step::Forward(settings.grid_step)),
+                KeyCode::Char('k') => motion_queue.push(Step::Backward(settings.grid_step)),
+                KeyCode::Char('j') => {
+                    motion_queue.push(Step::TurnLeft(std::f32::consts::FRAC_PI_2))
+                }
+                KeyCode::Char('l') => {
+                    motion_queue.push(Step::TurnRight(std::f32::consts::FRAC_PI_2))
+                }
+                KeyCode::Char('u') => motion_queue.push(Step::StrafeLeft(settings.strafe_distance)),
+                KeyCode::Char('o') => {
+                    motion_queue.push(Step::StrafeRight(settings.strafe_distance))
+                }
+                KeyCode::Char('x') => {
+                    motion_queue.abort();
+                    if cruise.take().is_some() {
+                        smoothed_speed = SpeedData::ZERO;
+                        *shared.error_message.lock_recover() =
+                            ErrorMessage::new("Cruise disengaged".to_string());
+                    }
+                }
+                KeyCode::Char('0') => shared.odometer.lock_recover().reset(),
+                KeyCode::PageUp => {
+                    let entries = shared.log.lock_recover().len();
+                    log_scroll = (log_scroll + LOG_SCROLL_STEP).min(entries.saturating_sub(1));
+                }
+                KeyCode::PageDown => log_scroll = log_scroll.saturating_sub(LOG_SCROLL_STEP),
+                KeyCode::End => log_scroll = 0,
+                KeyCode::Char('r') => recover_requested = true,
+                KeyCode::Char('t') => {
+                    let message = match takeover {
+                        _ if current_state != ControlState::InitializedButNotHold => {
+                            "No other session holds control"
+                        }
+                        Some((Takeover::Confirm, _)) => {
+                            takeover_requested = true;
+                            "Taking over control..."
+                        }
+                        Some((Takeover::Requested, _)) => "Takeover already requested",
+                        None => {
+                            takeover = Some((Takeover::Confirm, Instant::now()));
+                            "Take control from the other session? [T] again to confirm"
+                        }
+                    };
+                    *shared.error_message.lock_recover() = ErrorMessage::new(message.to_string());
+                }
+                KeyCode::Enter if shared.protocol.lock_recover().blocked => {
+                    shared.protocol.lock_recover().acknowledge();
+                    let message = "Protocol mismatch acknowledged, moves enabled";
+                    shared.log.lock_recover().push(message);
+                    *shared.error_message.lock_recover() =
+                        ErrorMessage::with_severity(message.to_string(), ErrorSeverity::Info);
+                }
+                KeyCode::Char('f') => smoothing = smoothing.next(),
+                KeyCode::Char('m') => camera = camera.next(),
+                KeyCode::Char('n') => {
+                    theme = theme.next();
+                    chosen_theme = Some(theme);
+                    ui.set_theme(theme.theme());
+                    monochrome = is_monochrome(theme);
+                    *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+                        format!("Theme: {}", theme.name()),
+                        ErrorSeverity::Info,
+                    );
+                }
+                KeyCode::Char('p') => {
+                    let Some(name) = config.next_profile(active_profile.as_deref()) else {
+                        *shared.error_message.lock_recover() =
+                            ErrorMessage::new("No profiles, pass --config".to_string());
+                        continue;
+                    };
+                    // Stop before the speeds change under the operator's hands
+                    motion_queue.abort();
+                    cruise = None;
+                    smoothed_speed = SpeedData::ZERO;
 
-        // Check if we should exit
-        if keyboard_clone.should_exit() {
-            // Send API close command
-            let close_message = create_close_msg();
-            let close_bytes = close_message.encode_to_vec();
+                    settings = config.profiles[name].apply(&base_settings);
+                    keyboard_clone.set_speeds(settings.linear_speed, settings.angular_speed);
+                    motion_queue = MotionQueue::new(settings.linear_speed, settings.angular_speed);
+                    smoothing = settings.smoothing;
+                    geometry = settings.geometry();
+                    *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+                        format!("Profile: {}", name),
+                        ErrorSeverity::Info,
+                    );
+                    active_profile = Some(name.to_string());
+                }
+                KeyCode::Char('b') => {
+                    let message = if leader.is_none() {
+                        "No leader, pass --follow"
+                    } else if following {
+                        following = false;
+                        smoothed_speed = SpeedData::ZERO;
+                        "Follow disengaged"
+                    } else if current_state == ControlState::CanMove && !emergency {
+                        following = true;
+                        cruise = None;
+                        motion_queue.abort();
+                        "Following leader"
+                    } else {
+                        "Can't follow without control"
+                    };
+                    *shared.error_message.lock_recover() = ErrorMessage::new(message.to_string());
+                }
+                key if key == bindings.pause => {
+                    let mut paused = shared.paused.lock_recover();
+                    *paused = !*paused;
+                    // Nothing picks up where it was when resuming
+                    let message = if *paused {
+                        motion_queue.abort();
+                        cruise = None;
+                        following = false;
+                        "Paused, sending zero"
+                    } else {
+                        "Resumed"
+                    };
+                    shared.log.lock_recover().push(message);
+                    *shared.error_message.lock_recover() =
+                        ErrorMessage::with_severity(message.to_string(), ErrorSeverity::Info);
+                }
+                KeyCode::Tab if base_count > 1 => {
+                    if script.is_some() || graceful_stop_at.is_some() {
+                        *shared.error_message.lock_recover() = ErrorMessage::new(
+                            "Can't switch bases during a script or a graceful exit".to_string(),
+                        );
+                    } else {
+                        switch_requested = true;
+                    }
+                }
+                KeyCode::Char('g') if graceful_stop_at.is_none() => {
+                    graceful_stop_at = Some(Instant::now());
+                    motion_queue.abort();
+                    cruise = None;
+                    *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+                        "Stopping before release...".to_string(),
+                        ErrorSeverity::Info,
+                    );
+                }
+                KeyCode::Char('v') => {
+                    let message = if cruise.take().is_some() {
+                        "Cruise disengaged"
+                    } else if current_state == ControlState::CanMove
+                        && !smoothed_speed.is_zero(f32::EPSILON)
+                    {
+                        cruise = Some(smoothed_speed);
+                        "Cruise engaged"
+                    } else {
+                        "Cruise needs a nonzero commanded speed"
+                    };
+                    *shared.error_message.lock_recover() = ErrorMessage::new(message.to_string());
+                }
+                _ => {}
+            }
+        }
 
-            ws_sink
-                .send(tungstenite::Message::Binary(close_bytes.into()))
-                .await
-                .ok();
+        // Nothing more is sent to the base being switched away from, its idle
+        // ticks stop it from the next tick on
+        if switch_requested {
+            continue;
+        }
 
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            break;
+        // Warn once each time opposite keys of an axis start being held together
+        let conflicting_keys = keyboard_clone.has_conflicting_keys();
+        if conflicting_keys && !had_conflicting_keys {
+            *shared.error_message.lock_recover() =
+                ErrorMessage::new("Conflicting keys held, that axis is stopped".to_string());
         }
+        had_conflicting_keys = conflicting_keys;
 
-        // State machine logic - send appropriate commands based on state
-        match current_state {
-            ControlState::Uninitialized => {
-                // Set report frequency to 50Hz
-                let set_freq_msg = create_set_frequency_msg(base_backend::ReportFrequency::Rf50Hz);
-                let set_freq_bytes = set_freq_msg.encode_to_vec();
-                if ws_sink
-                    .send(tungstenite::Message::Binary(set_freq_bytes.into()))
-                    .await
-                    .is_err()
-                {
-                    break;
+        // Cruise is released by estop, losing control or input against the held velocity.
+        // Estop drops the command to zero at once instead of smoothing down to it.
+        let manual_input = !target_speed.is_zero(f32::EPSILON);
+        if let Some(held) = cruise {
+            if emergency || current_state != ControlState::CanMove || target_speed.opposes(&held) {
+                cruise = None;
+                if emergency {
+                    smoothed_speed = SpeedData::ZERO;
                 }
+                *shared.error_message.lock_recover() =
+                    ErrorMessage::new("Cruise disengaged".to_string());
+            }
+        }
+
+        // Following is broken, with an immediate stop, by any manual input,
+        // estop or losing control. A stale leader is not followed but stopped for.
+        let leader_speed = leader
+            .as_ref()
+            .and_then(|leader| leader.lock_recover().fresh_velocity());
+        if following && (manual_input || emergency || current_state != ControlState::CanMove) {
+            following = false;
+            smoothed_speed = SpeedData::ZERO;
+            *shared.error_message.lock_recover() =
+                ErrorMessage::new("Follow disengaged".to_string());
+        }
 
-                // Initialize the base API control
-                let enable_message = create_init_msg();
-                let enable_bytes = enable_message.encode_to_vec();
+        let commanded_target = match cruise {
+            _ if graceful_stop_at.is_some() => SpeedData::ZERO,
+            _ if following => leader_speed.map_or(SpeedData::ZERO, |speed| {
+                speed * args.follow_scale + args.follow_offset
+            }),
+            Some(held) if !manual_input => held,
+            _ => target_speed,
+        };
+
+        let now = Instant::now();
+        let dt = now.duration_since(last_tick).as_secs_f32();
+        last_tick = now;
+        // A terminal too small for the panels takes no new targets from the
+        // keys, the operator can't see what is commanded. The base slows down
+        // as if they were released.
+        let commanded_target = if ui.too_small() {
+            SpeedData::ZERO
+        } else {
+            commanded_target
+        };
+        let paused = *shared.paused.lock_recover();
+        // Waiting for the exit to be confirmed sends zero like a pause
+        let exit_pending = keyboard_clone.exit_confirmation_pending();
+        smoothed_speed = if current_state == ControlState::CanMove
+            && graceful_stop_at.is_none()
+            && !paused
+            && !exit_pending
+        {
+            smoothing.apply(smoothed_speed, commanded_target, dt)
+        } else {
+            SpeedData::ZERO
+        };
+
+        // Emergency stop, any manual movement or losing control aborts the whole sequence
+        let lost_control = current_state != ControlState::CanMove && motion_queue.is_running();
+        if (emergency || manual_input || lost_control) && motion_queue.abort() {
+            *shared.error_message.lock_recover() =
+                ErrorMessage::new("Motion queue aborted".to_string());
+        }
 
-                if ws_sink
-                    .send(tungstenite::Message::Binary(enable_bytes.into()))
-                    .await
-                    .is_err()
-                {
-                    break;
+        // The script only runs while we hold control, its clock stops during an
+        // estop or while control is lost. It overrides the movement keys.
+        let script_speed = match &mut script {
+            Some(runner)
+                if current_state == ControlState::CanMove
+                    && !emergency
+                    && !paused
+                    && !exit_pending
+                    && graceful_stop_at.is_none() =>
+            {
+                runner.tick(now)
+            }
+            Some(runner) => {
+                runner.pause();
+                None
+            }
+            None => None,
+        };
+        if script.as_ref().is_some_and(ScriptRunner::is_finished) {
+            script = None;
+            shared.log.lock_recover().push("Script finished");
+        }
+
+        let last_command = robot.last_command();
+        let wheel_speeds = geometry.map(|g| g.wheel_speeds(last_command));
+
+        // Draw UI
+        let _ = ui
+            .draw(&UiView {
+                control_state: current_state,
+                target_speed,
+                actual_speed: displayed_speed,
+                actual_speed_stale,
+                pressed_keys: &pressed_keys,
+                error_message: &error_msg,
+                emergency_stop: emergency,
+                motion_queue: &motion_queue,
+                odometer: *shared.odometer.lock_recover(),
+                smoothing,
+                cruise,
+                log: &shared.log.lock_recover().clone(),
+                wheel_speeds: wheel_speeds.as_deref(),
+                monochrome,
+                url: Some(robot.url()),
+                session_id: *shared.session_id.lock_recover(),
+                profile: active_profile.as_deref(),
+                camera,
+                exit_hold_progress: keyboard_clone.exit_hold_progress(),
+                speed_history: &shared.speed_history.lock_recover().clone(),
+                log_scroll,
+                latency: shared.latency.lock_recover().average(now),
+                battery: *shared.battery.lock_recover(),
+                protocol: shared
+                    .protocol
+                    .lock_recover()
+                    .indicator(&args.accepted_protocol_versions),
+                takeover: takeover.map(|(stage, _)| stage),
+                paused,
+                confirm_exit: exit_pending,
+                speed_mode: keyboard_clone.speed_mode(),
+                deadman: keyboard_clone.deadman_engaged(),
+                // The speed keys change what 100% is
+                max_speed: args
+                    .show_percent
+                    .then(|| SpeedData::from_linear_angular(keyboard_clone.speeds())),
+                follow: leader.as_ref().map(|leader| FollowIndicator {
+                    engaged: following,
+                    link: leader.lock_recover().link.describe(),
+                    leader_speed,
+                }),
+                units: args.units,
+                key_speeds: keyboard_clone.speeds(),
+                dimmed: dim_after.is_some_and(|after| last_activity.elapsed() >= after),
+                show_help,
+                bases: &bases,
+            })
+            .is_err();
+
+        if let Some(status_line) = &mut status_line {
+            let written = status_line.update(now, || {
+                format_status_line(current_state, emergency, last_command, displayed_speed)
+            });
+            match written {
+                Err(e) if !status_line_failing => {
+                    shared
+                        .log
+                        .lock_recover()
+                        .push(format!("Failed to write the status line file: {}", e));
+                    status_line_failing = true;
                 }
+                Err(_) => {}
+                Ok(()) => status_line_failing = false,
             }
+        }
 
-            ControlState::CanMove => {
-                // Send move command with current target speed
-                let move_message = create_move_msg(target_speed.x, target_speed.y, target_speed.z);
-                let move_bytes = move_message.encode_to_vec();
-
-                if ws_sink
-                    .send(tungstenite::Message::Binary(move_bytes.into()))
-                    .await
-                    .is_err()
-                {
-                    break;
+        // A graceful exit goes on once the base reports standing still, or nothing
+        // can be moving because we don't hold control
+        let stop_done = match graceful_stop_at {
+            Some(since) => {
+                let standing_still = !actual_speed_stale
+                    && actual_speed.is_some_and(|s| s.is_zero(args.stop_tolerance));
+                let timed_out = since.elapsed() > stop_timeout;
+                if timed_out && !standing_still {
+                    shared
+                        .log
+                        .lock_recover()
+                        .push("Graceful stop timed out, releasing anyway");
                 }
+                current_state != ControlState::CanMove || standing_still || timed_out
             }
+            None => false,
+        };
 
-            ControlState::InitializedButNotHold => {
-                // Wait for control authority
-                continue;
+        // Check if we should exit
+        if input.should_exit || stop_done || *interrupted.lock_recover() {
+            // Stop and release every base, unless its connection is gone anyway
+            let timeout = Duration::from_millis(args.close_timeout_ms);
+            for robot in &mut robots {
+                if let Err(e) = robot.release(timeout).await {
+                    ui.cleanup().ok();
+                    eprintln!("Warning: {}", e);
+                }
             }
+            break;
+        }
+
+        // A takeover ends once control is ours, the other session released it or
+        // the prompt / request timed out
+        if let Some((stage, since)) = takeover {
+            let timeout = match stage {
+                Takeover::Confirm => TAKEOVER_CONFIRM_TIMEOUT,
+                Takeover::Requested => TAKEOVER_TIMEOUT,
+            };
+            let message = if current_state != ControlState::InitializedButNotHold {
+                Some(
+                    if current_state == ControlState::CanMove && stage == Takeover::Requested {
+                        "Took over control"
+                    } else {
+                        "Takeover no longer needed"
+                    },
+                )
+            } else if since.elapsed() >= timeout {
+                Some(match stage {
+                    Takeover::Confirm => "Takeover not confirmed, cancelled",
+                    Takeover::Requested => "Takeover refused, another session still holds control",
+                })
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                takeover = None;
+                takeover_requested = false;
+                shared.log.lock_recover().push(message);
+                *shared.error_message.lock_recover() = ErrorMessage::new(message.to_string());
+            }
+        }
+
+        // Until a reconnect succeeds nothing is sent; once it does the state is
+        // Uninitialized, so the handshake runs again.
+        if !robot.keep_alive().await {
+            recover_requested = false;
+            takeover_requested = false;
+            continue;
+        }
+        if !command_tick && !operator_stop_latched {
+            continue;
+        }
+
+        let drive = if operator_stop {
+            recover_requested = false;
+            Drive::Stop
+        } else {
+            // The recover burst repeats the queued step's or the target speed,
+            // nothing is sent while another session holds control
+            let now = Instant::now();
+            let recover = std::mem::take(&mut recover_requested).then(|| {
+                if current_state == ControlState::InitializedButNotHold {
+                    SpeedData::ZERO
+                } else {
+                    motion_queue.tick(now).unwrap_or(target_speed)
+                }
+            });
+            // The script's or the queued step's speed, or the smoothed target speed
+            let speed = if current_state == ControlState::CanMove {
+                script_speed
+                    .or_else(|| motion_queue.tick(now))
+                    .unwrap_or(smoothed_speed)
+            } else {
+                SpeedData::ZERO
+            };
+            Drive::Command(Command {
+                speed,
+                hold: paused || exit_pending,
+                recover,
+                takeover: std::mem::take(&mut takeover_requested),
+            })
+        };
+        if robot.drive(drive).await.takeover_sent {
+            takeover = Some((Takeover::Requested, Instant::now()));
+            robot.shared().log.lock_recover().push("Takeover requested");
         }
     }
-}
+    flush_odometry_csv(&odometry_csv);
 
-// WebSocket Message Handlers
-// Process base status messages and update state
-fn handle_base_status(
-    base_status: &base_backend::BaseStatus,
-    session_id: u32,
-    odometry_data: Arc<Mutex<Option<SpeedData>>>,
-    emergency_stop: Arc<Mutex<bool>>,
-    error_message: Arc<Mutex<ErrorMessage>>,
-) -> ControlState {
-    // Check for parking/emergency stop
-    let parking = base_status.parking_stop_detail.is_some();
-    if let Some(ref parking_detail) = base_status.parking_stop_detail {
-        let msg = format!("Emergency Stop: {:?}", parking_detail);
-        *error_message.lock().unwrap() = ErrorMessage::new(msg);
-        *emergency_stop.lock().unwrap() = true;
-    } else {
-        *emergency_stop.lock().unwrap() = false;
-        // Clear error message after 3 seconds
-        let mut err = error_message.lock().unwrap();
-        if err.is_expired(std::time::Duration::from_secs(3)) {
-            *err = ErrorMessage::default();
+    if let Some(path) = &session_state_path {
+        let (linear_speed, angular_speed) = keyboard_clone.speeds();
+        let state = SessionState {
+            linear_speed: Some(linear_speed),
+            angular_speed: Some(angular_speed),
+            theme: chosen_theme.or(session.theme),
+        };
+        if let Err(e) = save_session_state(path, &state) {
+            warn!(target: "session", "state not saved to {}: {}", path.display(), e);
         }
     }
-    let session_holder = base_status.session_holder;
-    let api_initialized = base_status.api_control_initialized;
-
-    // Determine control state
-    let state = if !api_initialized {
-        ControlState::Uninitialized
-    } else if !parking && session_holder == session_id {
-        ControlState::CanMove
-    } else {
-        ControlState::InitializedButNotHold
+}
+
+// Send the log macros' events to the --log-file, or to stderr when headless and
+// a --log-level is given. Never to a terminal the UI draws on.
+#[cfg(not(feature = "tracing"))]
+fn init_logging(
+    path: Option<&std::path::Path>,
+    level: Option<log::LevelFilter>,
+    headless: bool,
+) -> Result<(), String> {
+    let target = match path {
+        Some(path) => env_logger::Target::Pipe(Box::new(
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("can't write {}: {}", path.display(), e))?,
+        )),
+        None if headless && level.is_some() => env_logger::Target::Stderr,
+        None => return Ok(()),
     };
+    env_logger::Builder::new()
+        .filter_level(level.unwrap_or(log::LevelFilter::Info))
+        .format_timestamp_millis()
+        .target(target)
+        .init();
+    Ok(())
+}
 
-    // Update odometry data if we have control
-    if state == ControlState::CanMove {
-        if let Some(ref estimated_odometry) = base_status.estimated_odometry {
-            *odometry_data.lock().unwrap() = Some(SpeedData {
-                x: estimated_odometry.speed_x,
-                y: estimated_odometry.speed_y,
-                z: estimated_odometry.speed_z,
-            });
+// Like the env_logger one, with the spans of the connections and the control
+// loop. The log macros' events are forwarded into the spans they occur in.
+#[cfg(feature = "tracing")]
+fn init_logging(
+    path: Option<&std::path::Path>,
+    level: Option<log::LevelFilter>,
+    headless: bool,
+) -> Result<(), String> {
+    use tracing_subscriber::filter::LevelFilter;
+    use tracing_subscriber::fmt::format::FmtSpan;
+
+    let max_level = match level.unwrap_or(log::LevelFilter::Info) {
+        log::LevelFilter::Off => LevelFilter::OFF,
+        log::LevelFilter::Error => LevelFilter::ERROR,
+        log::LevelFilter::Warn => LevelFilter::WARN,
+        log::LevelFilter::Info => LevelFilter::INFO,
+        log::LevelFilter::Debug => LevelFilter::DEBUG,
+        log::LevelFilter::Trace => LevelFilter::TRACE,
+    };
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(max_level)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE);
+    match path {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| format!("can't write {}: {}", path.display(), e))?;
+            subscriber
+                .with_writer(Mutex::new(file))
+                .with_ansi(false)
+                .init();
         }
+        None if headless && level.is_some() => subscriber.with_writer(std::io::stderr).init(),
+        None => {}
     }
+    Ok(())
+}
+
+// Write out the rows the --log-csv file still buffers
+fn flush_odometry_csv(csv: &Option<Arc<Mutex<OdometryLogger>>>) {
+    if let Some(csv) = csv {
+        csv.lock_recover().flush().ok();
+    }
+}
 
-    state
+/// Session id the synthetic statuses of --preview are addressed to
+const PREVIEW_SESSION_ID: u32 = 1;
+
+/// One representative state shown by --preview
+struct PreviewScene {
+    name: &'static str,
+    status: base_backend::BaseStatus,
+    target_speed: SpeedData,
+}
+
+fn preview_scenes() -> Vec<PreviewScene> {
+    // Only the speeds matter here, whatever else the odometry message carries
+    #[allow(clippy::needless_update)]
+    let odometry = |speed_x, speed_y, speed_z| {
+        Some(base_backend::BaseEstimatedOdometry {
+            speed_x,
+            speed_y,
+            speed_z,
+            ..Default::default()
+        })
+    };
+    let in_control = base_backend::BaseStatus {
+        api_control_initialized: true,
+        session_holder: PREVIEW_SESSION_ID,
+        battery_thousandth: 730,
+        ..Default::default()
+    };
+    vec![
+        PreviewScene {
+            name: "Initializing",
+            status: base_backend::BaseStatus::default(),
+            target_speed: SpeedData::ZERO,
+        },
+        PreviewScene {
+            name: "Moving",
+            status: base_backend::BaseStatus {
+                estimated_odometry: odometry(0.098, 0.0, 0.49),
+                ..in_control.clone()
+            },
+            target_speed: SpeedData::new(LINEAR_SPEED, 0.0, ANGULAR_SPEED),
+        },
+        PreviewScene {
+            name: "Stale odometry",
+            status: in_control.clone(),
+            target_speed: SpeedData::new(LINEAR_SPEED, 0.0, 0.0),
+        },
+        PreviewScene {
+            name: "No control",
+            status: base_backend::BaseStatus {
+                session_holder: PREVIEW_SESSION_ID + 1,
+                ..in_control.clone()
+            },
+            target_speed: SpeedData::ZERO,
+        },
+        PreviewScene {
+            name: "Emergency stop",
+            status: base_backend::BaseStatus {
+                parking_stop_detail: Some(Default::default()),
+                ..in_control
+            },
+            target_speed: SpeedData::ZERO,
+        },
+    ]
 }
 
-// Spawn task to receive and process WebSocket messages
-fn spawn_websocket_receiver(
-    mut ws_stream: futures_util::stream::SplitStream<
-        tokio_tungstenite::WebSocketStream<
-            tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
-        >,
-    >,
-    control_state: Arc<Mutex<ControlState>>,
-    odometry_data: Arc<Mutex<Option<SpeedData>>>,
-    emergency_stop: Arc<Mutex<bool>>,
-    error_message: Arc<Mutex<ErrorMessage>>,
+// Render the UI without a robot, cycling through the preview scenes. The
+// synthetic statuses go through the same handling as frames from a robot.
+// Runs until the exit key, Esc or --preview-duration-ms.
+async fn run_preview(
+    args: &Args,
+    settings: &Settings,
+    monochrome: bool,
+    ui: &mut dyn Ui,
+    keyboard: &dyn InputSource,
 ) {
-    tokio::spawn(async move {
-        while let Some(msg) = ws_stream.next().await {
-            let msg = msg.unwrap();
-            if let tungstenite::Message::Binary(bytes) = msg {
-                let msg = base_backend::ApiUp::decode(bytes).unwrap();
-                if let Some(log) = msg.log {
-                    *error_message.lock().unwrap() = ErrorMessage::new(format!("Log: {:?}", log));
-                }
-                let session_id = msg.session_id;
-                let protocol_version = msg.protocol_major_version;
-                if let Some(base_backend::api_up::Status::BaseStatus(base_status)) = msg.status {
-                    let state = handle_base_status(
-                        &base_status,
-                        session_id,
-                        odometry_data.clone(),
-                        emergency_stop.clone(),
-                        error_message.clone(),
-                    );
-                    *control_state.lock().unwrap() = state;
-                    // Only show control loss message when actually losing control
-                    if state == ControlState::InitializedButNotHold {
-                        *error_message.lock().unwrap() =
-                            ErrorMessage::new("Control in hands of another user".to_string());
-                    }
-                    if state == ControlState::CanMove
-                        && protocol_version != ACCEPTABLE_PROTOCOL_MAJOR_VERSION
-                    {
-                        *error_message.lock().unwrap() =
-                            ErrorMessage::new("Protocol version mismatch".to_string());
-                    }
-                }
-            };
+    let shared = SharedState::default();
+    *shared.displayed_speed.lock_recover() = SpeedFilter::new(args.actual_speed_alpha);
+    let options = ReceiverOptions {
+        accepted_protocol_versions: args.accepted_protocol_versions.clone(),
+        missing_odometry: args.missing_odometry,
+        debug_frames: false,
+        mirror: None,
+        recording: None,
+        odometry_csv: None,
+        status_json: None,
+    };
+    let protocol_version = args.accepted_protocol_versions.first();
+    let scenes = preview_scenes();
+    let scene_duration = Duration::from_millis(args.preview_scene_ms);
+    let duration = args.preview_duration_ms.map(Duration::from_millis);
+    let motion_queue = MotionQueue::new(settings.linear_speed, settings.angular_speed);
+    let started = Instant::now();
+    let mut shown = None;
+    let mut show_help = false;
+
+    loop {
+        let elapsed = started.elapsed();
+        let keys = keyboard.take_key_presses();
+        if keyboard.should_exit()
+            || keys.contains(&KeyCode::Esc)
+            || duration.is_some_and(|duration| elapsed >= duration)
+        {
+            return;
         }
-    });
+        if keys
+            .iter()
+            .any(|key| matches!(key, KeyCode::Char('h') | KeyCode::Char('?')))
+        {
+            show_help = !show_help;
+        }
+
+        let index =
+            (elapsed.as_millis() / scene_duration.as_millis().max(1)) as usize % scenes.len();
+        let scene = &scenes[index];
+        if shown != Some(index) {
+            shared
+                .log
+                .lock_recover()
+                .push(format!("Preview: {}", scene.name));
+            shown = Some(index);
+        }
+        shared
+            .protocol
+            .lock_recover()
+            .check(protocol_version, &options.accepted_protocol_versions);
+        process_base_status(&scene.status, PREVIEW_SESSION_ID, &shared, &options);
+
+        let wheel_speeds = settings
+            .geometry()
+            .map(|g| g.wheel_speeds(scene.target_speed));
+        ui.draw(&UiView {
+            control_state: *shared.control_state.lock_recover(),
+            target_speed: scene.target_speed,
+            actual_speed: shared.displayed_speed.lock_recover().value(),
+            actual_speed_stale: *shared.odometry_stale.lock_recover(),
+            pressed_keys: &keyboard.get_pressed_keys(),
+            error_message: &shared.error_message.lock_recover().clone(),
+            emergency_stop: *shared.emergency_stop.lock_recover(),
+            motion_queue: &motion_queue,
+            odometer: *shared.odometer.lock_recover(),
+            smoothing: settings.smoothing,
+            cruise: None,
+            log: &shared.log.lock_recover().clone(),
+            wheel_speeds: wheel_speeds.as_deref(),
+            monochrome,
+            url: None,
+            session_id: *shared.session_id.lock_recover(),
+            profile: None,
+            camera: args.camera,
+            exit_hold_progress: keyboard.exit_hold_progress(),
+            speed_history: &shared.speed_history.lock_recover().clone(),
+            log_scroll: 0,
+            latency: None,
+            battery: *shared.battery.lock_recover(),
+            protocol: shared
+                .protocol
+                .lock_recover()
+                .indicator(&args.accepted_protocol_versions),
+            takeover: None,
+            paused: false,
+            confirm_exit: keyboard.exit_confirmation_pending(),
+            speed_mode: keyboard.speed_mode(),
+            deadman: keyboard.deadman_engaged(),
+            max_speed: args
+                .show_percent
+                .then(|| SpeedData::from_linear_angular(keyboard.speeds())),
+            follow: None,
+            units: args.units,
+            key_speeds: keyboard.speeds(),
+            dimmed: false,
+            show_help,
+            bases: &[],
+        })
+        .ok();
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
 }
 
-// Message Creation Helpers
-//Create a message to set the report frequency
-fn create_set_frequency_msg(frequency: base_backend::ReportFrequency) -> base_backend::ApiDown {
-    base_backend::ApiDown {
-        down: Some(base_backend::api_down::Down::SetReportFrequency(
-            frequency as i32,
-        )),
+// Send the recorded messages again with their original timing, keyboard input
+// is ignored except for exiting, which stops the robot and ends the replay early.
+// The robot's feedback is shown as usual. Ends by releasing control.
+async fn run_replay(
+    messages: &[(Duration, base_backend::ApiDown)],
+    mut robot: RobotConnection,
+    args: &Args,
+    settings: &Settings,
+    monochrome: bool,
+    ui: &mut dyn Ui,
+    keyboard: &dyn InputSource,
+) {
+    let shared = robot.shared().clone();
+    let motion_queue = MotionQueue::new(settings.linear_speed, settings.angular_speed);
+    let started = Instant::now();
+    let mut next = 0;
+    let mut commanded = SpeedData::ZERO;
+    shared
+        .log
+        .lock_recover()
+        .push(format!("Replay: {} messages", messages.len()));
+
+    while next < messages.len() {
+        let keys = keyboard.take_key_presses();
+        if keyboard.should_exit() || keys.contains(&KeyCode::Esc) {
+            shared.log.lock_recover().push("Replay: aborted");
+            robot.send(&create_move_msg(0.0, 0.0, 0.0)).await.ok();
+            break;
+        }
+        if !robot.is_connected() {
+            ui.cleanup().ok();
+            eprintln!("Error: connection lost during the replay");
+            return;
+        }
+
+        while let Some((at, message)) = messages.get(next) {
+            if started.elapsed() < *at {
+                break;
+            }
+            if let Err(e) = robot.send(message).await {
+                ui.cleanup().ok();
+                eprintln!("Error: replay send failed: {}", e);
+                return;
+            }
+            commanded = move_speed(message).unwrap_or(commanded);
+            next += 1;
+        }
+
+        ui.draw(&UiView {
+            control_state: *shared.control_state.lock_recover(),
+            target_speed: commanded,
+            actual_speed: shared.displayed_speed.lock_recover().value(),
+            actual_speed_stale: *shared.odometry_stale.lock_recover(),
+            pressed_keys: &HashMap::new(),
+            error_message: &shared.error_message.lock_recover().clone(),
+            emergency_stop: *shared.emergency_stop.lock_recover(),
+            motion_queue: &motion_queue,
+            odometer: *shared.odometer.lock_recover(),
+            smoothing: settings.smoothing,
+            cruise: None,
+            log: &shared.log.lock_recover().clone(),
+            wheel_speeds: None,
+            monochrome,
+            url: Some(robot.url()),
+            session_id: *shared.session_id.lock_recover(),
+            profile: None,
+            camera: args.camera,
+            exit_hold_progress: None,
+            speed_history: &shared.speed_history.lock_recover().clone(),
+            log_scroll: 0,
+            latency: None,
+            battery: *shared.battery.lock_recover(),
+            protocol: shared
+                .protocol
+                .lock_recover()
+                .indicator(&args.accepted_protocol_versions),
+            takeover: None,
+            paused: false,
+            confirm_exit: false,
+            speed_mode: keyboard.speed_mode(),
+            deadman: keyboard.deadman_engaged(),
+            max_speed: None,
+            follow: None,
+            units: args.units,
+            key_speeds: keyboard.speeds(),
+            dimmed: false,
+            show_help: false,
+            bases: &[],
+        })
+        .ok();
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let timeout = Duration::from_millis(args.close_timeout_ms);
+    if let Err(e) = robot.release(timeout).await {
+        ui.cleanup().ok();
+        eprintln!("Warning: {}", e);
     }
 }
 
-// Create a message to initialize API control
-fn create_init_msg() -> base_backend::ApiDown {
-    base_backend::ApiDown {
-        down: Some(base_backend::api_down::Down::BaseCommand(
-            base_backend::BaseCommand {
-                command: Some(base_backend::base_command::Command::ApiControlInitialize(
-                    true,
-                )),
-            },
-        )),
+// Connect to `endpoint`, showing a failure until the operator retries or
+// quits. None when they quit.
+async fn connect_with_retry(
+    endpoint: &Endpoint,
+    shared: &SharedState,
+    options: &ReceiverOptions,
+    settings: LinkSettings,
+    ui: &mut dyn Ui,
+    keyboard: &dyn InputSource,
+) -> Option<RobotConnection> {
+    loop {
+        ui.draw_connection(endpoint.url(), None).ok();
+        let connected =
+            RobotConnection::connect(endpoint.clone(), shared.clone(), options.clone(), settings)
+                .await;
+        let error = match connected {
+            Ok(robot) => return Some(robot),
+            Err(e) => describe_connect_error(&e),
+        };
+
+        keyboard.take_key_presses();
+        loop {
+            ui.draw_connection(endpoint.url(), Some(&error)).ok();
+            let keys = keyboard.take_key_presses();
+            if keyboard.should_exit() || keys.contains(&KeyCode::Esc) {
+                return None;
+            }
+            if keys.contains(&KeyCode::Char('r')) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
     }
 }
 
-// Create a message to send move commands
-// Users can easily see and modify speed parameters here
-fn create_move_msg(speed_x: f32, speed_y: f32, speed_z: f32) -> base_backend::ApiDown {
-    base_backend::ApiDown {
-        down: Some(base_backend::api_down::Down::BaseCommand(
-            base_backend::BaseCommand {
-                command: Some(base_backend::base_command::Command::SimpleMoveCommand(
-                    base_backend::SimpleBaseMoveCommand {
-                        command: Some(base_backend::simple_base_move_command::Command::XyzSpeed(
-                            base_backend::XyzSpeed {
-                                speed_x,
-                                speed_y,
-                                speed_z,
-                            },
-                        )),
-                    },
-                )),
-            },
-        )),
+// "X,Y,Z" speeds, e.g. "0.1,0,0"
+fn parse_speed(s: &str) -> Result<SpeedData, String> {
+    let values = s
+        .split(',')
+        .map(|v| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|e| format!("invalid speed '{}': {}", v, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [x, y, z] => Ok(SpeedData::new(x, y, z)),
+        _ => Err(format!("expected X,Y,Z, got '{}'", s)),
     }
 }
 
-//Create a message to close/disable API control
-fn create_close_msg() -> base_backend::ApiDown {
-    base_backend::ApiDown {
-        down: Some(base_backend::api_down::Down::BaseCommand(
-            base_backend::BaseCommand {
-                command: Some(base_backend::base_command::Command::ApiControlInitialize(
-                    false,
-                )),
-            },
-        )),
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_report_frequency_is_supported() {
+        assert!(report_frequency_from_hz(DEFAULT_REPORT_FREQUENCY_HZ).is_some());
+        assert!(SUPPORTED_REPORT_HZ.contains(&DEFAULT_REPORT_FREQUENCY_HZ));
+    }
+
+    #[test]
+    fn speeds_are_parsed_as_x_y_z() {
+        assert_eq!(
+            parse_speed("0.1, 0,-0.2"),
+            Ok(SpeedData::new(0.1, 0.0, -0.2))
+        );
+        assert!(parse_speed("0.1,0").is_err());
+        assert!(parse_speed("a,b,c").is_err());
     }
 }
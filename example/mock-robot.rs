@@ -0,0 +1,87 @@
+// ============================================================================
+// Mock Robot - A simulated base to drive the examples against without hardware
+// ============================================================================
+//
+// Listens for WebSocket connections and answers them like a base would: API
+// control goes to the first session initializing it, moves are integrated into
+// odometry that lags the command and BaseStatus is reported at the requested
+// frequency.
+//
+// Usage:
+//   cargo run --bin mock-robot
+//   cargo run --bin base-advanced-control-websocket ws://127.0.0.1:8439
+//   cargo run --bin mock-robot -- --parking-stop-after-ms 5000 --parking-stop-for-ms 3000
+// ============================================================================
+
+use clap::Parser;
+use log::{error, info};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use robot_examples::lock_recover::LockRecover;
+use robot_examples::mock_robot::{serve, MockBase, DEFAULT_ODOMETRY_LAG};
+
+#[derive(Parser)]
+struct Args {
+    #[arg(long, default_value = "127.0.0.1:8439", help = "Address to listen on")]
+    listen: std::net::SocketAddr,
+    #[arg(
+        long,
+        default_value_t = DEFAULT_ODOMETRY_LAG.as_millis() as u64,
+        help = "Time constant of the odometry following the commanded speed, in milliseconds"
+    )]
+    odometry_lag_ms: u64,
+    #[arg(
+        long,
+        help = "Simulate a parking stop this many milliseconds after startup, and again as often"
+    )]
+    parking_stop_after_ms: Option<u64>,
+    #[arg(
+        long,
+        default_value_t = 3000,
+        help = "How long a simulated parking stop lasts, in milliseconds"
+    )]
+    parking_stop_for_ms: u64,
+}
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(
+        env_logger::Env::default().filter_or(env_logger::DEFAULT_FILTER_ENV, "info"),
+    )
+    .init();
+    let args = Args::parse();
+    let base = Arc::new(Mutex::new(MockBase::new(Duration::from_millis(
+        args.odometry_lag_ms,
+    ))));
+
+    if let Some(after_ms) = args.parking_stop_after_ms {
+        let base = base.clone();
+        let (after, stop_for) = (
+            Duration::from_millis(after_ms),
+            Duration::from_millis(args.parking_stop_for_ms),
+        );
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(after).await;
+                info!("parking stop");
+                base.lock_recover().set_parking_stop(true, Instant::now());
+                tokio::time::sleep(stop_for).await;
+                info!("parking stop cleared");
+                base.lock_recover().set_parking_stop(false, Instant::now());
+            }
+        });
+    }
+
+    let listener = match tokio::net::TcpListener::bind(args.listen).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to listen on {}: {}", args.listen, e);
+            return;
+        }
+    };
+    info!("Mock robot listening on ws://{}", args.listen);
+    if let Err(e) = serve(listener, base).await {
+        error!("Accepting connections failed: {}", e);
+    }
+}
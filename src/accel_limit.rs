@@ -0,0 +1,180 @@
+// ============================================================================
+// Acceleration Limit Module - Hard slew rate limit on the commands sent
+// ============================================================================
+//
+// Unlike the smoothing profiles, which shape the keyboard target, this sits at
+// the very end of the command path and limits whatever is about to be sent.
+
+use std::time::Instant;
+
+use super::keyboard_input::SpeedData;
+
+/// Maximum rate of change of the commanded speed. `None` leaves that
+/// direction unlimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AccelLimits {
+    /// Speeding up along X/Y, m/s^2
+    pub linear_accel: Option<f32>,
+    /// Slowing down along X/Y, m/s^2
+    pub linear_decel: Option<f32>,
+    /// Speeding up around Z, rad/s^2
+    pub angular_accel: Option<f32>,
+    /// Slowing down around Z, rad/s^2
+    pub angular_decel: Option<f32>,
+}
+
+/// Limits each command against the last one sent
+#[derive(Clone, Debug)]
+pub struct AccelLimiter {
+    limits: AccelLimits,
+    last_sent: Option<(SpeedData, Instant)>,
+}
+
+impl AccelLimiter {
+    pub fn new(limits: AccelLimits) -> Self {
+        Self {
+            limits,
+            last_sent: None,
+        }
+    }
+
+    /// `requested`, limited so that it differs from the last sent speed by at
+    /// most what the limits allow in the time since. After a `reset` the base
+    /// is assumed to stand still.
+    pub fn limit(&self, requested: SpeedData, now: Instant) -> SpeedData {
+        let (previous, dt) = match self.last_sent {
+            Some((speed, at)) => (speed, now.duration_since(at).as_secs_f32()),
+            None => (SpeedData::ZERO, 0.0),
+        };
+        let l = &self.limits;
+        SpeedData::new(
+            slew(previous.x, requested.x, l.linear_accel, l.linear_decel, dt),
+            slew(previous.y, requested.y, l.linear_accel, l.linear_decel, dt),
+            slew(
+                previous.z,
+                requested.z,
+                l.angular_accel,
+                l.angular_decel,
+                dt,
+            ),
+        )
+    }
+
+    /// Remember the speed actually sent at `now`
+    pub fn sent(&mut self, speed: SpeedData, now: Instant) {
+        self.last_sent = Some((speed, now));
+    }
+
+    /// Forget the last sent speed, e.g. when no commands are being sent
+    pub fn reset(&mut self) {
+        self.last_sent = None;
+    }
+}
+
+// Move from `previous` towards `target` within the rate limit. Moving away from
+// zero is acceleration, moving towards or through zero is deceleration.
+fn slew(previous: f32, target: f32, accel: Option<f32>, decel: Option<f32>, dt: f32) -> f32 {
+    let speeding_up = target.abs() > previous.abs() && target * previous >= 0.0;
+    let rate = if speeding_up { accel } else { decel };
+    match rate {
+        Some(rate) => {
+            let max_delta = rate * dt;
+            previous + (target - previous).clamp(-max_delta, max_delta)
+        }
+        None => target,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const EPS: f32 = 1e-4;
+    const TICK: Duration = Duration::from_millis(10);
+
+    fn limiter() -> AccelLimiter {
+        AccelLimiter::new(AccelLimits {
+            linear_accel: Some(0.5),
+            linear_decel: Some(1.0),
+            angular_accel: Some(2.0),
+            angular_decel: None,
+        })
+    }
+
+    // Feed `target` for `ticks` ticks, returning every speed sent
+    fn run(
+        limiter: &mut AccelLimiter,
+        start: Instant,
+        target: SpeedData,
+        ticks: u32,
+    ) -> Vec<SpeedData> {
+        (1..=ticks)
+            .map(|i| {
+                let now = start + TICK * i;
+                let speed = limiter.limit(target, now);
+                limiter.sent(speed, now);
+                speed
+            })
+            .collect()
+    }
+
+    #[test]
+    fn step_up_is_limited_to_the_accel_rate() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        limiter.sent(SpeedData::ZERO, start);
+        let sent = run(&mut limiter, start, SpeedData::new(1.0, 0.0, 0.0), 100);
+
+        let mut previous = 0.0;
+        for speed in &sent {
+            assert!(speed.x - previous <= 0.5 * TICK.as_secs_f32() + EPS);
+            previous = speed.x;
+        }
+        // 0.5 m/s^2 for one second
+        assert!((sent.last().unwrap().x - 0.5).abs() < EPS);
+    }
+
+    #[test]
+    fn step_down_uses_the_decel_rate() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        limiter.sent(SpeedData::new(0.5, 0.0, 0.0), start);
+        let sent = run(&mut limiter, start, SpeedData::ZERO, 25);
+        // 1.0 m/s^2 for 0.25 s
+        assert!((sent.last().unwrap().x - 0.25).abs() < EPS);
+
+        let sent = run(&mut limiter, start + TICK * 25, SpeedData::ZERO, 50);
+        assert_eq!(sent.last().unwrap().x, 0.0);
+    }
+
+    #[test]
+    fn reversing_decelerates_through_zero_first() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        limiter.sent(SpeedData::new(0.2, 0.0, 0.0), start);
+        let speed = limiter.limit(SpeedData::new(-0.2, 0.0, 0.0), start + TICK);
+        assert!((speed.x - (0.2 - 1.0 * TICK.as_secs_f32())).abs() < EPS);
+    }
+
+    #[test]
+    fn unlimited_direction_passes_through() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        limiter.sent(SpeedData::new(0.0, 0.0, 1.0), start);
+        let speed = limiter.limit(SpeedData::ZERO, start + TICK);
+        assert_eq!(speed.z, 0.0);
+    }
+
+    #[test]
+    fn starts_from_standstill_after_reset() {
+        let mut limiter = limiter();
+        let start = Instant::now();
+        limiter.sent(SpeedData::new(0.5, 0.0, 0.0), start);
+        limiter.reset();
+        assert_eq!(
+            limiter.limit(SpeedData::new(0.5, 0.0, 0.0), start + TICK),
+            SpeedData::ZERO
+        );
+    }
+}
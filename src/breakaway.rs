@@ -0,0 +1,123 @@
+// ============================================================================
+// Breakaway Module - Low-speed boost to overcome static friction
+// ============================================================================
+
+use std::str::FromStr;
+
+use super::keyboard_input::SpeedData;
+
+/// Boost of one axis: a nonzero command below `threshold` (absolute value) is
+/// raised to `minimum`, keeping its sign. The default never boosts.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AxisBoost {
+    pub threshold: f32,
+    pub minimum: f32,
+}
+
+impl AxisBoost {
+    pub fn apply(&self, value: f32) -> f32 {
+        if value != 0.0 && value.abs() < self.threshold {
+            self.minimum.copysign(value)
+        } else {
+            value
+        }
+    }
+}
+
+/// Parsed from `THRESHOLD:MINIMUM`, e.g. `0.05:0.08`
+impl FromStr for AxisBoost {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (threshold, minimum) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected THRESHOLD:MINIMUM, got '{}'", s))?;
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<f32>()
+                .map_err(|e| format!("invalid speed '{}': {}", v, e))
+        };
+        let boost = Self {
+            threshold: parse(threshold)?,
+            minimum: parse(minimum)?,
+        };
+        if boost.threshold < 0.0 || boost.minimum < 0.0 {
+            return Err(format!(
+                "threshold and minimum must not be negative: '{}'",
+                s
+            ));
+        }
+        Ok(boost)
+    }
+}
+
+/// Per-axis breakaway boost applied to every command sent
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Breakaway {
+    pub x: AxisBoost,
+    pub y: AxisBoost,
+    pub z: AxisBoost,
+}
+
+impl Breakaway {
+    pub fn apply(&self, speed: SpeedData) -> SpeedData {
+        SpeedData::new(
+            self.x.apply(speed.x),
+            self.y.apply(speed.y),
+            self.z.apply(speed.z),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOST: AxisBoost = AxisBoost {
+        threshold: 0.05,
+        minimum: 0.08,
+    };
+
+    #[test]
+    fn below_threshold_is_raised_to_minimum() {
+        assert_eq!(BOOST.apply(0.01), 0.08);
+        assert_eq!(BOOST.apply(-0.01), -0.08);
+    }
+
+    #[test]
+    fn at_and_above_threshold_is_unchanged() {
+        assert_eq!(BOOST.apply(0.05), 0.05);
+        assert_eq!(BOOST.apply(-0.05), -0.05);
+        assert_eq!(BOOST.apply(0.3), 0.3);
+    }
+
+    #[test]
+    fn zero_stays_exactly_zero() {
+        assert_eq!(BOOST.apply(0.0), 0.0);
+        let breakaway = Breakaway {
+            x: BOOST,
+            y: BOOST,
+            z: BOOST,
+        };
+        assert_eq!(breakaway.apply(SpeedData::ZERO), SpeedData::ZERO);
+    }
+
+    #[test]
+    fn axes_are_boosted_independently() {
+        let breakaway = Breakaway {
+            x: BOOST,
+            ..Default::default()
+        };
+        assert_eq!(
+            breakaway.apply(SpeedData::new(0.01, 0.01, -0.01)),
+            SpeedData::new(0.08, 0.01, -0.01)
+        );
+    }
+
+    #[test]
+    fn parses_threshold_and_minimum() {
+        assert_eq!("0.05:0.08".parse::<AxisBoost>(), Ok(BOOST));
+        assert!("0.05".parse::<AxisBoost>().is_err());
+        assert!("-0.05:0.08".parse::<AxisBoost>().is_err());
+    }
+}
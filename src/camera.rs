@@ -0,0 +1,116 @@
+// ============================================================================
+// Camera Module - Remap operator input to a camera mounted rotated on the base
+// ============================================================================
+
+use super::keyboard_input::SpeedData;
+
+/// Direction the camera looks, counter-clockwise from the base's forward axis.
+/// Movement input is taken relative to this view, so "forward" drives the base
+/// towards what the camera shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum CameraMount {
+    #[default]
+    #[value(name = "0")]
+    Front,
+    #[value(name = "90")]
+    Left,
+    #[value(name = "180")]
+    Rear,
+    #[value(name = "270")]
+    Right,
+}
+
+impl CameraMount {
+    /// Next orientation, used by the runtime toggle key
+    pub fn next(self) -> Self {
+        match self {
+            CameraMount::Front => CameraMount::Left,
+            CameraMount::Left => CameraMount::Rear,
+            CameraMount::Rear => CameraMount::Right,
+            CameraMount::Right => CameraMount::Front,
+        }
+    }
+
+    pub fn degrees(self) -> u16 {
+        match self {
+            CameraMount::Front => 0,
+            CameraMount::Left => 90,
+            CameraMount::Rear => 180,
+            CameraMount::Right => 270,
+        }
+    }
+
+    /// Turn a speed given in the camera view into the base frame. Rotating
+    /// about the vertical axis doesn't change the sense of rotation, so Z is
+    /// passed through.
+    pub fn to_base_frame(self, input: SpeedData) -> SpeedData {
+        input.rotate_xy((self.degrees() as f32).to_radians())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-6;
+
+    fn assert_close(actual: SpeedData, expected: SpeedData) {
+        assert!(
+            (actual.x - expected.x).abs() < EPS
+                && (actual.y - expected.y).abs() < EPS
+                && (actual.z - expected.z).abs() < EPS,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn front_mount_passes_input_through() {
+        let input = SpeedData::new(0.1, 0.2, 0.5);
+        assert_close(CameraMount::Front.to_base_frame(input), input);
+    }
+
+    #[test]
+    fn left_mount_maps_forward_to_base_left() {
+        // The camera looks left, so forward in its view is -Y (left) on the base
+        let forward = SpeedData::new(0.1, 0.0, 0.0);
+        assert_close(
+            CameraMount::Left.to_base_frame(forward),
+            SpeedData::new(0.0, -0.1, 0.0),
+        );
+        // and right in its view is the base's forward
+        let right = SpeedData::new(0.0, 0.1, 0.0);
+        assert_close(
+            CameraMount::Left.to_base_frame(right),
+            SpeedData::new(0.1, 0.0, 0.0),
+        );
+    }
+
+    #[test]
+    fn rear_mount_reverses_linear_keeps_rotation() {
+        let input = SpeedData::new(0.1, 0.2, 0.5);
+        assert_close(
+            CameraMount::Rear.to_base_frame(input),
+            SpeedData::new(-0.1, -0.2, 0.5),
+        );
+    }
+
+    #[test]
+    fn right_mount_maps_forward_to_base_right() {
+        let forward = SpeedData::new(0.1, 0.0, 0.0);
+        assert_close(
+            CameraMount::Right.to_base_frame(forward),
+            SpeedData::new(0.0, 0.1, 0.0),
+        );
+    }
+
+    #[test]
+    fn next_cycles_through_all_mounts() {
+        let mut mount = CameraMount::Front;
+        for expected in [90, 180, 270, 0] {
+            mount = mount.next();
+            assert_eq!(mount.degrees(), expected);
+        }
+    }
+}
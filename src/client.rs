@@ -0,0 +1,507 @@
+// ============================================================================
+// Client Module - Messages to the robot and a minimal client sending them
+// ============================================================================
+//
+// `RobotClient` is the handshake every program controlling the base needs:
+// connect, take API control, send moves and release control again. For a
+// session that survives dropped connections, with authentication, TLS and
+// recording, see `connection::RobotConnection`.
+
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use prost::Message;
+use tokio_tungstenite::MaybeTlsStream;
+
+use super::base_backend;
+use super::keyboard_input::SpeedData;
+use super::robot_ui::ControlState;
+
+pub type WsStream = tokio_tungstenite::WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("websocket: {0}")]
+    WebSocket(#[from] tungstenite::Error),
+    #[error("undecodable message from robot: {0}")]
+    Decode(#[from] prost::DecodeError),
+    #[error("connection closed by robot")]
+    Closed,
+}
+
+/// Report rates the robot has a ReportFrequency for, see report_frequency_from_hz
+pub const SUPPORTED_REPORT_HZ: [u32; 6] = [1, 50, 100, 250, 500, 1000];
+
+/// Report frequency variant for a rate in Hz, None if the robot doesn't support it
+pub fn report_frequency_from_hz(hz: u32) -> Option<base_backend::ReportFrequency> {
+    use base_backend::ReportFrequency;
+    match hz {
+        1000 => Some(ReportFrequency::Rf1000Hz),
+        500 => Some(ReportFrequency::Rf500Hz),
+        250 => Some(ReportFrequency::Rf250Hz),
+        100 => Some(ReportFrequency::Rf100Hz),
+        50 => Some(ReportFrequency::Rf50Hz),
+        1 => Some(ReportFrequency::Rf1Hz),
+        _ => None,
+    }
+}
+
+pub fn create_set_frequency_msg(frequency: base_backend::ReportFrequency) -> base_backend::ApiDown {
+    base_backend::ApiDown {
+        down: Some(base_backend::api_down::Down::SetReportFrequency(
+            frequency as i32,
+        )),
+    }
+}
+
+/// Create a message to initialize API control: `ApiControlInitialize(true)`.
+///
+/// `ApiControlInitialize` is the only `BaseCommand` of the public proto that
+/// takes or releases control, so every firmware built from it uses this one
+/// (and `create_close_msg` to release). There is no other variant to select.
+pub fn create_init_msg() -> base_backend::ApiDown {
+    base_backend::ApiDown {
+        down: Some(base_backend::api_down::Down::BaseCommand(
+            base_backend::BaseCommand {
+                command: Some(base_backend::base_command::Command::ApiControlInitialize(
+                    true,
+                )),
+            },
+        )),
+    }
+}
+
+// Create a message to take control while another session holds it. The public
+// API has no separate force-acquire command, so this is the initialize request
+// sent again on the operator's explicit confirmation.
+pub fn create_takeover_msg() -> base_backend::ApiDown {
+    create_init_msg()
+}
+
+// Create a message to send move commands
+// Users can easily see and modify speed parameters here
+pub fn create_move_msg(speed_x: f32, speed_y: f32, speed_z: f32) -> base_backend::ApiDown {
+    base_backend::ApiDown {
+        down: Some(base_backend::api_down::Down::BaseCommand(
+            base_backend::BaseCommand {
+                command: Some(base_backend::base_command::Command::SimpleMoveCommand(
+                    base_backend::SimpleBaseMoveCommand {
+                        command: Some(base_backend::simple_base_move_command::Command::XyzSpeed(
+                            base_backend::XyzSpeed {
+                                speed_x,
+                                speed_y,
+                                speed_z,
+                            },
+                        )),
+                    },
+                )),
+            },
+        )),
+    }
+}
+
+//Create a message to close/disable API control: `ApiControlInitialize(false)`
+pub fn create_close_msg() -> base_backend::ApiDown {
+    base_backend::ApiDown {
+        down: Some(base_backend::api_down::Down::BaseCommand(
+            base_backend::BaseCommand {
+                command: Some(base_backend::base_command::Command::ApiControlInitialize(
+                    false,
+                )),
+            },
+        )),
+    }
+}
+
+/// Speed of a move command, None for any other message
+pub fn move_speed(message: &base_backend::ApiDown) -> Option<SpeedData> {
+    use base_backend::api_down::Down;
+    use base_backend::base_command::Command;
+    use base_backend::simple_base_move_command::Command as MoveCommand;
+
+    match &message.down {
+        Some(Down::BaseCommand(base_backend::BaseCommand {
+            command:
+                Some(Command::SimpleMoveCommand(base_backend::SimpleBaseMoveCommand {
+                    command: Some(MoveCommand::XyzSpeed(speed)),
+                })),
+        })) => Some(SpeedData::new(speed.speed_x, speed.speed_y, speed.speed_z)),
+        _ => None,
+    }
+}
+
+/// Default ceiling of `SpeedLimit` on the X/Y speed, in m/s
+pub const DEFAULT_MAX_LINEAR: f32 = 0.5;
+/// Default ceiling of `SpeedLimit` on the rotation speed, in rad/s
+pub const DEFAULT_MAX_ANGULAR: f32 = 1.5;
+
+/// Hard ceiling on every move command sent. Separate from the configured
+/// speeds, which turbo modifiers or a bad config could push too far.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedLimit {
+    /// Magnitude of the X/Y speed, in m/s
+    pub max_linear: f32,
+    /// Magnitude of the rotation speed, in rad/s
+    pub max_angular: f32,
+}
+
+impl Default for SpeedLimit {
+    fn default() -> Self {
+        Self {
+            max_linear: DEFAULT_MAX_LINEAR,
+            max_angular: DEFAULT_MAX_ANGULAR,
+        }
+    }
+}
+
+impl SpeedLimit {
+    /// Cap `speed`. X and Y are scaled down together so the direction of
+    /// travel is kept. A component that isn't a finite number becomes zero.
+    pub fn clamp(&self, speed: SpeedData) -> SpeedData {
+        let finite = |value: f32| if value.is_finite() { value } else { 0.0 };
+        let (x, y, z) = (finite(speed.x), finite(speed.y), finite(speed.z));
+        let linear = x.hypot(y);
+        let scale = if linear > self.max_linear {
+            self.max_linear / linear
+        } else {
+            1.0
+        };
+        SpeedData::new(
+            x * scale,
+            y * scale,
+            z.clamp(-self.max_angular, self.max_angular),
+        )
+    }
+
+    /// Cap the speed of a move command, any other message is left alone
+    pub fn apply(&self, message: &mut base_backend::ApiDown) {
+        use base_backend::{api_down::Down, base_command::Command, simple_base_move_command};
+        let Some(Down::BaseCommand(base_backend::BaseCommand {
+            command: Some(Command::SimpleMoveCommand(move_command)),
+        })) = &mut message.down
+        else {
+            return;
+        };
+        let Some(simple_base_move_command::Command::XyzSpeed(speed)) = &mut move_command.command
+        else {
+            return;
+        };
+        let clamped = self.clamp(SpeedData::new(speed.speed_x, speed.speed_y, speed.speed_z));
+        speed.speed_x = clamped.x;
+        speed.speed_y = clamped.y;
+        speed.speed_z = clamped.z;
+    }
+}
+
+/// True if `status` shows `session_id` holding API control with no parking stop
+pub fn holds_control(status: &base_backend::BaseStatus, session_id: u32) -> bool {
+    status.api_control_initialized
+        && status.parking_stop_detail.is_none()
+        && status.session_holder == session_id
+}
+
+/// Control state of `session_id` as `status` reports it. During a parking
+/// stop the holder can't move either, which counts as not holding control.
+pub fn control_state(status: &base_backend::BaseStatus, session_id: u32) -> ControlState {
+    if !status.api_control_initialized {
+        ControlState::Uninitialized
+    } else if holds_control(status, session_id) {
+        ControlState::CanMove
+    } else {
+        ControlState::InitializedButNotHold
+    }
+}
+
+/// What one status tells a session: whether it may move, and whether the base
+/// is in an emergency (parking) stop
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ControlStatus {
+    pub state: ControlState,
+    pub emergency_stop: bool,
+}
+
+impl ControlStatus {
+    pub fn new(status: &base_backend::BaseStatus, session_id: u32) -> Self {
+        Self {
+            state: control_state(status, session_id),
+            emergency_stop: status.parking_stop_detail.is_some(),
+        }
+    }
+}
+
+/// Operator-readable reason for a parking stop. The fields the base set are
+/// named in words, a free-text reason as is; a detail with fields that can't
+/// be put that way is shown in its Debug form.
+pub fn describe_parking_stop(detail: &base_backend::ParkingStopDetail) -> String {
+    serde_json::to_value(detail)
+        .ok()
+        .and_then(|fields| describe_fields(&fields))
+        .unwrap_or_else(|| format!("{:?}", detail))
+}
+
+// "reason: bumper" becomes "bumper", "is_remote: true" becomes "remote" and
+// "zone: 3" becomes "zone 3". Unset fields are left out; nested messages and
+// lists give None.
+fn describe_fields(fields: &serde_json::Value) -> Option<String> {
+    use serde_json::Value;
+    let mut parts = Vec::new();
+    for (name, value) in fields.as_object()? {
+        let label = name.strip_prefix("is_").unwrap_or(name).replace('_', " ");
+        match value {
+            Value::Null | Value::Bool(false) => {}
+            Value::String(text) if text.is_empty() => {}
+            Value::String(text) if name == "reason" => parts.push(text.clone()),
+            Value::String(text) => parts.push(format!("{}: {}", label, text)),
+            Value::Bool(true) => parts.push(label),
+            Value::Number(number) if number.as_f64() == Some(0.0) => {}
+            Value::Number(number) => parts.push(format!("{} {}", label, number)),
+            Value::Array(_) | Value::Object(_) => return None,
+        }
+    }
+    Some(if parts.is_empty() {
+        "no reason given".to_string()
+    } else {
+        parts.join(", ")
+    })
+}
+
+/// Speed to actually send for `requested`: nothing without control, and zero
+/// while the base reports an emergency stop, whatever keys are held
+pub fn gated_command(
+    control_state: ControlState,
+    emergency_stop: bool,
+    requested: SpeedData,
+) -> Option<SpeedData> {
+    match control_state {
+        ControlState::CanMove if emergency_stop => Some(SpeedData::ZERO),
+        ControlState::CanMove => Some(requested),
+        _ => None,
+    }
+}
+
+pub fn set_nodelay(stream: &MaybeTlsStream<tokio::net::TcpStream>) -> std::io::Result<()> {
+    match stream {
+        MaybeTlsStream::Plain(stream) => stream.set_nodelay(true),
+        #[cfg(feature = "tls")]
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().get_ref().get_ref().set_nodelay(true),
+        // Variants of TLS backends this example doesn't enable
+        #[allow(unreachable_patterns)]
+        _ => Ok(()),
+    }
+}
+
+/// A connection to one robot
+pub struct RobotClient {
+    sink: SplitSink<WsStream, tungstenite::Message>,
+    stream: SplitStream<WsStream>,
+    // From the last ApiUp received
+    session_id: Option<u32>,
+    speed_limit: SpeedLimit,
+}
+
+impl RobotClient {
+    /// Connect to `url` and request API control. Control is only granted once
+    /// a status shows it, see `wait_for_control`.
+    pub async fn connect(url: &str) -> Result<Self, ClientError> {
+        let (ws_stream, _) = tokio_tungstenite::connect_async(url).await?;
+        // Only an optimization, the connection works without it
+        let _ = set_nodelay(ws_stream.get_ref());
+        let (sink, stream) = ws_stream.split();
+        let mut client = Self {
+            sink,
+            stream,
+            session_id: None,
+            speed_limit: SpeedLimit::default(),
+        };
+        client.send(&create_init_msg()).await?;
+        Ok(client)
+    }
+
+    pub fn session_id(&self) -> Option<u32> {
+        self.session_id
+    }
+
+    /// Replace the default ceiling on the moves sent
+    pub fn set_speed_limit(&mut self, speed_limit: SpeedLimit) {
+        self.speed_limit = speed_limit;
+    }
+
+    /// Encode an ApiDown message and send it as a binary frame. Move commands
+    /// are capped by the speed limit.
+    pub async fn send(&mut self, message: &base_backend::ApiDown) -> Result<(), ClientError> {
+        let mut message = message.clone();
+        self.speed_limit.apply(&mut message);
+        self.sink
+            .send(tungstenite::Message::Binary(message.encode_to_vec().into()))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn send_move(&mut self, speed: SpeedData) -> Result<(), ClientError> {
+        self.send(&create_move_msg(speed.x, speed.y, speed.z)).await
+    }
+
+    /// Next message from the robot. Frames other than binary ones are skipped.
+    pub async fn recv(&mut self) -> Result<base_backend::ApiUp, ClientError> {
+        while let Some(frame) = self.stream.next().await {
+            let tungstenite::Message::Binary(bytes) = frame? else {
+                continue;
+            };
+            let message = base_backend::ApiUp::decode(&bytes[..])?;
+            self.session_id = Some(message.session_id);
+            return Ok(message);
+        }
+        Err(ClientError::Closed)
+    }
+
+    /// Receive until a status shows this session in control, requesting
+    /// control again for every status that doesn't
+    pub async fn wait_for_control(&mut self) -> Result<(), ClientError> {
+        loop {
+            let message = self.recv().await?;
+            if let Some(base_backend::api_up::Status::BaseStatus(status)) = &message.status {
+                if holds_control(status, message.session_id) {
+                    return Ok(());
+                }
+                self.send(&create_init_msg()).await?;
+            }
+        }
+    }
+
+    /// Stop the base, release control and close the connection
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.send_move(SpeedData::ZERO).await?;
+        self.send(&create_close_msg()).await?;
+        self.sink.close().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_frequency_maps_supported_rates() {
+        use base_backend::ReportFrequency;
+        assert_eq!(report_frequency_from_hz(50), Some(ReportFrequency::Rf50Hz));
+        assert_eq!(report_frequency_from_hz(1), Some(ReportFrequency::Rf1Hz));
+        assert_eq!(
+            report_frequency_from_hz(1000),
+            Some(ReportFrequency::Rf1000Hz)
+        );
+        for hz in SUPPORTED_REPORT_HZ {
+            assert!(report_frequency_from_hz(hz).is_some(), "{} Hz", hz);
+        }
+    }
+
+    #[test]
+    fn report_frequency_rejects_unsupported_rates() {
+        for hz in [0, 10, 20, 60, 2000] {
+            assert_eq!(report_frequency_from_hz(hz), None, "{} Hz", hz);
+        }
+    }
+
+    #[test]
+    fn move_speed_is_read_from_move_commands_only() {
+        assert_eq!(
+            move_speed(&create_move_msg(0.1, 0.0, -0.2)),
+            Some(SpeedData::new(0.1, 0.0, -0.2))
+        );
+        assert_eq!(move_speed(&create_init_msg()), None);
+    }
+
+    fn api_control_initialize(message: &base_backend::ApiDown) -> Option<bool> {
+        match &message.down {
+            Some(base_backend::api_down::Down::BaseCommand(base_backend::BaseCommand {
+                command: Some(base_backend::base_command::Command::ApiControlInitialize(on)),
+            })) => Some(*on),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn init_and_close_toggle_api_control_initialize() {
+        assert_eq!(api_control_initialize(&create_init_msg()), Some(true));
+        assert_eq!(api_control_initialize(&create_close_msg()), Some(false));
+    }
+
+    #[test]
+    fn takeover_sends_the_initialize_request_again() {
+        assert_eq!(create_takeover_msg(), create_init_msg());
+    }
+
+    #[test]
+    fn over_range_speed_is_clamped_keeping_its_direction() {
+        let limit = SpeedLimit {
+            max_linear: 0.5,
+            max_angular: 1.0,
+        };
+        let clamped = limit.clamp(SpeedData::new(0.6, -0.8, -3.0));
+        assert!((clamped.x - 0.3).abs() < 1e-6, "{:?}", clamped);
+        assert!((clamped.y + 0.4).abs() < 1e-6, "{:?}", clamped);
+        assert_eq!(clamped.z, -1.0);
+
+        let within = SpeedData::new(0.1, 0.2, 0.5);
+        assert_eq!(limit.clamp(within), within);
+        assert_eq!(
+            limit.clamp(SpeedData::new(f32::NAN, 0.1, f32::INFINITY)),
+            SpeedData::new(0.0, 0.1, 0.0)
+        );
+    }
+
+    #[test]
+    fn only_move_commands_are_clamped() {
+        let limit = SpeedLimit::default();
+        let mut command = create_move_msg(5.0, 0.0, 0.0);
+        limit.apply(&mut command);
+        assert_eq!(command, create_move_msg(DEFAULT_MAX_LINEAR, 0.0, 0.0));
+
+        let mut init = create_init_msg();
+        limit.apply(&mut init);
+        assert_eq!(init, create_init_msg());
+    }
+
+    #[test]
+    fn control_needs_initialized_api_and_matching_session() {
+        let status = base_backend::BaseStatus {
+            api_control_initialized: true,
+            session_holder: 7,
+            ..Default::default()
+        };
+        assert!(holds_control(&status, 7));
+        assert!(!holds_control(&status, 8));
+        let uninitialized = base_backend::BaseStatus {
+            api_control_initialized: false,
+            ..status.clone()
+        };
+        assert!(!holds_control(&uninitialized, 7));
+    }
+
+    #[test]
+    fn parking_stop_fields_are_put_in_words() {
+        let fields = serde_json::json!({
+            "reason": "front bumper pressed",
+            "zone": 3,
+            "is_remotely_clearable": true,
+            "sensor": "",
+            "timeout": false,
+        });
+        assert_eq!(
+            describe_fields(&fields).unwrap(),
+            "remotely clearable, front bumper pressed, zone 3"
+        );
+        assert_eq!(
+            describe_fields(&serde_json::json!({"reason": ""})).unwrap(),
+            "no reason given"
+        );
+        // Nested messages aren't mapped, the caller shows the Debug form
+        assert_eq!(
+            describe_fields(&serde_json::json!({"sensors": [{"id": 1}]})),
+            None
+        );
+        assert_eq!(
+            describe_parking_stop(&Default::default()),
+            "no reason given"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+// ============================================================================
+// Config Module - Startup values and named settings profiles from a TOML file
+// ============================================================================
+//
+// Command line flags override the top-level values of the file, which override
+// the built-in defaults. Profiles then build on the result.
+//
+// Example:
+//
+//   linear_speed = 0.2
+//   report_frequency = 50
+//   theme = "monochrome"
+//
+//   default_profile = "indoor"
+//
+//   [profiles.indoor]
+//   linear_speed = 0.1
+//   smoothing = "ramp"
+//
+//   [profiles.warehouse]
+//   linear_speed = 0.5
+//   angular_speed = 1.0
+//   base_type = "mecanum"
+//   wheel_radius = 0.076
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::kinematics::{BaseGeometry, BaseType};
+use super::robot_ui::ThemeName;
+use super::smoothing::SmoothingProfile;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("failed to read config {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("malformed config {path}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    #[error("unknown profile '{0}'")]
+    UnknownProfile(String),
+}
+
+/// Settings that can be switched at runtime by selecting another profile
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Settings {
+    /// Speed of the X/Y movement keys in m/s
+    pub linear_speed: f32,
+    /// Speed of the rotation keys in rad/s
+    pub angular_speed: f32,
+    pub grid_step: f32,
+    pub strafe_distance: f32,
+    pub smoothing: SmoothingProfile,
+    pub base_type: Option<BaseType>,
+    pub wheel_radius: f32,
+    pub track_width: f32,
+    pub wheel_base: f32,
+}
+
+impl Settings {
+    /// Base geometry for the wheel speed display, if the wheel layout is known
+    pub fn geometry(&self) -> Option<BaseGeometry> {
+        self.base_type.map(|base_type| BaseGeometry {
+            base_type,
+            wheel_radius: self.wheel_radius,
+            track_width: self.track_width,
+            wheel_base: self.wheel_base,
+        })
+    }
+}
+
+/// One named profile. Values left out keep the command line / built-in setting.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub linear_speed: Option<f32>,
+    pub angular_speed: Option<f32>,
+    pub grid_step: Option<f32>,
+    pub strafe_distance: Option<f32>,
+    pub smoothing: Option<SmoothingProfile>,
+    pub base_type: Option<BaseType>,
+    pub wheel_radius: Option<f32>,
+    pub track_width: Option<f32>,
+    pub wheel_base: Option<f32>,
+}
+
+impl Profile {
+    /// `base` with every value this profile sets replaced
+    pub fn apply(&self, base: &Settings) -> Settings {
+        Settings {
+            linear_speed: self.linear_speed.unwrap_or(base.linear_speed),
+            angular_speed: self.angular_speed.unwrap_or(base.angular_speed),
+            grid_step: self.grid_step.unwrap_or(base.grid_step),
+            strafe_distance: self.strafe_distance.unwrap_or(base.strafe_distance),
+            smoothing: self.smoothing.unwrap_or(base.smoothing),
+            base_type: self.base_type.or(base.base_type),
+            wheel_radius: self.wheel_radius.unwrap_or(base.wheel_radius),
+            track_width: self.track_width.unwrap_or(base.track_width),
+            wheel_base: self.wheel_base.unwrap_or(base.wheel_base),
+        }
+    }
+}
+
+/// Values fixed at startup, after merging defaults, the file and the command line
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StartupSettings {
+    /// Speed of the X/Y movement keys in m/s
+    pub linear_speed: f32,
+    /// Speed of the rotation keys in rad/s
+    pub angular_speed: f32,
+    /// BaseStatus report rate requested from the robot, in Hz
+    pub report_frequency: u32,
+    /// A held key counts as released after this many ms without a repeat event
+    pub release_timeout_ms: u64,
+    /// Same before a newly pressed key's first repeat
+    pub first_press_timeout_ms: u64,
+    pub theme: ThemeName,
+}
+
+/// Startup values given on the command line, `None` where a flag was left out
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StartupOverrides {
+    pub linear_speed: Option<f32>,
+    pub angular_speed: Option<f32>,
+    pub report_frequency: Option<u32>,
+    pub release_timeout_ms: Option<u64>,
+    pub first_press_timeout_ms: Option<u64>,
+    pub theme: Option<ThemeName>,
+}
+
+/// Contents of the config file
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub linear_speed: Option<f32>,
+    pub angular_speed: Option<f32>,
+    pub report_frequency: Option<u32>,
+    pub release_timeout_ms: Option<u64>,
+    pub first_press_timeout_ms: Option<u64>,
+    pub theme: Option<ThemeName>,
+    /// Profile selected at startup when none is given on the command line
+    pub default_profile: Option<String>,
+    /// Profiles by name, switched through in name order
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let config: Config = toml::from_str(&text).map_err(|source| ConfigError::Parse {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        if let Some(name) = &config.default_profile {
+            config.profile(name)?;
+        }
+        Ok(config)
+    }
+
+    /// `defaults`, overridden by the values of this file, overridden by `cli`
+    pub fn merge(&self, defaults: StartupSettings, cli: StartupOverrides) -> StartupSettings {
+        StartupSettings {
+            linear_speed: cli
+                .linear_speed
+                .or(self.linear_speed)
+                .unwrap_or(defaults.linear_speed),
+            angular_speed: cli
+                .angular_speed
+                .or(self.angular_speed)
+                .unwrap_or(defaults.angular_speed),
+            report_frequency: cli
+                .report_frequency
+                .or(self.report_frequency)
+                .unwrap_or(defaults.report_frequency),
+            release_timeout_ms: cli
+                .release_timeout_ms
+                .or(self.release_timeout_ms)
+                .unwrap_or(defaults.release_timeout_ms),
+            first_press_timeout_ms: cli
+                .first_press_timeout_ms
+                .or(self.first_press_timeout_ms)
+                .unwrap_or(defaults.first_press_timeout_ms),
+            theme: cli.theme.or(self.theme).unwrap_or(defaults.theme),
+        }
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile, ConfigError> {
+        self.profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile(name.to_string()))
+    }
+
+    /// Name of the profile after `current`, wrapping around
+    pub fn next_profile(&self, current: Option<&str>) -> Option<&str> {
+        let mut names = self.profiles.keys().map(String::as_str);
+        match current {
+            Some(current) => names
+                .clone()
+                .skip_while(|name| *name != current)
+                .nth(1)
+                .or_else(|| names.next()),
+            None => names.next(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        default_profile = "indoor"
+
+        [profiles.indoor]
+        linear_speed = 0.1
+        smoothing = "ramp"
+
+        [profiles.warehouse]
+        linear_speed = 0.5
+        base_type = "mecanum"
+    "#;
+
+    fn base() -> Settings {
+        Settings {
+            linear_speed: 0.2,
+            angular_speed: 0.5,
+            grid_step: 0.5,
+            strafe_distance: 0.2,
+            smoothing: SmoothingProfile::None,
+            base_type: None,
+            wheel_radius: 0.05,
+            track_width: 0.3,
+            wheel_base: 0.3,
+        }
+    }
+
+    #[test]
+    fn profile_overrides_only_what_it_sets() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        let settings = config.profile("indoor").unwrap().apply(&base());
+        assert_eq!(settings.linear_speed, 0.1);
+        assert_eq!(settings.smoothing, SmoothingProfile::Ramp);
+        assert_eq!(settings.angular_speed, 0.5);
+        assert_eq!(settings.geometry(), None);
+
+        let settings = config.profile("warehouse").unwrap().apply(&base());
+        assert_eq!(
+            settings.geometry().map(|g| g.base_type),
+            Some(BaseType::Mecanum)
+        );
+    }
+
+    #[test]
+    fn next_profile_cycles_in_name_order() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        assert_eq!(config.next_profile(None), Some("indoor"));
+        assert_eq!(config.next_profile(Some("indoor")), Some("warehouse"));
+        assert_eq!(config.next_profile(Some("warehouse")), Some("indoor"));
+        assert_eq!(Config::default().next_profile(None), None);
+    }
+
+    fn startup_defaults() -> StartupSettings {
+        StartupSettings {
+            linear_speed: 0.1,
+            angular_speed: 0.5,
+            report_frequency: 50,
+            release_timeout_ms: 100,
+            first_press_timeout_ms: 500,
+            theme: ThemeName::Dark,
+        }
+    }
+
+    #[test]
+    fn load_merges_file_over_defaults_and_cli_over_file() {
+        let path = std::env::temp_dir().join(format!("robot-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+                linear_speed = 0.3
+                angular_speed = 1.2
+                report_frequency = 100
+                first_press_timeout_ms = 300
+                theme = "monochrome"
+
+                [profiles.slow]
+                linear_speed = 0.05
+            "#,
+        )
+        .unwrap();
+        let config = Config::load(&path);
+        std::fs::remove_file(&path).ok();
+        let config = config.unwrap();
+
+        let cli = StartupOverrides {
+            angular_speed: Some(0.8),
+            ..Default::default()
+        };
+        let merged = config.merge(startup_defaults(), cli);
+        assert_eq!(
+            merged,
+            StartupSettings {
+                linear_speed: 0.3,
+                angular_speed: 0.8,
+                report_frequency: 100,
+                release_timeout_ms: 100,
+                first_press_timeout_ms: 300,
+                theme: ThemeName::Monochrome,
+            }
+        );
+        assert!(config.profile("slow").is_ok());
+    }
+
+    #[test]
+    fn load_reports_malformed_file() {
+        let path = std::env::temp_dir().join(format!("robot-bad-{}.toml", std::process::id()));
+        std::fs::write(&path, "linear_speed = \"fast\"").unwrap();
+        let result = Config::load(&path);
+        std::fs::remove_file(&path).ok();
+        let error = result.unwrap_err();
+        assert!(matches!(error, ConfigError::Parse { .. }));
+        assert!(error.to_string().starts_with("malformed config"));
+    }
+
+    #[test]
+    fn theme_names_are_kebab_case() {
+        let config: Config = toml::from_str("theme = \"high-contrast\"").unwrap();
+        assert_eq!(config.theme, Some(ThemeName::HighContrast));
+        assert_eq!(ThemeName::Monochrome.theme(), ThemeName::Dark.theme());
+        assert!(toml::from_str::<Config>("theme = \"sepia\"").is_err());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(toml::from_str::<Config>("[profiles.a]\nlinear_sped = 1.0").is_err());
+    }
+}
@@ -0,0 +1,959 @@
+// ============================================================================
+// Connection Module - A base kept connected, in our control and commanded
+// ============================================================================
+//
+// `RobotConnection` is what the control loop drives: it reconnects with
+// backoff when the link drops, keeps it pinged, runs the handshake whenever
+// the base reports control uninitialized and turns each command tick into the
+// move the base may take, limited and gated by its emergency stop. On exit
+// `release` stops the base and hands control back.
+
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use prost::Message;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tungstenite::client::IntoClientRequest;
+
+use super::accel_limit::{AccelLimiter, AccelLimits};
+use super::base_backend;
+use super::breakaway::Breakaway;
+use super::client::{
+    create_close_msg, create_init_msg, create_move_msg, create_set_frequency_msg,
+    create_takeover_msg, gated_command, set_nodelay, SpeedLimit, WsStream,
+};
+use super::keyboard_input::SpeedData;
+use super::lock_recover::LockRecover;
+use super::log_buffer::LogBuffer;
+use super::receiver::{
+    mark_disconnected, spawn_keepalive, spawn_websocket_receiver, ReceiverOptions, Recording,
+    SharedState, PING_INTERVAL,
+};
+use super::robot_ui::{ControlState, ErrorMessage, ErrorSeverity};
+use super::session_log::Direction;
+
+/// Number of move commands the recover key sends back to back
+const RECOVERY_BURST: usize = 5;
+
+/// With --log-commands, how often an unchanged command is logged again
+const COMMAND_LOG_REPEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// First delay between reconnect attempts, doubled after each failure
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+
+/// Upper bound of the reconnect delay
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(10);
+
+/// Where and how to connect to the robot
+#[derive(Clone)]
+pub struct Endpoint {
+    url: String,
+    /// TLS settings for wss:// URLs, None for the system defaults
+    #[cfg(feature = "tls")]
+    connector: Option<tokio_tungstenite::Connector>,
+    /// Authorization header sent with the upgrade request
+    authorization: Option<tungstenite::http::HeaderValue>,
+}
+
+impl Endpoint {
+    #[cfg(feature = "tls")]
+    pub fn new(
+        url: String,
+        insecure: bool,
+        ca_cert: Option<&std::path::Path>,
+    ) -> Result<Self, String> {
+        if !insecure && ca_cert.is_none() {
+            return Ok(Self {
+                url,
+                connector: None,
+                authorization: None,
+            });
+        }
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(insecure);
+        if let Some(path) = ca_cert {
+            let pem = std::fs::read(path)
+                .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+            let cert = native_tls::Certificate::from_pem(&pem)
+                .map_err(|e| format!("invalid certificate {}: {}", path.display(), e))?;
+            builder.add_root_certificate(cert);
+        }
+        let connector = builder
+            .build()
+            .map_err(|e| format!("failed to set up TLS: {}", e))?;
+        Ok(Self {
+            url,
+            connector: Some(tokio_tungstenite::Connector::NativeTls(connector)),
+            authorization: None,
+        })
+    }
+
+    #[cfg(not(feature = "tls"))]
+    pub fn new(
+        url: String,
+        insecure: bool,
+        ca_cert: Option<&std::path::Path>,
+    ) -> Result<Self, String> {
+        if url.starts_with("wss://") || insecure || ca_cert.is_some() {
+            return Err("built without TLS support, rebuild with --features tls".to_string());
+        }
+        Ok(Self {
+            url,
+            authorization: None,
+        })
+    }
+
+    /// Authenticate the upgrade request with `Authorization: Bearer <token>`
+    pub fn with_token(mut self, token: Option<&str>) -> Result<Self, String> {
+        self.authorization = token
+            .map(|token| tungstenite::http::HeaderValue::from_str(&format!("Bearer {}", token)))
+            .transpose()
+            .map_err(|_| {
+                "the token contains characters not allowed in an HTTP header".to_string()
+            })?;
+        Ok(self)
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    async fn connect(&self) -> Result<WsStream, tungstenite::Error> {
+        let mut request = self.url.as_str().into_client_request()?;
+        if let Some(authorization) = &self.authorization {
+            request.headers_mut().insert(
+                tungstenite::http::header::AUTHORIZATION,
+                authorization.clone(),
+            );
+        }
+        #[cfg(feature = "tls")]
+        let (ws_stream, _) = tokio_tungstenite::connect_async_tls_with_config(
+            request,
+            None,
+            false,
+            self.connector.clone(),
+        )
+        .await?;
+        #[cfg(not(feature = "tls"))]
+        let (ws_stream, _) = tokio_tungstenite::connect_async(request).await?;
+        Ok(ws_stream)
+    }
+}
+
+/// Connection error for the operator. A rejected handshake is told apart from
+/// a robot that can't be reached, the fix is a different one.
+pub fn describe_connect_error(error: &tungstenite::Error) -> String {
+    match error {
+        tungstenite::Error::Http(response) => match response.status().as_u16() {
+            401 | 403 => format!(
+                "authentication failed (HTTP {}), check --token",
+                response.status()
+            ),
+            _ => format!("handshake rejected (HTTP {})", response.status()),
+        },
+        _ => format!("network error: {}", error),
+    }
+}
+
+/// How every base of the session is talked to
+#[derive(Clone, Copy, Debug)]
+pub struct LinkSettings {
+    pub report_frequency: base_backend::ReportFrequency,
+    /// Write the messages sent to the log
+    pub log_commands: bool,
+    pub speed_limit: SpeedLimit,
+    /// Raises tiny commands enough for the base to break away
+    pub breakaway: Breakaway,
+    /// Hard limit on how fast the sent speed may change, whatever produced it
+    pub accel_limits: AccelLimits,
+    /// Init unanswered by any BaseStatus for this long gets a warning
+    pub init_status_timeout: Duration,
+}
+
+/// What the driven base is to do on a command tick
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Drive {
+    /// A latched operator stop: zero whatever the control state and nothing
+    /// else, not even init, until it is released
+    Stop,
+    Command(Command),
+}
+
+/// A command tick of the driven base
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Command {
+    /// Speed to move with while we hold control
+    pub speed: SpeedData,
+    /// Slow down to zero within the accel limits and stay there, e.g. while paused
+    pub hold: bool,
+    /// Recover: re-init if needed and send a burst of this speed
+    pub recover: Option<SpeedData>,
+    /// Ask the session holding control to hand it over
+    pub takeover: bool,
+}
+
+/// What a command tick sent
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Tick {
+    pub takeover_sent: bool,
+}
+
+/// Why releasing control on exit didn't complete
+#[derive(Debug, thiserror::Error)]
+pub enum ReleaseError {
+    #[error("failed to send the close message to {0}, the base may still hold the session")]
+    NotSent(String),
+    #[error("{url} did not confirm releasing API control within {} ms", timeout.as_millis())]
+    NotConfirmed { url: String, timeout: Duration },
+}
+
+/// One base of the session. The control loop drives the active one, the
+/// others are kept connected, holding control and standing still.
+pub struct RobotConnection {
+    endpoint: Endpoint,
+    shared: SharedState,
+    options: ReceiverOptions,
+    settings: LinkSettings,
+    // Dropped when the connection is lost, then re-established in the background
+    connection: Option<Connection>,
+    reconnect: Reconnect,
+    accel_limiter: AccelLimiter,
+    // Last speed sent to the base
+    last_command: SpeedData,
+    init_watch: InitWatch,
+}
+
+impl RobotConnection {
+    /// Connect to `endpoint` and start receiving into `shared`
+    pub async fn connect(
+        endpoint: Endpoint,
+        shared: SharedState,
+        options: ReceiverOptions,
+        settings: LinkSettings,
+    ) -> Result<Self, tungstenite::Error> {
+        let connection = connect_and_spawn(
+            endpoint.clone(),
+            shared.clone(),
+            options.clone(),
+            settings.log_commands,
+            settings.speed_limit,
+        )
+        .await?;
+        Ok(Self {
+            endpoint,
+            shared,
+            options,
+            settings,
+            connection: Some(connection),
+            reconnect: Reconnect::default(),
+            accel_limiter: AccelLimiter::new(settings.accel_limits),
+            last_command: SpeedData::ZERO,
+            init_watch: InitWatch::default(),
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        self.endpoint.url()
+    }
+
+    pub fn shared(&self) -> &SharedState {
+        &self.shared
+    }
+
+    /// Speed of the last move command sent
+    pub fn last_command(&self) -> SpeedData {
+        self.last_command
+    }
+
+    /// The connection is up and its receiver running
+    pub fn is_connected(&self) -> bool {
+        self.connection
+            .as_ref()
+            .is_some_and(|link| !link.receiver.is_finished())
+    }
+
+    /// Start driving from standstill, e.g. after switching to this base
+    pub fn reset_drive(&mut self) {
+        self.last_command = SpeedData::ZERO;
+        self.accel_limiter.reset();
+        self.init_watch.restart();
+    }
+
+    /// Reconnect a lost connection and ping a live one. Returns whether it is
+    /// up; once a reconnect succeeds the state is Uninitialized, so the next
+    /// command tick runs the handshake again.
+    pub async fn keep_alive(&mut self) -> bool {
+        // A finished receiver means the robot or the network closed the connection
+        if !self.is_connected() {
+            self.connection = None;
+        }
+        let Some(link) = self.connection.as_mut() else {
+            self.last_command = SpeedData::ZERO;
+            self.accel_limiter.reset();
+            if let Some(reconnected) = self
+                .reconnect
+                .poll(
+                    &self.endpoint,
+                    &self.shared,
+                    &self.options,
+                    self.settings.log_commands,
+                    self.settings.speed_limit,
+                )
+                .await
+            {
+                self.connection = Some(reconnected);
+                self.init_watch.restart();
+            }
+            return false;
+        };
+        if link.last_ping.elapsed() >= PING_INTERVAL {
+            link.last_ping = Instant::now();
+            if let Err(e) = link.sink.send_ping().await {
+                mark_disconnected(&self.shared, &e.to_string());
+                self.connection = None;
+                return false;
+            }
+        }
+        true
+    }
+
+    /// One command tick of the driven base, after `keep_alive` found the
+    /// connection up. A failed send drops the connection for a reconnect.
+    pub async fn drive(&mut self, drive: Drive) -> Tick {
+        let Some(mut link) = self.connection.take() else {
+            return Tick::default();
+        };
+        match self.send_tick(&mut link, drive).await {
+            Ok(tick) => {
+                self.connection = Some(link);
+                tick
+            }
+            Err(e) => {
+                mark_disconnected(&self.shared, &e.to_string());
+                Tick::default()
+            }
+        }
+    }
+
+    /// One tick of a base that isn't driven: reconnect it when lost, take
+    /// control and hold it with zero commands, so switching to it is immediate
+    /// and a base switched away from stops at once
+    pub async fn idle(&mut self) {
+        if !self.keep_alive().await {
+            return;
+        }
+        let state = *self.shared.control_state.lock_recover();
+        let Some(link) = self.connection.as_mut() else {
+            return;
+        };
+        if let Err(e) = hold_still(link, state, &self.settings).await {
+            mark_disconnected(&self.shared, &e.to_string());
+            self.connection = None;
+        }
+    }
+
+    /// Send a message as it is, e.g. one of a recorded session
+    pub async fn send(
+        &mut self,
+        message: &base_backend::ApiDown,
+    ) -> Result<(), tungstenite::Error> {
+        match self.connection.as_mut() {
+            Some(link) => link.sink.send_api_down(message).await,
+            None => Err(tungstenite::Error::AlreadyClosed),
+        }
+    }
+
+    /// Stop the base if we hold control and hand control back, waiting up to
+    /// `timeout` for the base to confirm. Nothing is sent without a connection.
+    pub async fn release(&mut self, timeout: Duration) -> Result<(), ReleaseError> {
+        let url = self.endpoint.url.clone();
+        let Some(link) = self.connection.as_mut() else {
+            return Ok(());
+        };
+        // Whatever the last command tick sent, the last move is a stop
+        if *self.shared.control_state.lock_recover() == ControlState::CanMove {
+            let stop = create_move_msg(0.0, 0.0, 0.0);
+            let stopped = tokio::time::timeout(timeout, link.sink.send_api_down(&stop))
+                .await
+                .is_ok_and(|sent| sent.is_ok());
+            if !stopped {
+                warn!(target: "command", "stop not sent to {} before closing", url);
+            }
+        }
+        let close_message = create_close_msg();
+        let close_sent_at = Instant::now();
+        // A stuck send is given up on too, exiting must not hang on the network
+        let sent = tokio::time::timeout(timeout, link.sink.send_api_down(&close_message))
+            .await
+            .is_ok_and(|sent| sent.is_ok());
+        if !sent {
+            warn!(target: "connection", "close message not sent to {}", url);
+            return Err(ReleaseError::NotSent(url));
+        }
+        if !wait_for_release(&self.shared, close_sent_at, timeout).await {
+            warn!(target: "connection", "release not confirmed by {}", url);
+            return Err(ReleaseError::NotConfirmed { url, timeout });
+        }
+        Ok(())
+    }
+
+    // Send what the control state calls for on this tick
+    async fn send_tick(
+        &mut self,
+        link: &mut Connection,
+        drive: Drive,
+    ) -> Result<Tick, tungstenite::Error> {
+        let command = match drive {
+            Drive::Stop => {
+                link.sink
+                    .send_api_down(&create_move_msg(0.0, 0.0, 0.0))
+                    .await?;
+                self.accel_limiter.sent(SpeedData::ZERO, Instant::now());
+                *self.shared.commanded.lock_recover() = SpeedData::ZERO;
+                self.last_command = SpeedData::ZERO;
+                return Ok(Tick::default());
+            }
+            Drive::Command(command) => command,
+        };
+
+        let state = *self.shared.control_state.lock_recover();
+        if state != ControlState::Uninitialized {
+            self.init_watch.restart();
+        }
+        if let Some(speed) = command.recover {
+            self.recover(link, state, speed, command.hold).await?;
+        }
+        let mut tick = Tick::default();
+        if command.takeover {
+            link.sink.send_api_down(&create_takeover_msg()).await?;
+            tick.takeover_sent = true;
+        }
+
+        match state {
+            ControlState::Uninitialized => {
+                self.last_command = SpeedData::ZERO;
+                self.accel_limiter.reset();
+                self.warn_if_init_unanswered();
+                link.sink
+                    .send_api_down(&create_set_frequency_msg(self.settings.report_frequency))
+                    .await?;
+                link.sink.send_api_down(&create_init_msg()).await?;
+            }
+            ControlState::CanMove => {
+                // The state is read again so a status that arrived during this tick counts
+                let now = Instant::now();
+                let requested = limited_speed(
+                    &self.accel_limiter,
+                    &self.settings.breakaway,
+                    command.speed,
+                    command.hold,
+                    now,
+                );
+                let Some(speed) = gated_command(
+                    *self.shared.control_state.lock_recover(),
+                    *self.shared.emergency_stop.lock_recover(),
+                    requested,
+                ) else {
+                    return Ok(tick);
+                };
+                if self.shared.protocol.lock_recover().blocked {
+                    self.last_command = SpeedData::ZERO;
+                    return Ok(tick);
+                }
+                link.sink
+                    .send_api_down(&create_move_msg(speed.x, speed.y, speed.z))
+                    .await?;
+                self.accel_limiter.sent(speed, now);
+                self.shared.latency.lock_recover().command_sent(now);
+                *self.shared.commanded.lock_recover() = speed;
+                self.last_command = speed;
+            }
+            ControlState::InitializedButNotHold => {
+                // Wait for control authority
+                self.last_command = SpeedData::ZERO;
+                self.accel_limiter.reset();
+            }
+        }
+        Ok(tick)
+    }
+
+    // One-shot recovery after e.g. the base's command watchdog tripped during a
+    // network stall: re-init if needed and push a burst of the current command
+    async fn recover(
+        &mut self,
+        link: &mut Connection,
+        state: ControlState,
+        speed: SpeedData,
+        hold: bool,
+    ) -> Result<(), tungstenite::Error> {
+        let (reinit, burst, action) = match state {
+            ControlState::Uninitialized => (true, true, "re-sent init and a command burst"),
+            ControlState::CanMove => (false, true, "re-sent a command burst"),
+            ControlState::InitializedButNotHold => {
+                (false, false, "another session holds control, nothing sent")
+            }
+        };
+        if reinit {
+            // A fresh init gets the full timeout before the warning again
+            self.init_watch.restart();
+            link.sink.send_api_down(&create_init_msg()).await?;
+        }
+        if burst {
+            let now = Instant::now();
+            let speed = limited_speed(
+                &self.accel_limiter,
+                &self.settings.breakaway,
+                speed,
+                hold,
+                now,
+            );
+            let move_message = create_move_msg(speed.x, speed.y, speed.z);
+            for _ in 0..RECOVERY_BURST {
+                link.sink.send_api_down(&move_message).await?;
+            }
+            self.accel_limiter.sent(speed, now);
+        }
+        *self.shared.error_message.lock_recover() =
+            ErrorMessage::new(format!("Recover: {}", action));
+        Ok(())
+    }
+
+    // Some firmware never answers init with a BaseStatus; don't leave the
+    // operator looking at "Initializing..." without a hint
+    fn warn_if_init_unanswered(&mut self) {
+        let last_status = *self.shared.last_status.lock_recover();
+        if self.init_watch.unanswered(
+            Instant::now(),
+            last_status,
+            self.settings.init_status_timeout,
+        ) {
+            *self.shared.error_message.lock_recover() =
+                ErrorMessage::new("No status from robot after init - check firmware".to_string());
+        }
+    }
+}
+
+/// When the current round of init messages started, and whether we already
+/// warned that the robot never answered it
+#[derive(Clone, Copy, Debug, Default)]
+struct InitWatch {
+    sent_at: Option<Instant>,
+    warned: bool,
+}
+
+impl InitWatch {
+    // Start a new round with the next init sent
+    fn restart(&mut self) {
+        *self = Self::default();
+    }
+
+    // Init is sent at `now`. True once per round, when no status arrived for
+    // `timeout` since the round started.
+    fn unanswered(
+        &mut self,
+        now: Instant,
+        last_status: Option<Instant>,
+        timeout: Duration,
+    ) -> bool {
+        let sent_at = *self.sent_at.get_or_insert(now);
+        let answered = last_status.is_some_and(|t| t >= sent_at);
+        if self.warned || answered || now.duration_since(sent_at) <= timeout {
+            return false;
+        }
+        self.warned = true;
+        true
+    }
+}
+
+/// Open connection to the robot: commands go out through `sink`, frames are
+/// handled by the `receiver` task
+struct Connection {
+    sink: CommandSink<futures_util::stream::SplitSink<WsStream, tungstenite::Message>>,
+    receiver: tokio::task::JoinHandle<()>,
+    keepalive: tokio::task::JoinHandle<()>,
+    last_ping: Instant,
+}
+
+impl Drop for Connection {
+    // A dropped connection must not keep feeding frames into the shared state
+    fn drop(&mut self) {
+        self.receiver.abort();
+        self.keepalive.abort();
+    }
+}
+
+// The speed a command tick sends at `now`: zero while held, after the
+// breakaway boost and within the accel limits. A hold slows down at the decel
+// rate like any other stop, only the operator's estop (`Drive::Stop`) skips it.
+fn limited_speed(
+    limiter: &AccelLimiter,
+    breakaway: &Breakaway,
+    speed: SpeedData,
+    hold: bool,
+    now: Instant,
+) -> SpeedData {
+    let target = if hold { SpeedData::ZERO } else { speed };
+    limiter.limit(breakaway.apply(target), now)
+}
+
+// Connect to `endpoint` and start receiving into `shared`. Used for the first
+// connection and for every reconnect.
+async fn connect_and_spawn(
+    endpoint: Endpoint,
+    shared: SharedState,
+    options: ReceiverOptions,
+    log_commands: bool,
+    speed_limit: SpeedLimit,
+) -> Result<Connection, tungstenite::Error> {
+    // The receiver keeps the span open, so it lasts until the link is lost
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!("connection", url = %endpoint.url);
+    let connect = open_connection(endpoint, shared, options, log_commands, speed_limit);
+    #[cfg(feature = "tracing")]
+    let connect = tracing::Instrument::instrument(connect, span);
+    connect.await
+}
+
+// connect_and_spawn, inside the connection's span
+async fn open_connection(
+    endpoint: Endpoint,
+    shared: SharedState,
+    options: ReceiverOptions,
+    log_commands: bool,
+    speed_limit: SpeedLimit,
+) -> Result<Connection, tungstenite::Error> {
+    info!(target: "connection", "connecting to {}", endpoint.url);
+    let ws_stream = endpoint.connect().await.inspect_err(|e| {
+        warn!(target: "connection", "connecting to {} failed: {}", endpoint.url, e);
+    })?;
+    info!(target: "connection", "connected to {}", endpoint.url);
+
+    // Set TCP nodelay for better performance. Only an optimization, so a
+    // failure is logged and we carry on.
+    if let Err(e) = set_nodelay(ws_stream.get_ref()) {
+        shared
+            .log
+            .lock_recover()
+            .push(format!("Warning: failed to set TCP nodelay: {}", e));
+    }
+    let (ws_sink, ws_stream) = ws_stream.split();
+    let sink = CommandSink::new(
+        ws_sink,
+        log_commands.then(|| shared.log.clone()),
+        options.recording.clone(),
+        speed_limit,
+    );
+    let last_frame = Arc::new(Mutex::new(Instant::now()));
+    let receiver = spawn_websocket_receiver(ws_stream, shared.clone(), options, last_frame.clone());
+    let keepalive = spawn_keepalive(receiver.abort_handle(), last_frame, shared);
+    Ok(Connection {
+        sink,
+        receiver,
+        keepalive,
+        last_ping: Instant::now(),
+    })
+}
+
+// Keep the connection in our control without moving the base
+async fn hold_still(
+    link: &mut Connection,
+    state: ControlState,
+    settings: &LinkSettings,
+) -> Result<(), tungstenite::Error> {
+    match state {
+        ControlState::Uninitialized => {
+            link.sink
+                .send_api_down(&create_set_frequency_msg(settings.report_frequency))
+                .await?;
+            link.sink.send_api_down(&create_init_msg()).await
+        }
+        ControlState::CanMove => {
+            link.sink
+                .send_api_down(&create_move_msg(0.0, 0.0, 0.0))
+                .await
+        }
+        ControlState::InitializedButNotHold => Ok(()),
+    }
+}
+
+// Wait until a BaseStatus received after `since` reports API control as released.
+// Returns false if that doesn't happen within `timeout`.
+async fn wait_for_release(shared: &SharedState, since: Instant, timeout: Duration) -> bool {
+    let deadline = since + timeout;
+    while Instant::now() < deadline {
+        let fresh_status = matches!(*shared.last_status.lock_recover(), Some(t) if t >= since);
+        if fresh_status && *shared.control_state.lock_recover() == ControlState::Uninitialized {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    false
+}
+
+/// Background reconnect attempts with exponential backoff
+#[derive(Default)]
+struct Reconnect {
+    // Failed attempts since the last successful connection
+    failures: u32,
+    next_attempt: Option<Instant>,
+    pending: Option<tokio::task::JoinHandle<Result<Connection, tungstenite::Error>>>,
+}
+
+impl Reconnect {
+    // Start an attempt when one is due and collect its result when it is done.
+    // Returns the new connection once an attempt succeeds.
+    async fn poll(
+        &mut self,
+        endpoint: &Endpoint,
+        shared: &SharedState,
+        options: &ReceiverOptions,
+        log_commands: bool,
+        speed_limit: SpeedLimit,
+    ) -> Option<Connection> {
+        let Some(pending) = &self.pending else {
+            if self.next_attempt.is_none_or(|at| Instant::now() >= at) {
+                self.pending = Some(tokio::spawn(connect_and_spawn(
+                    endpoint.clone(),
+                    shared.clone(),
+                    options.clone(),
+                    log_commands,
+                    speed_limit,
+                )));
+            }
+            return None;
+        };
+        if !pending.is_finished() {
+            return None;
+        }
+        let error = match self.pending.take()?.await {
+            Ok(Ok(connection)) => {
+                self.failures = 0;
+                self.next_attempt = None;
+                shared.log.lock_recover().push("Reconnected");
+                *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+                    "Reconnected, initializing...".to_string(),
+                    ErrorSeverity::Info,
+                );
+                return Some(connection);
+            }
+            Ok(Err(e)) => describe_connect_error(&e),
+            Err(e) => e.to_string(),
+        };
+        let delay = RECONNECT_INITIAL_DELAY
+            .saturating_mul(2u32.saturating_pow(self.failures))
+            .min(RECONNECT_MAX_DELAY);
+        self.failures += 1;
+        self.next_attempt = Some(Instant::now() + delay);
+        let message = format!(
+            "Reconnect failed: {}, retrying in {:.1} s",
+            error,
+            delay.as_secs_f32()
+        );
+        shared.log.lock_recover().push(message.clone());
+        *shared.error_message.lock_recover() = ErrorMessage::new(message);
+        None
+    }
+}
+
+/// Sink for outgoing ApiDown messages. When a log is attached, every message
+/// sent is also written to it; an identical message of the same kind is logged
+/// at most once per `COMMAND_LOG_REPEAT_INTERVAL` so the 100Hz stream of move
+/// commands doesn't flood the log.
+struct CommandSink<S> {
+    sink: S,
+    log: Option<Arc<Mutex<LogBuffer>>>,
+    last_logged: HashMap<&'static str, (base_backend::ApiDown, Instant)>,
+    recording: Option<Recording>,
+    speed_limit: SpeedLimit,
+}
+
+impl<S> CommandSink<S>
+where
+    S: futures_util::Sink<tungstenite::Message> + Unpin,
+    S::Error: std::fmt::Display,
+{
+    fn new(
+        sink: S,
+        log: Option<Arc<Mutex<LogBuffer>>>,
+        recording: Option<Recording>,
+        speed_limit: SpeedLimit,
+    ) -> Self {
+        Self {
+            sink,
+            log,
+            last_logged: HashMap::new(),
+            recording,
+            speed_limit,
+        }
+    }
+
+    // Encode an ApiDown message and send it as a binary frame. Every move
+    // command, whatever produced it, is capped by the speed limit here; the
+    // log and the recording show what was actually sent.
+    async fn send_api_down(&mut self, message: &base_backend::ApiDown) -> Result<(), S::Error> {
+        let mut message = message.clone();
+        self.speed_limit.apply(&mut message);
+        let message = &message;
+        self.log_command(message);
+        if let Some(recording) = &self.recording {
+            recording.record(Direction::Down, message);
+        }
+        let sent = self
+            .sink
+            .send(tungstenite::Message::Binary(message.encode_to_vec().into()))
+            .await;
+        if let Err(e) = &sent {
+            let (kind, _) = describe_api_down(message);
+            warn!(target: "command", "sending {} failed: {}", kind, e);
+        }
+        sent
+    }
+
+    async fn send_ping(&mut self) -> Result<(), S::Error> {
+        self.sink
+            .send(tungstenite::Message::Ping(Vec::new().into()))
+            .await
+    }
+
+    fn log_command(&mut self, message: &base_backend::ApiDown) {
+        let Some(log) = &self.log else {
+            return;
+        };
+        let (kind, text) = describe_api_down(message);
+        let now = Instant::now();
+        if let Some((last, at)) = self.last_logged.get(kind) {
+            if last == message && now.duration_since(*at) < COMMAND_LOG_REPEAT_INTERVAL {
+                return;
+            }
+        }
+        self.last_logged.insert(kind, (message.clone(), now));
+        log.lock_recover().push(format!("-> {}", text));
+    }
+}
+
+// Short kind tag and human readable description of an outgoing message
+fn describe_api_down(message: &base_backend::ApiDown) -> (&'static str, String) {
+    use base_backend::api_down::Down;
+    use base_backend::base_command::Command;
+    use base_backend::simple_base_move_command::Command as MoveCommand;
+
+    match &message.down {
+        Some(Down::SetReportFrequency(frequency)) => {
+            let name = base_backend::ReportFrequency::try_from(*frequency)
+                .map(|f| format!("{:?}", f))
+                .unwrap_or_else(|_| frequency.to_string());
+            ("frequency", format!("set report frequency {}", name))
+        }
+        Some(Down::BaseCommand(base_backend::BaseCommand {
+            command: Some(Command::ApiControlInitialize(enable)),
+        })) => {
+            if *enable {
+                ("init", "init (ApiControlInitialize true)".to_string())
+            } else {
+                ("close", "close (ApiControlInitialize false)".to_string())
+            }
+        }
+        Some(Down::BaseCommand(base_backend::BaseCommand {
+            command:
+                Some(Command::SimpleMoveCommand(base_backend::SimpleBaseMoveCommand {
+                    command: Some(MoveCommand::XyzSpeed(speed)),
+                })),
+        })) => (
+            "move",
+            format!(
+                "move x={:+.3} y={:+.3} z={:+.3}",
+                speed.speed_x, speed.speed_y, speed.speed_z
+            ),
+        ),
+        _ => ("other", format!("{:?}", message)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+    #[test]
+    fn hold_after_full_speed_ramps_down_at_the_decel_rate() {
+        let tick = Duration::from_millis(20);
+        let mut limiter = AccelLimiter::new(AccelLimits {
+            linear_decel: Some(1.0),
+            ..Default::default()
+        });
+        let breakaway = Breakaway::default();
+        let start = Instant::now();
+        limiter.sent(SpeedData::new(0.5, 0.0, 0.0), start);
+
+        let mut previous = 0.5;
+        for i in 1..=30 {
+            let now = start + tick * i;
+            let speed = limited_speed(
+                &limiter,
+                &breakaway,
+                SpeedData::new(0.5, 0.0, 0.0),
+                true,
+                now,
+            );
+            let expected = (0.5 - 1.0 * tick.as_secs_f32() * i as f32).max(0.0);
+            assert!((speed.x - expected).abs() < 1e-4, "tick {}: {:?}", i, speed);
+            assert!(previous - speed.x <= 1.0 * tick.as_secs_f32() + 1e-4);
+            previous = speed.x;
+            limiter.sent(speed, now);
+        }
+        assert_eq!(previous, 0.0);
+    }
+
+    #[test]
+    fn unanswered_init_is_warned_about_once() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut watch = InitWatch::default();
+        assert!(!watch.unanswered(start, None, INIT_TIMEOUT));
+        assert!(!watch.unanswered(at(400), None, INIT_TIMEOUT));
+        assert!(watch.unanswered(at(600), None, INIT_TIMEOUT));
+        assert!(!watch.unanswered(at(700), None, INIT_TIMEOUT));
+        // A status from before the round doesn't answer it
+        let mut watch = InitWatch::default();
+        watch.unanswered(at(100), None, INIT_TIMEOUT);
+        assert!(watch.unanswered(at(700), Some(start), INIT_TIMEOUT));
+        // One from after does
+        let mut watch = InitWatch::default();
+        watch.unanswered(start, None, INIT_TIMEOUT);
+        assert!(!watch.unanswered(at(600), Some(at(50)), INIT_TIMEOUT));
+    }
+
+    #[test]
+    fn re_init_restarts_the_init_watch() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut watch = InitWatch::default();
+        watch.unanswered(start, None, INIT_TIMEOUT);
+        assert!(watch.unanswered(at(600), None, INIT_TIMEOUT));
+
+        // The recover key re-sent init: the full timeout runs again before
+        // the next warning
+        watch.restart();
+        assert!(!watch.unanswered(at(700), None, INIT_TIMEOUT));
+        assert!(!watch.unanswered(at(1100), None, INIT_TIMEOUT));
+        assert!(watch.unanswered(at(1300), None, INIT_TIMEOUT));
+    }
+
+    #[test]
+    fn rejected_token_is_told_apart_from_network_errors() {
+        let rejected = |status: u16| {
+            let response = tungstenite::http::Response::builder()
+                .status(status)
+                .body(None)
+                .unwrap();
+            describe_connect_error(&tungstenite::Error::Http(response))
+        };
+        assert!(rejected(401).starts_with("authentication failed (HTTP 401"));
+        assert!(rejected(403).starts_with("authentication failed (HTTP 403"));
+        assert!(rejected(404).starts_with("handshake rejected (HTTP 404"));
+        let network = tungstenite::Error::Io(std::io::ErrorKind::ConnectionRefused.into());
+        assert!(describe_connect_error(&network).starts_with("network error"));
+    }
+}
@@ -0,0 +1,67 @@
+// ============================================================================
+// Frame Rate Module - Rolling rate of successful UI draws
+// ============================================================================
+
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Number of frame timestamps the rate is averaged over
+const FRAME_WINDOW: usize = 30;
+
+#[derive(Clone, Debug, Default)]
+pub struct FrameRate {
+    frames: VecDeque<Instant>,
+}
+
+impl FrameRate {
+    /// Note a frame that was drawn at `at`
+    pub fn record(&mut self, at: Instant) {
+        if self.frames.len() >= FRAME_WINDOW {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(at);
+    }
+
+    /// Frames per second over the kept window, None until two frames were drawn
+    pub fn rate(&self) -> Option<f32> {
+        let (first, last) = (self.frames.front()?, self.frames.back()?);
+        let span = last.saturating_duration_since(*first).as_secs_f32();
+        if span <= 0.0 {
+            return None;
+        }
+        Some((self.frames.len() - 1) as f32 / span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn rate_from_frame_intervals() {
+        let start = Instant::now();
+        let mut frames = FrameRate::default();
+        frames.record(start);
+        assert_eq!(frames.rate(), None);
+        for i in 1..=4 {
+            frames.record(start + Duration::from_millis(50 * i));
+        }
+        assert_eq!(frames.rate(), Some(20.0));
+    }
+
+    #[test]
+    fn only_the_latest_frames_count() {
+        let start = Instant::now();
+        let mut frames = FrameRate::default();
+        // A long pause before the window...
+        frames.record(start);
+        let resumed = start + Duration::from_secs(10);
+        // ...is pushed out by the frames after it
+        for i in 0..FRAME_WINDOW as u32 {
+            frames.record(resumed + Duration::from_millis(100) * i);
+        }
+        let rate = frames.rate().unwrap();
+        assert!((rate - 10.0).abs() < 1e-3, "{}", rate);
+    }
+}
@@ -0,0 +1,138 @@
+// ============================================================================
+// Gamepad Input Module - Drive the base from a USB gamepad (gilrs feature)
+// ============================================================================
+//
+// Left stick is X/Y linear speed, right stick horizontal is Z rotation, the
+// south face button (A on Xbox layouts) exits.
+
+use crossterm::event::KeyCode;
+use gilrs::{Axis, Button, EventType, Gilrs};
+use std::collections::HashMap;
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+
+use super::input_source::InputSource;
+use super::keyboard_input::{AxisInvert, KeyState, SpeedData};
+use super::lock_recover::LockRecover;
+
+/// Stick deflection below this is treated as centered, worn sticks rarely
+/// rest at exactly zero
+const STICK_DEADZONE: f32 = 0.1;
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub struct GamepadInput {
+    speed: Arc<Mutex<SpeedData>>,
+    should_exit: Arc<Mutex<bool>>,
+    linear_speed: Arc<Mutex<f32>>,
+    angular_speed: Arc<Mutex<f32>>,
+    invert: AxisInvert,
+}
+
+impl GamepadInput {
+    pub fn new(linear_speed: f32, angular_speed: f32, invert: AxisInvert) -> std::io::Result<Self> {
+        let input = Self {
+            speed: Arc::new(Mutex::new(SpeedData::default())),
+            should_exit: Arc::new(Mutex::new(false)),
+            linear_speed: Arc::new(Mutex::new(linear_speed)),
+            angular_speed: Arc::new(Mutex::new(angular_speed)),
+            invert,
+        };
+        input.spawn_handler()?;
+        Ok(input)
+    }
+
+    // Gilrs isn't Send on every platform, so it is created and polled on its
+    // own thread. Initialization errors are handed back to the caller.
+    fn spawn_handler(&self) -> std::io::Result<()> {
+        let speed = self.speed.clone();
+        let should_exit = self.should_exit.clone();
+        let linear_speed = self.linear_speed.clone();
+        let angular_speed = self.angular_speed.clone();
+        let (ready_tx, ready_rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut gilrs = match Gilrs::new() {
+                Ok(gilrs) => {
+                    ready_tx.send(Ok(())).ok();
+                    gilrs
+                }
+                Err(e) => {
+                    ready_tx.send(Err(e.to_string())).ok();
+                    return;
+                }
+            };
+            loop {
+                while let Some(event) = gilrs.next_event() {
+                    if let EventType::ButtonPressed(Button::South, _) = event.event {
+                        *should_exit.lock_recover() = true;
+                    }
+                }
+
+                // First connected pad drives, a disconnect stops the base
+                let sticks =
+                    gilrs
+                        .gamepads()
+                        .find(|(_, pad)| pad.is_connected())
+                        .map(|(_, pad)| {
+                            (
+                                deadzone(pad.value(Axis::LeftStickY)),
+                                deadzone(pad.value(Axis::LeftStickX)),
+                                deadzone(pad.value(Axis::RightStickX)),
+                            )
+                        });
+                let linear = *linear_speed.lock_recover();
+                let angular = *angular_speed.lock_recover();
+                *speed.lock_recover() = match sticks {
+                    // Stick right is +y, but turns the base clockwise (-z)
+                    Some((forward, right, turn)) => {
+                        SpeedData::new(forward * linear, right * linear, -turn * angular)
+                    }
+                    None => SpeedData::ZERO,
+                };
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(std::io::Error::other(e)),
+            Err(_) => Err(std::io::Error::other(
+                "gamepad thread exited during startup",
+            )),
+        }
+    }
+}
+
+impl InputSource for GamepadInput {
+    fn get_speed(&self) -> SpeedData {
+        self.invert.apply(*self.speed.lock_recover())
+    }
+
+    fn set_speeds(&self, linear_speed: f32, angular_speed: f32) {
+        *self.linear_speed.lock_recover() = linear_speed;
+        *self.angular_speed.lock_recover() = angular_speed;
+    }
+
+    fn speeds(&self) -> (f32, f32) {
+        (
+            *self.linear_speed.lock_recover(),
+            *self.angular_speed.lock_recover(),
+        )
+    }
+
+    fn should_exit(&self) -> bool {
+        *self.should_exit.lock_recover()
+    }
+
+    fn get_pressed_keys(&self) -> HashMap<KeyCode, KeyState> {
+        HashMap::new()
+    }
+}
+
+fn deadzone(value: f32) -> f32 {
+    if value.abs() < STICK_DEADZONE {
+        0.0
+    } else {
+        value
+    }
+}
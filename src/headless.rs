@@ -0,0 +1,109 @@
+// ============================================================================
+// Headless Module - Plain status lines instead of the terminal UI
+// ============================================================================
+//
+// For CI and SSH sessions without a TTY. Nothing is redrawn in place: a status
+// line is printed every HEADLESS_STATUS_INTERVAL, and messages and connection
+// attempts as they happen.
+
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+use super::robot_ui::{Ui, UiView};
+use super::status_line::format_status_line;
+
+/// A status line is printed at most this often
+const HEADLESS_STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct HeadlessUi<W: Write = io::Stdout> {
+    out: W,
+    last_status: Option<Instant>,
+    // Timestamp of the last message printed, each one is printed once
+    last_message: Option<Instant>,
+    last_connection: Option<(String, Option<String>)>,
+}
+
+impl HeadlessUi {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> HeadlessUi<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            last_status: None,
+            last_message: None,
+            last_connection: None,
+        }
+    }
+}
+
+impl<W: Write> Ui for HeadlessUi<W> {
+    fn draw(&mut self, view: &UiView) -> io::Result<()> {
+        let message = view.error_message;
+        if message.timestamp.is_some()
+            && message.timestamp != self.last_message
+            && !message.message.is_empty()
+        {
+            self.last_message = message.timestamp;
+            writeln!(self.out, "{}", message.message)?;
+        }
+
+        let now = Instant::now();
+        if self
+            .last_status
+            .is_some_and(|last| now.duration_since(last) < HEADLESS_STATUS_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_status = Some(now);
+        writeln!(
+            self.out,
+            "{}",
+            format_status_line(
+                view.control_state,
+                view.emergency_stop,
+                view.target_speed,
+                view.actual_speed,
+            )
+        )?;
+        self.out.flush()
+    }
+
+    fn draw_connection(&mut self, url: &str, error: Option<&str>) -> io::Result<()> {
+        let attempt = (url.to_string(), error.map(str::to_string));
+        if self.last_connection.as_ref() == Some(&attempt) {
+            return Ok(());
+        }
+        match error {
+            Some(error) => writeln!(self.out, "Connection to {} failed: {}", url, error)?,
+            None => writeln!(self.out, "Connecting to {}...", url)?,
+        }
+        self.last_connection = Some(attempt);
+        self.out.flush()
+    }
+
+    fn cleanup(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connection_attempts_are_printed_when_they_change() {
+        let mut ui = HeadlessUi::new(Vec::new());
+        ui.draw_connection("ws://robot", None).unwrap();
+        ui.draw_connection("ws://robot", None).unwrap();
+        ui.draw_connection("ws://robot", Some("refused")).unwrap();
+        ui.draw_connection("ws://robot", Some("refused")).unwrap();
+        assert_eq!(
+            String::from_utf8(ui.out).unwrap(),
+            "Connecting to ws://robot...\nConnection to ws://robot failed: refused\n"
+        );
+    }
+}
@@ -0,0 +1,132 @@
+// ============================================================================
+// Input Source Module - What the control loop reads the operator's input from
+// ============================================================================
+
+use crossterm::event::KeyCode;
+use std::collections::HashMap;
+
+use super::keyboard_input::{InputSnapshot, KeyState, KeyboardInput, SpeedData, SpeedMode};
+
+/// Operator input the control loop can be driven from. Sources without a
+/// notion of some of it (a gamepad has no terminal keys) keep the defaults.
+pub trait InputSource: Send + Sync {
+    /// Target speed from the input currently held
+    fn get_speed(&self) -> SpeedData;
+
+    /// Change the full-deflection speeds, takes effect on the next input event
+    fn set_speeds(&self, linear_speed: f32, angular_speed: f32);
+
+    /// Current full-deflection speeds (m/s, rad/s)
+    fn speeds(&self) -> (f32, f32);
+
+    fn should_exit(&self) -> bool;
+
+    /// Terminal keys currently held, shown in the controls panel
+    fn get_pressed_keys(&self) -> HashMap<KeyCode, KeyState>;
+
+    /// Terminal keys newly pressed since the last call
+    fn take_key_presses(&self) -> Vec<KeyCode> {
+        Vec::new()
+    }
+
+    /// Keys released as stuck since the last call
+    fn take_stuck_keys(&self) -> Vec<KeyCode> {
+        Vec::new()
+    }
+
+    fn has_conflicting_keys(&self) -> bool {
+        false
+    }
+
+    /// Operator emergency stop, latched until explicitly reset
+    fn emergency_requested(&self) -> bool {
+        false
+    }
+
+    fn exit_hold_progress(&self) -> Option<f32> {
+        None
+    }
+
+    /// The exit key was pressed once and waits for its confirmation
+    fn exit_confirmation_pending(&self) -> bool {
+        false
+    }
+
+    fn speed_mode(&self) -> SpeedMode {
+        SpeedMode::Normal
+    }
+
+    /// Whether the dead-man key is held, None when the input doesn't require one
+    fn deadman_engaged(&self) -> Option<bool> {
+        None
+    }
+
+    /// Speed, held keys, exit and emergency stop read together. Sources that
+    /// can take them under one lock override this.
+    fn snapshot(&self) -> InputSnapshot {
+        InputSnapshot {
+            speed: self.get_speed(),
+            pressed_keys: self.get_pressed_keys(),
+            should_exit: self.should_exit(),
+            emergency: self.emergency_requested(),
+        }
+    }
+}
+
+impl InputSource for KeyboardInput {
+    fn get_speed(&self) -> SpeedData {
+        KeyboardInput::get_speed(self)
+    }
+
+    fn set_speeds(&self, linear_speed: f32, angular_speed: f32) {
+        KeyboardInput::set_speeds(self, linear_speed, angular_speed)
+    }
+
+    fn speeds(&self) -> (f32, f32) {
+        KeyboardInput::speeds(self)
+    }
+
+    fn should_exit(&self) -> bool {
+        KeyboardInput::should_exit(self)
+    }
+
+    fn get_pressed_keys(&self) -> HashMap<KeyCode, KeyState> {
+        KeyboardInput::get_pressed_keys(self)
+    }
+
+    fn take_key_presses(&self) -> Vec<KeyCode> {
+        KeyboardInput::take_key_presses(self)
+    }
+
+    fn take_stuck_keys(&self) -> Vec<KeyCode> {
+        KeyboardInput::take_stuck_keys(self)
+    }
+
+    fn has_conflicting_keys(&self) -> bool {
+        KeyboardInput::has_conflicting_keys(self)
+    }
+
+    fn emergency_requested(&self) -> bool {
+        KeyboardInput::emergency_requested(self)
+    }
+
+    fn exit_hold_progress(&self) -> Option<f32> {
+        KeyboardInput::exit_hold_progress(self)
+    }
+
+    fn exit_confirmation_pending(&self) -> bool {
+        KeyboardInput::exit_confirmation_pending(self)
+    }
+
+    fn speed_mode(&self) -> SpeedMode {
+        KeyboardInput::speed_mode(self)
+    }
+
+    fn deadman_engaged(&self) -> Option<bool> {
+        KeyboardInput::deadman_engaged(self)
+    }
+
+    fn snapshot(&self) -> InputSnapshot {
+        KeyboardInput::snapshot(self)
+    }
+}
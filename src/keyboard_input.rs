@@ -0,0 +1,1494 @@
+// ============================================================================
+// Keyboard Input Module - Handles keyboard events and speed control
+// ============================================================================
+
+use crossterm::event::{Event, EventStream, KeyCode, KeyModifiers};
+use futures::StreamExt;
+use std::ops::{Add, Mul};
+use std::sync::{Arc, Mutex};
+use std::{collections::HashMap, time::Instant};
+use tokio::time::MissedTickBehavior;
+
+use super::lock_recover::LockRecover;
+
+// Speed data structure for X, Y, Z axes
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize)]
+pub struct SpeedData {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl SpeedData {
+    pub const ZERO: SpeedData = SpeedData {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Per-axis speeds of a (linear, angular) pair such as the key speeds:
+    /// the linear one on X and Y, the angular one on Z
+    pub fn from_linear_angular((linear, angular): (f32, f32)) -> Self {
+        Self::new(linear, linear, angular)
+    }
+
+    /// Magnitude of the linear (X/Y) part in m/s
+    pub fn magnitude_linear(&self) -> f32 {
+        self.x.hypot(self.y)
+    }
+
+    /// True if every axis is within `epsilon` of zero
+    pub fn is_zero(&self, epsilon: f32) -> bool {
+        self.x.abs() <= epsilon && self.y.abs() <= epsilon && self.z.abs() <= epsilon
+    }
+
+    /// Limit the linear magnitude to `max_linear` keeping its direction,
+    /// and the angular speed to `±max_angular`
+    pub fn clamp(&self, max_linear: f32, max_angular: f32) -> Self {
+        let magnitude = self.magnitude_linear();
+        let linear_factor = if magnitude > max_linear && magnitude > 0.0 {
+            max_linear / magnitude
+        } else {
+            1.0
+        };
+        Self {
+            x: self.x * linear_factor,
+            y: self.y * linear_factor,
+            z: self.z.clamp(-max_angular, max_angular),
+        }
+    }
+
+    /// True if some axis of `other` points against the same axis of `self`
+    pub fn opposes(&self, other: &SpeedData) -> bool {
+        self.x * other.x < 0.0 || self.y * other.y < 0.0 || self.z * other.z < 0.0
+    }
+
+    /// Rotate the X/Y vector by `angle` radians counter-clockwise seen from
+    /// above (+Y points right), Z is unchanged
+    pub fn rotate_xy(&self, angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x: self.x * cos + self.y * sin,
+            y: -self.x * sin + self.y * cos,
+            z: self.z,
+        }
+    }
+
+    /// Multiply every axis by `factor`
+    pub fn scale(&self, factor: f32) -> Self {
+        Self {
+            x: self.x * factor,
+            y: self.y * factor,
+            z: self.z * factor,
+        }
+    }
+}
+
+impl Add for SpeedData {
+    type Output = SpeedData;
+
+    fn add(self, rhs: SpeedData) -> SpeedData {
+        SpeedData {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl Mul<f32> for SpeedData {
+    type Output = SpeedData;
+
+    fn mul(self, rhs: f32) -> SpeedData {
+        self.scale(rhs)
+    }
+}
+
+#[derive(Clone)]
+pub struct KeyState {
+    first_seen: Instant,
+    last_seen: Instant,
+    is_holding: bool,
+    /// Time without an event after which this key counts as released
+    release_timeout: std::time::Duration,
+}
+
+impl KeyState {
+    /// State of a key that was just pressed for the first time, with the
+    /// default first press window
+    pub fn pressed_at(now: Instant) -> Self {
+        Self::pressed_with_timeout(now, DEFAULT_FIRST_PRESS_TIMEOUT)
+    }
+
+    /// State of a key that was just pressed, released if it doesn't repeat
+    /// within `first_press_timeout`
+    pub fn pressed_with_timeout(now: Instant, first_press_timeout: std::time::Duration) -> Self {
+        Self {
+            first_seen: now,
+            last_seen: now,
+            is_holding: false,
+            release_timeout: first_press_timeout,
+        }
+    }
+
+    /// Record a repeat event of the held key, after which it is released
+    /// `release_timeout` after its last repeat instead of the first press window
+    pub fn repeated_at(&mut self, now: Instant, release_timeout: std::time::Duration) {
+        self.is_holding = true;
+        self.last_seen = now;
+        self.release_timeout = release_timeout;
+    }
+
+    pub fn is_released(&self, now: Instant) -> bool {
+        now.duration_since(self.last_seen) > self.release_timeout
+    }
+
+    /// How long the key has been held without being released
+    pub fn held_for(&self, now: Instant) -> std::time::Duration {
+        now.duration_since(self.first_seen)
+    }
+}
+
+/// Keys for movement, the operator emergency stop and exit. The default is
+/// WASD/QE, Space to latch a stop, Backspace to release it, C to exit and Tab
+/// as the dead-man enable key. The arrow keys always move in addition to the
+/// bound movement keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyBindings {
+    pub forward: KeyCode,
+    pub backward: KeyCode,
+    pub left: KeyCode,
+    pub right: KeyCode,
+    pub rotate_left: KeyCode,
+    pub rotate_right: KeyCode,
+    pub emergency_stop: KeyCode,
+    pub emergency_reset: KeyCode,
+    pub exit: KeyCode,
+    /// Must be held for the movement keys to move, with `require_deadman`
+    pub deadman: KeyCode,
+    /// Raise/lower the speed of the X/Y movement keys by a step
+    pub linear_faster: KeyCode,
+    pub linear_slower: KeyCode,
+    /// Raise/lower the speed of the rotation keys by a step
+    pub angular_faster: KeyCode,
+    pub angular_slower: KeyCode,
+    /// Pause / resume the command output, read by the control loop
+    pub pause: KeyCode,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            forward: KeyCode::Char('w'),
+            backward: KeyCode::Char('s'),
+            left: KeyCode::Char('a'),
+            right: KeyCode::Char('d'),
+            rotate_left: KeyCode::Char('q'),
+            rotate_right: KeyCode::Char('e'),
+            emergency_stop: KeyCode::Char(' '),
+            emergency_reset: KeyCode::Backspace,
+            exit: KeyCode::Char('c'),
+            deadman: KeyCode::Tab,
+            linear_faster: KeyCode::Char('+'),
+            linear_slower: KeyCode::Char('-'),
+            angular_faster: KeyCode::Char(']'),
+            angular_slower: KeyCode::Char('['),
+            pause: KeyCode::Char('z'),
+        }
+    }
+}
+
+impl KeyBindings {
+    fn is_speed_key(&self, key: KeyCode) -> bool {
+        [
+            self.linear_faster,
+            self.linear_slower,
+            self.angular_faster,
+            self.angular_slower,
+        ]
+        .contains(&key)
+    }
+}
+
+/// Steps of the speed keys, which change the speeds of the movement keys for
+/// the rest of the session
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedSteps {
+    /// m/s per press
+    pub linear_step: f32,
+    /// rad/s per press
+    pub angular_step: f32,
+    /// Highest speeds the keys go up to. The lowest is one step.
+    pub max_linear: f32,
+    pub max_angular: f32,
+}
+
+impl Default for SpeedSteps {
+    fn default() -> Self {
+        Self {
+            linear_step: 0.05,
+            angular_step: 0.1,
+            max_linear: 1.0,
+            max_angular: 3.0,
+        }
+    }
+}
+
+impl SpeedSteps {
+    /// Speeds after pressing `key`, unchanged if it isn't a speed key. A speed
+    /// already outside the range is only moved towards it, never past it.
+    pub fn apply(
+        &self,
+        key: KeyCode,
+        bindings: &KeyBindings,
+        linear_speed: f32,
+        angular_speed: f32,
+    ) -> (f32, f32) {
+        let faster = |value: f32, step: f32, max: f32| (value + step).min(max.max(value));
+        let slower = |value: f32, step: f32| (value - step).max(step.min(value));
+        if key == bindings.linear_faster {
+            (
+                faster(linear_speed, self.linear_step, self.max_linear),
+                angular_speed,
+            )
+        } else if key == bindings.linear_slower {
+            (slower(linear_speed, self.linear_step), angular_speed)
+        } else if key == bindings.angular_faster {
+            (
+                linear_speed,
+                faster(angular_speed, self.angular_step, self.max_angular),
+            )
+        } else if key == bindings.angular_slower {
+            (linear_speed, slower(angular_speed, self.angular_step))
+        } else {
+            (linear_speed, angular_speed)
+        }
+    }
+}
+
+/// Speed multiplier selected by the modifier held with the movement keys
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SpeedMode {
+    #[default]
+    Normal,
+    /// Shift held
+    Turbo,
+    /// Ctrl held
+    Precision,
+}
+
+impl SpeedMode {
+    /// Ctrl wins if both are held, the safer choice
+    pub fn from_modifiers(modifiers: KeyModifiers) -> Self {
+        if modifiers.contains(KeyModifiers::CONTROL) {
+            SpeedMode::Precision
+        } else if modifiers.contains(KeyModifiers::SHIFT) {
+            SpeedMode::Turbo
+        } else {
+            SpeedMode::Normal
+        }
+    }
+}
+
+/// Multipliers applied to the configured speeds while Shift or Ctrl is held
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedModifiers {
+    pub turbo: f32,
+    pub precision: f32,
+}
+
+impl Default for SpeedModifiers {
+    fn default() -> Self {
+        Self {
+            turbo: 2.0,
+            precision: 0.25,
+        }
+    }
+}
+
+impl SpeedModifiers {
+    pub fn multiplier(&self, mode: SpeedMode) -> f32 {
+        match mode {
+            SpeedMode::Normal => 1.0,
+            SpeedMode::Turbo => self.turbo,
+            SpeedMode::Precision => self.precision,
+        }
+    }
+}
+
+/// Axes whose sign is flipped, for bases mounted reversed. Applied to the
+/// speed handed out, the key mapping itself stays the same.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AxisInvert {
+    pub x: bool,
+    pub y: bool,
+    pub z: bool,
+}
+
+impl AxisInvert {
+    pub fn apply(&self, speed: SpeedData) -> SpeedData {
+        let sign = |invert: bool| if invert { -1.0 } else { 1.0 };
+        SpeedData::new(
+            speed.x * sign(self.x),
+            speed.y * sign(self.y),
+            speed.z * sign(self.z),
+        )
+    }
+}
+
+/// How fast the keyboard speed follows the held keys. `None` jumps straight
+/// to the new speed, which is the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RampConfig {
+    /// Speeding up along X/Y, m/s^2
+    pub accel: Option<f32>,
+    /// Slowing down along X/Y, m/s^2
+    pub decel: Option<f32>,
+}
+
+impl RampConfig {
+    /// Move `current` towards `target` over `dt` seconds. Z ramps at the same
+    /// fraction of `angular_speed` per second as X/Y of `linear_speed`, so all
+    /// axes take the same time to reach full speed.
+    pub fn step(
+        &self,
+        current: SpeedData,
+        target: SpeedData,
+        linear_speed: f32,
+        angular_speed: f32,
+        dt: f32,
+    ) -> SpeedData {
+        let angular_ratio = if linear_speed > 0.0 {
+            angular_speed / linear_speed
+        } else {
+            1.0
+        };
+        let axis = |current: f32, target: f32, ratio: f32| {
+            // Moving away from zero is acceleration, towards or through it deceleration
+            let speeding_up = target.abs() > current.abs() && target * current >= 0.0;
+            match if speeding_up { self.accel } else { self.decel } {
+                Some(rate) => {
+                    let max_delta = rate * ratio * dt;
+                    current + (target - current).clamp(-max_delta, max_delta)
+                }
+                None => target,
+            }
+        };
+        SpeedData::new(
+            axis(current.x, target.x, 1.0),
+            axis(current.y, target.y, 1.0),
+            axis(current.z, target.z, angular_ratio),
+        )
+    }
+}
+
+/// Default time without a repeat event after which a held key counts as released
+pub const DEFAULT_RELEASE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(100);
+/// Default time a newly pressed key is kept without events. Terminals only
+/// start repeating a held key after their repeat delay, which is longer than
+/// the interval between repeats.
+pub const DEFAULT_FIRST_PRESS_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+/// Default time to press the exit key a second time with `confirm_exit`
+pub const DEFAULT_CONFIRM_EXIT_WINDOW: std::time::Duration = std::time::Duration::from_secs(2);
+/// Default longest a key may be held before it is released as stuck
+pub const DEFAULT_MAX_KEY_HOLD: std::time::Duration = std::time::Duration::from_secs(30);
+/// Interval at which released keys are dropped and the ramp advances while
+/// no events arrive. Key events themselves are handled as soon as they come in.
+const RELEASE_SWEEP_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Everything about the keyboard handler besides the speeds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyboardOptions {
+    pub bindings: KeyBindings,
+    pub ramp: RampConfig,
+    /// Multipliers while Shift/Ctrl is held
+    pub modifiers: SpeedModifiers,
+    /// When set, the exit key must be held this long instead of just pressed
+    pub exit_hold: Option<std::time::Duration>,
+    /// When set, the first exit key press only stops and a second press
+    /// within this window exits. Ignored with `exit_hold`.
+    pub confirm_exit: Option<std::time::Duration>,
+    /// A held key counts as released after this long without a repeat event.
+    /// Shorter than the OS key repeat interval, a held key is released between
+    /// repeats and the motion stutters.
+    pub release_timeout: std::time::Duration,
+    /// Like `release_timeout`, but before the first repeat. Shorter than the
+    /// OS key repeat delay, holding a key stutters right after pressing it.
+    pub first_press_timeout: std::time::Duration,
+    /// A key held this long is released as stuck, repeat events or not: a
+    /// terminal that lost the key release can keep repeating it. It counts
+    /// again once its events stop and it is pressed anew. None disables it.
+    pub max_hold: Option<std::time::Duration>,
+    pub invert: AxisInvert,
+    /// Only move while the dead-man key is held, releasing it stops at once
+    pub require_deadman: bool,
+    pub speed_steps: SpeedSteps,
+}
+
+impl Default for KeyboardOptions {
+    fn default() -> Self {
+        Self {
+            bindings: KeyBindings::default(),
+            ramp: RampConfig::default(),
+            modifiers: SpeedModifiers::default(),
+            exit_hold: None,
+            confirm_exit: None,
+            release_timeout: DEFAULT_RELEASE_TIMEOUT,
+            first_press_timeout: DEFAULT_FIRST_PRESS_TIMEOUT,
+            max_hold: Some(DEFAULT_MAX_KEY_HOLD),
+            invert: AxisInvert::default(),
+            require_deadman: false,
+            speed_steps: SpeedSteps::default(),
+        }
+    }
+}
+
+/// What the control loop reads from the keyboard every tick, all of it taken
+/// from the same key event
+#[derive(Clone, Default)]
+pub struct InputSnapshot {
+    pub speed: SpeedData,
+    pub pressed_keys: HashMap<KeyCode, KeyState>,
+    pub should_exit: bool,
+    /// Latched by the emergency stop key until the reset key is pressed
+    pub emergency: bool,
+}
+
+/// Tells new presses of the exit key from the repeats of a held one. Without
+/// key release events a quick second tap looks like the start of the key
+/// repeat, so an event of a key that isn't repeating yet only counts as a
+/// press once no further event followed within the release timeout.
+#[derive(Clone, Copy, Debug, Default)]
+struct ExitPresses {
+    // When the possible second tap came in
+    tap_at: Option<Instant>,
+}
+
+impl ExitPresses {
+    /// An event of the exit key at `now`, with the key's state if it was
+    /// already held. True for a press that counts right away.
+    fn event(&mut self, held: Option<&KeyState>, now: Instant) -> bool {
+        self.tap_at = match held {
+            Some(key_state) if !key_state.is_holding => Some(now),
+            _ => None,
+        };
+        held.is_none()
+    }
+
+    /// When the tap was, once it turned out not to be the start of a repeat
+    fn settled(&mut self, now: Instant, release_timeout: std::time::Duration) -> Option<Instant> {
+        let tap_at = self.tap_at?;
+        if now.duration_since(tap_at) < release_timeout {
+            return None;
+        }
+        self.tap_at = None;
+        Some(tap_at)
+    }
+}
+
+//Keyboard input handler - encapsulates all keyboard processing complexity
+pub struct KeyboardInput {
+    // Updated by the handler under one lock, `speed` before the axis inversion
+    state: Arc<Mutex<InputSnapshot>>,
+    // Opposite keys of the same axis are held at the same time
+    conflicting_keys: Arc<Mutex<bool>>,
+    // Keys pressed since the last take_key_presses(), in press order
+    key_presses: Arc<Mutex<Vec<KeyCode>>>,
+    // Keys released as stuck since the last take_stuck_keys()
+    stuck_keys: Arc<Mutex<Vec<KeyCode>>>,
+    // With confirm_exit, when the exit key was first pressed and not confirmed yet
+    exit_armed_at: Arc<Mutex<Option<Instant>>>,
+    linear_speed: Arc<Mutex<f32>>,
+    angular_speed: Arc<Mutex<f32>>,
+    options: KeyboardOptions,
+    // Modifier held with the last key event, back to Normal once all keys are released
+    speed_mode: Arc<Mutex<SpeedMode>>,
+    // When the handler last moved `speed` towards the held keys
+    last_update: Arc<Mutex<Instant>>,
+}
+
+impl KeyboardInput {
+    /// Keyboard input with the default key bindings
+    pub fn new(linear_speed: f32, angular_speed: f32) -> std::io::Result<Self> {
+        Self::with_bindings(linear_speed, angular_speed, KeyBindings::default())
+    }
+
+    pub fn with_bindings(
+        linear_speed: f32,
+        angular_speed: f32,
+        bindings: KeyBindings,
+    ) -> std::io::Result<Self> {
+        let options = KeyboardOptions {
+            bindings,
+            ..Default::default()
+        };
+        Self::with_options(linear_speed, angular_speed, options)
+    }
+
+    pub fn with_options(
+        linear_speed: f32,
+        angular_speed: f32,
+        options: KeyboardOptions,
+    ) -> std::io::Result<Self> {
+        if options.release_timeout.is_zero() || options.first_press_timeout.is_zero() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "key release timeouts must be nonzero",
+            ));
+        }
+        if options.max_hold.is_some_and(|max_hold| max_hold.is_zero()) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "the maximum key hold must be nonzero",
+            ));
+        }
+        let input = Self {
+            state: Arc::new(Mutex::new(InputSnapshot::default())),
+            conflicting_keys: Arc::new(Mutex::new(false)),
+            key_presses: Arc::new(Mutex::new(Vec::new())),
+            stuck_keys: Arc::new(Mutex::new(Vec::new())),
+            exit_armed_at: Arc::new(Mutex::new(None)),
+            linear_speed: Arc::new(Mutex::new(linear_speed)),
+            angular_speed: Arc::new(Mutex::new(angular_speed)),
+            options,
+            speed_mode: Arc::new(Mutex::new(SpeedMode::Normal)),
+            last_update: Arc::new(Mutex::new(Instant::now())),
+        };
+        input.spawn_handler();
+        Ok(input)
+    }
+
+    /// Speed, held keys, exit and emergency stop under a single lock, so they
+    /// never disagree with each other
+    pub fn snapshot(&self) -> InputSnapshot {
+        let mut snapshot = self.state.lock_recover().clone();
+        snapshot.speed = self.options.invert.apply(snapshot.speed);
+        snapshot
+    }
+
+    pub fn get_speed(&self) -> SpeedData {
+        self.snapshot().speed
+    }
+
+    /// Change the speeds the movement keys map to, takes effect on the next key event
+    pub fn set_speeds(&self, linear_speed: f32, angular_speed: f32) {
+        *self.linear_speed.lock_recover() = linear_speed;
+        *self.angular_speed.lock_recover() = angular_speed;
+    }
+
+    /// Speeds the movement keys map to, as changed by the speed keys
+    pub fn speeds(&self) -> (f32, f32) {
+        (
+            *self.linear_speed.lock_recover(),
+            *self.angular_speed.lock_recover(),
+        )
+    }
+
+    pub fn should_exit(&self) -> bool {
+        self.snapshot().should_exit
+    }
+
+    /// With hold-to-exit, how far the exit key has been held from 0.0 to 1.0,
+    /// or None while it isn't held
+    pub fn exit_hold_progress(&self) -> Option<f32> {
+        let hold = self.options.exit_hold?;
+        let state = self.state.lock_recover();
+        let held = state
+            .pressed_keys
+            .get(&self.options.bindings.exit)?
+            .held_for(Instant::now());
+        Some((held.as_secs_f32() / hold.as_secs_f32()).min(1.0))
+    }
+
+    /// With confirm-exit, true from the first exit key press until it is
+    /// confirmed or the window passes. The speed is held at zero meanwhile.
+    pub fn exit_confirmation_pending(&self) -> bool {
+        Self::exit_pending(
+            *self.exit_armed_at.lock_recover(),
+            self.options.confirm_exit,
+            Instant::now(),
+        )
+    }
+
+    pub fn speed_mode(&self) -> SpeedMode {
+        *self.speed_mode.lock_recover()
+    }
+
+    /// True while opposite movement keys of one axis are held together
+    pub fn has_conflicting_keys(&self) -> bool {
+        *self.conflicting_keys.lock_recover()
+    }
+
+    /// True from an emergency stop key press until the reset key. While set
+    /// the speed is held at zero.
+    pub fn emergency_requested(&self) -> bool {
+        self.snapshot().emergency
+    }
+
+    pub fn get_pressed_keys(&self) -> HashMap<KeyCode, KeyState> {
+        self.snapshot().pressed_keys
+    }
+
+    /// Whether the dead-man key is held, None when it isn't required
+    pub fn deadman_engaged(&self) -> Option<bool> {
+        self.options.require_deadman.then(|| {
+            self.state
+                .lock_recover()
+                .pressed_keys
+                .contains_key(&self.options.bindings.deadman)
+        })
+    }
+
+    /// Return the keys newly pressed since the last call. Auto-repeat events of
+    /// a key that is being held are not reported again.
+    pub fn take_key_presses(&self) -> Vec<KeyCode> {
+        std::mem::take(&mut *self.key_presses.lock_recover())
+    }
+
+    /// Return the keys released as stuck since the last call, see
+    /// `KeyboardOptions::max_hold`
+    pub fn take_stuck_keys(&self) -> Vec<KeyCode> {
+        std::mem::take(&mut *self.stuck_keys.lock_recover())
+    }
+
+    fn spawn_handler(&self) {
+        let input = self.state.clone();
+        let key_presses = self.key_presses.clone();
+        let stuck_keys = self.stuck_keys.clone();
+        let exit_armed_at = self.exit_armed_at.clone();
+        let conflicting_keys = self.conflicting_keys.clone();
+        let linear_speed = self.linear_speed.clone();
+        let angular_speed = self.angular_speed.clone();
+        let KeyboardOptions {
+            bindings,
+            ramp,
+            modifiers,
+            exit_hold,
+            confirm_exit,
+            release_timeout,
+            first_press_timeout,
+            max_hold,
+            require_deadman,
+            speed_steps,
+            ..
+        } = self.options;
+        let speed_mode = self.speed_mode.clone();
+        let last_update = self.last_update.clone();
+        tokio::spawn(async move {
+            let mut events = EventStream::new();
+            let mut sweep = tokio::time::interval(RELEASE_SWEEP_INTERVAL);
+            sweep.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // Keys released as stuck, ignored until their events stop
+            let mut suppressed: HashMap<KeyCode, KeyState> = HashMap::new();
+            let mut exit_presses = ExitPresses::default();
+            loop {
+                tokio::select! {
+                    event = events.next() => match event {
+                        Some(Ok(Event::Key(key_event))) => {
+                            // Shift turns letters upper case, track them as the plain key
+                            let key_code = match key_event.code {
+                                KeyCode::Char(c)
+                                    if key_event.modifiers.contains(KeyModifiers::SHIFT) =>
+                                {
+                                    KeyCode::Char(c.to_ascii_lowercase())
+                                }
+                                code => code,
+                            };
+                            // The Shift typing '+' doesn't select turbo
+                            let speed_key = bindings.is_speed_key(key_code);
+                            if !speed_key {
+                                *speed_mode.lock_recover() =
+                                    SpeedMode::from_modifiers(key_event.modifiers);
+                            }
+
+                            let mut state = input.lock_recover();
+                            if key_code == bindings.emergency_stop {
+                                state.emergency = true;
+                            } else if key_code == bindings.emergency_reset {
+                                state.emergency = false;
+                            }
+
+                            // A repeat of the held exit key doesn't confirm, only a new press
+                            let now = Instant::now();
+                            if key_code == bindings.exit
+                                && exit_hold.is_none()
+                                && exit_presses.event(state.pressed_keys.get(&key_code), now)
+                                && Self::exit_pressed(
+                                    &mut exit_armed_at.lock_recover(),
+                                    confirm_exit,
+                                    now,
+                                )
+                            {
+                                state.should_exit = true;
+                                break;
+                            }
+
+                            if let Some(key_state) = suppressed.get_mut(&key_code) {
+                                key_state.repeated_at(Instant::now(), release_timeout);
+                                continue;
+                            }
+
+                            match state.pressed_keys.get_mut(&key_code) {
+                                Some(key_state) => {
+                                    key_state.repeated_at(Instant::now(), release_timeout);
+                                }
+                                None => {
+                                    if speed_key {
+                                        let mut linear = linear_speed.lock_recover();
+                                        let mut angular = angular_speed.lock_recover();
+                                        (*linear, *angular) =
+                                            speed_steps.apply(key_code, &bindings, *linear, *angular);
+                                    }
+                                    key_presses.lock_recover().push(key_code);
+                                    state.pressed_keys.insert(
+                                        key_code,
+                                        KeyState::pressed_with_timeout(
+                                            Instant::now(),
+                                            first_press_timeout,
+                                        ),
+                                    );
+                                }
+                            }
+                        }
+                        // Resizes included, the UI's next draw picks up the new size
+                        Some(Ok(_)) => {}
+                        // The terminal is gone, nothing more will arrive
+                        Some(Err(_)) | None => break,
+                    },
+                    _ = sweep.tick() => {
+                        let now = Instant::now();
+                        let mut state = input.lock_recover();
+                        if let Some(tap_at) = exit_presses.settled(now, release_timeout) {
+                            if Self::exit_pressed(&mut exit_armed_at.lock_recover(), confirm_exit, tap_at) {
+                                state.should_exit = true;
+                                break;
+                            }
+                        }
+                        Self::release_expired(&mut state.pressed_keys, now);
+                        Self::release_expired(&mut suppressed, now);
+                        if let Some(max_hold) = max_hold {
+                            for (key_code, key_state) in
+                                Self::release_stuck(&mut state.pressed_keys, now, max_hold)
+                            {
+                                stuck_keys.lock_recover().push(key_code);
+                                suppressed.insert(key_code, key_state);
+                            }
+                        }
+                        if state.pressed_keys.is_empty() {
+                            *speed_mode.lock_recover() = SpeedMode::Normal;
+                        }
+                    }
+                }
+                let linear = *linear_speed.lock_recover();
+                let angular = *angular_speed.lock_recover();
+                let multiplier = modifiers.multiplier(*speed_mode.lock_recover());
+                let mut state = input.lock_recover();
+                let (target, conflict) = Self::target_speed(
+                    &state.pressed_keys,
+                    &bindings,
+                    require_deadman,
+                    linear,
+                    angular,
+                    multiplier,
+                );
+                *conflicting_keys.lock_recover() = conflict;
+                let now = Instant::now();
+                let dt = now
+                    .duration_since(std::mem::replace(&mut *last_update.lock_recover(), now))
+                    .as_secs_f32();
+                // The emergency stop and releasing the dead-man key bypass the
+                // ramp, which starts again from zero after them
+                let deadman_released =
+                    require_deadman && !state.pressed_keys.contains_key(&bindings.deadman);
+                let exit_pending =
+                    Self::exit_pending(*exit_armed_at.lock_recover(), confirm_exit, now);
+                state.speed = if state.emergency || deadman_released || exit_pending {
+                    SpeedData::ZERO
+                } else {
+                    ramp.step(state.speed, target, linear, angular, dt)
+                };
+
+                // Releasing the exit key early drops it from pressed_keys, which cancels the hold
+                if let Some(hold) = exit_hold {
+                    let held = state
+                        .pressed_keys
+                        .get(&bindings.exit)
+                        .map(|key_state| key_state.held_for(Instant::now()));
+                    if let Some(held) = held {
+                        if held >= hold {
+                            state.should_exit = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    /// Whether an exit key press at `armed_at` is still waiting for its
+    /// confirmation at `now`
+    fn exit_pending(
+        armed_at: Option<Instant>,
+        confirm_exit: Option<std::time::Duration>,
+        now: Instant,
+    ) -> bool {
+        match (armed_at, confirm_exit) {
+            (Some(armed_at), Some(window)) => now.saturating_duration_since(armed_at) <= window,
+            _ => false,
+        }
+    }
+
+    /// A press of the exit key at `now`. True if it exits: right away without
+    /// `confirm_exit`, else as the second press within the window. The first
+    /// press arms the confirmation.
+    fn exit_pressed(
+        armed_at: &mut Option<Instant>,
+        confirm_exit: Option<std::time::Duration>,
+        now: Instant,
+    ) -> bool {
+        if confirm_exit.is_none() || Self::exit_pending(*armed_at, confirm_exit, now) {
+            *armed_at = None;
+            return true;
+        }
+        *armed_at = Some(now);
+        false
+    }
+
+    /// Drop every key whose own release timeout has passed since its last event
+    fn release_expired(keys: &mut HashMap<KeyCode, KeyState>, now: Instant) {
+        keys.retain(|_, state| !state.is_released(now));
+    }
+
+    /// Remove and return every key held longer than `max_hold`
+    fn release_stuck(
+        keys: &mut HashMap<KeyCode, KeyState>,
+        now: Instant,
+        max_hold: std::time::Duration,
+    ) -> Vec<(KeyCode, KeyState)> {
+        let stuck: Vec<KeyCode> = keys
+            .iter()
+            .filter(|(_, state)| state.held_for(now) > max_hold)
+            .map(|(key, _)| *key)
+            .collect();
+        stuck
+            .into_iter()
+            .filter_map(|key| keys.remove_entry(&key))
+            .collect()
+    }
+
+    /// Map the held keys to a target speed, scaled by the modifier
+    /// `multiplier`. Opposite keys of the same axis cancel out to zero, the
+    /// flag returned is true if any axis had such a conflict. With
+    /// `require_deadman` every axis is zero unless the dead-man key is held too.
+    pub fn target_speed(
+        key: &HashMap<KeyCode, KeyState>,
+        bindings: &KeyBindings,
+        require_deadman: bool,
+        linear_speed: f32,
+        angular_speed: f32,
+        multiplier: f32,
+    ) -> (SpeedData, bool) {
+        let linear_speed = linear_speed * multiplier;
+        let angular_speed = angular_speed * multiplier;
+
+        // Value of one axis from its positive and negative keys, any of them counts
+        let held = |codes: &[KeyCode]| codes.iter().any(|code| key.contains_key(code));
+        let axis = |positive: &[KeyCode], negative: &[KeyCode], value: f32| match (
+            held(positive),
+            held(negative),
+        ) {
+            (true, false) => (value, false),
+            (false, true) => (-value, false),
+            (true, true) => (0.0, true),
+            (false, false) => (0.0, false),
+        };
+
+        // The arrow keys move like WASD, whatever the movement keys are bound to
+        let (x, conflict_x) = axis(
+            &[bindings.forward, KeyCode::Up],
+            &[bindings.backward, KeyCode::Down],
+            linear_speed,
+        );
+        let (y, conflict_y) = axis(
+            &[bindings.right, KeyCode::Right],
+            &[bindings.left, KeyCode::Left],
+            linear_speed,
+        );
+        let (z, conflict_z) = axis(
+            &[bindings.rotate_left],
+            &[bindings.rotate_right],
+            angular_speed,
+        );
+
+        let speed = if require_deadman && !key.contains_key(&bindings.deadman) {
+            SpeedData::ZERO
+        } else {
+            SpeedData::new(x, y, z)
+        };
+        (speed, conflict_x || conflict_y || conflict_z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-6;
+
+    fn held(pressed: &[KeyCode]) -> HashMap<KeyCode, KeyState> {
+        let now = Instant::now();
+        pressed
+            .iter()
+            .map(|key| (*key, KeyState::pressed_at(now)))
+            .collect()
+    }
+
+    fn speed_scaled(
+        bindings: &KeyBindings,
+        pressed: &[KeyCode],
+        multiplier: f32,
+    ) -> (SpeedData, bool) {
+        KeyboardInput::target_speed(&held(pressed), bindings, false, 0.1, 0.5, multiplier)
+    }
+
+    fn speed_with(bindings: &KeyBindings, pressed: &[KeyCode]) -> (SpeedData, bool) {
+        speed_scaled(bindings, pressed, 1.0)
+    }
+
+    fn speed_for(pressed: &[char]) -> (SpeedData, bool) {
+        let keys: Vec<KeyCode> = pressed.iter().map(|c| KeyCode::Char(*c)).collect();
+        speed_with(&KeyBindings::default(), &keys)
+    }
+
+    #[test]
+    fn remapped_forward_key_drives_x() {
+        let bindings = KeyBindings {
+            forward: KeyCode::Up,
+            ..Default::default()
+        };
+        let (speed, _) = speed_with(&bindings, &[KeyCode::Up]);
+        assert_eq!(speed.x, 0.1);
+        assert_eq!(speed, SpeedData::new(0.1, 0.0, 0.0));
+    }
+
+    #[test]
+    fn old_key_does_nothing_after_remap() {
+        let bindings = KeyBindings {
+            forward: KeyCode::Char('z'),
+            ..Default::default()
+        };
+        let (speed, _) = speed_with(&bindings, &[KeyCode::Char('w')]);
+        assert_eq!(speed.x, 0.0);
+        let (speed, _) = speed_with(&bindings, &[KeyCode::Char('z')]);
+        assert_eq!(speed.x, 0.1);
+    }
+
+    #[test]
+    fn remapped_keys_still_cancel_out() {
+        let bindings = KeyBindings {
+            forward: KeyCode::Up,
+            backward: KeyCode::Down,
+            ..Default::default()
+        };
+        let (speed, conflict) = speed_with(&bindings, &[KeyCode::Up, KeyCode::Down]);
+        assert_eq!(speed.x, 0.0);
+        assert!(conflict);
+    }
+
+    #[test]
+    fn forward_and_backward_cancel_out() {
+        let (speed, conflict) = speed_for(&['w', 's']);
+        assert_eq!(speed, SpeedData::ZERO);
+        assert!(conflict);
+    }
+
+    #[test]
+    fn left_and_right_cancel_out() {
+        let (speed, conflict) = speed_for(&['a', 'd']);
+        assert_eq!(speed, SpeedData::ZERO);
+        assert!(conflict);
+    }
+
+    #[test]
+    fn rotate_left_and_right_cancel_out() {
+        let (speed, conflict) = speed_for(&['q', 'e']);
+        assert_eq!(speed, SpeedData::ZERO);
+        assert!(conflict);
+    }
+
+    #[test]
+    fn conflict_on_one_axis_keeps_the_others() {
+        let (speed, conflict) = speed_for(&['w', 's', 'd']);
+        assert_eq!(speed, SpeedData::new(0.0, 0.1, 0.0));
+        assert!(conflict);
+    }
+
+    #[test]
+    fn no_keys_is_zero() {
+        assert_eq!(speed_for(&[]), (SpeedData::ZERO, false));
+    }
+
+    #[test]
+    fn single_keys_drive_their_axis() {
+        let cases = [
+            ('w', SpeedData::new(0.1, 0.0, 0.0)),
+            ('s', SpeedData::new(-0.1, 0.0, 0.0)),
+            ('d', SpeedData::new(0.0, 0.1, 0.0)),
+            ('a', SpeedData::new(0.0, -0.1, 0.0)),
+            ('q', SpeedData::new(0.0, 0.0, 0.5)),
+            ('e', SpeedData::new(0.0, 0.0, -0.5)),
+        ];
+        for (key, expected) in cases {
+            assert_eq!(speed_for(&[key]), (expected, false), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn orthogonal_keys_combine() {
+        assert_eq!(speed_for(&['w', 'd']).0, SpeedData::new(0.1, 0.1, 0.0));
+        assert_eq!(speed_for(&['s', 'a']).0, SpeedData::new(-0.1, -0.1, 0.0));
+        assert_eq!(
+            speed_for(&['w', 'a', 'e']),
+            (SpeedData::new(0.1, -0.1, -0.5), false)
+        );
+    }
+
+    #[test]
+    fn rotation_combines_with_translation() {
+        assert_eq!(speed_for(&['w', 'q']).0, SpeedData::new(0.1, 0.0, 0.5));
+        assert_eq!(speed_for(&['d', 'e']).0, SpeedData::new(0.0, 0.1, -0.5));
+    }
+
+    #[test]
+    fn arrow_keys_mirror_wasd() {
+        let cases = [
+            (KeyCode::Up, 'w'),
+            (KeyCode::Down, 's'),
+            (KeyCode::Left, 'a'),
+            (KeyCode::Right, 'd'),
+        ];
+        for (arrow, letter) in cases {
+            let (speed, _) = speed_with(&KeyBindings::default(), &[arrow]);
+            assert_eq!(speed, speed_for(&[letter]).0, "{:?}", arrow);
+        }
+        // Either set works together with the other
+        let (speed, conflict) =
+            speed_with(&KeyBindings::default(), &[KeyCode::Char('w'), KeyCode::Up]);
+        assert_eq!((speed, conflict), (SpeedData::new(0.1, 0.0, 0.0), false));
+        let (speed, conflict) = speed_with(
+            &KeyBindings::default(),
+            &[KeyCode::Char('w'), KeyCode::Down],
+        );
+        assert_eq!((speed, conflict), (SpeedData::ZERO, true));
+    }
+
+    #[test]
+    fn unbound_keys_are_ignored() {
+        assert_eq!(speed_for(&['x', 'z', '0']), (SpeedData::ZERO, false));
+    }
+
+    #[test]
+    fn deadman_must_be_held_to_move() {
+        let speed_holding = |pressed: &[KeyCode]| {
+            KeyboardInput::target_speed(
+                &held(pressed),
+                &KeyBindings::default(),
+                true,
+                0.1,
+                0.5,
+                1.0,
+            )
+            .0
+        };
+        assert_eq!(
+            speed_holding(&[KeyCode::Char('w'), KeyCode::Char('q')]),
+            SpeedData::ZERO
+        );
+        assert_eq!(
+            speed_holding(&[KeyCode::Char('w'), KeyCode::Char('q'), KeyCode::Tab]),
+            SpeedData::new(0.1, 0.0, 0.5)
+        );
+    }
+
+    #[test]
+    fn zero_constant_is_zero() {
+        assert!(SpeedData::ZERO.is_zero(0.0));
+        assert_eq!(SpeedData::ZERO, SpeedData::default());
+    }
+
+    #[test]
+    fn magnitude_linear_ignores_angular() {
+        let speed = SpeedData::new(0.3, -0.4, 2.0);
+        assert!((speed.magnitude_linear() - 0.5).abs() < EPS);
+    }
+
+    #[test]
+    fn is_zero_uses_epsilon() {
+        let speed = SpeedData::new(0.0005, -0.0005, 0.0);
+        assert!(speed.is_zero(0.001));
+        assert!(!speed.is_zero(0.0001));
+    }
+
+    #[test]
+    fn clamp_preserves_linear_direction() {
+        let speed = SpeedData::new(0.6, 0.8, 0.0).clamp(0.5, 1.0);
+        assert!((speed.magnitude_linear() - 0.5).abs() < EPS);
+        assert!((speed.x / speed.y - 0.75).abs() < EPS);
+    }
+
+    #[test]
+    fn clamp_limits_angular_both_ways() {
+        assert_eq!(SpeedData::new(0.0, 0.0, 3.0).clamp(1.0, 1.5).z, 1.5);
+        assert_eq!(SpeedData::new(0.0, 0.0, -3.0).clamp(1.0, 1.5).z, -1.5);
+    }
+
+    #[test]
+    fn clamp_leaves_in_range_values_untouched() {
+        let speed = SpeedData::new(0.1, -0.1, 0.2);
+        assert_eq!(speed.clamp(1.0, 1.0), speed);
+    }
+
+    #[test]
+    fn opposes_checks_each_axis() {
+        let held = SpeedData::new(0.1, 0.0, 0.5);
+        assert!(held.opposes(&SpeedData::new(-0.1, 0.0, 0.0)));
+        assert!(held.opposes(&SpeedData::new(0.0, 0.0, -0.5)));
+        assert!(!held.opposes(&SpeedData::new(0.1, 0.1, 0.0)));
+        assert!(!held.opposes(&SpeedData::ZERO));
+    }
+
+    #[test]
+    fn scale_and_mul_agree() {
+        let speed = SpeedData::new(0.1, -0.2, 0.5);
+        assert_eq!(speed.scale(2.0), SpeedData::new(0.2, -0.4, 1.0));
+        assert_eq!(speed * 2.0, speed.scale(2.0));
+    }
+
+    #[test]
+    fn add_is_per_axis() {
+        let sum = SpeedData::new(0.1, 0.2, 0.3) + SpeedData::new(0.5, -0.2, -0.3);
+        assert!((sum.x - 0.6).abs() < EPS);
+        assert!(sum.y.abs() < EPS);
+        assert!(sum.z.abs() < EPS);
+    }
+
+    const RAMP: RampConfig = RampConfig {
+        accel: Some(0.5),
+        decel: Some(1.0),
+    };
+
+    // Ramp from `start` towards `target` in 50 ms ticks like the handler does
+    fn ramp_ticks(start: SpeedData, target: SpeedData, ticks: usize) -> Vec<SpeedData> {
+        let mut speed = start;
+        (0..ticks)
+            .map(|_| {
+                speed = RAMP.step(speed, target, 0.1, 0.5, 0.05);
+                speed
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_ramp_jumps_to_target() {
+        let target = SpeedData::new(0.1, -0.1, 0.5);
+        let speed = RampConfig::default().step(SpeedData::ZERO, target, 0.1, 0.5, 0.05);
+        assert_eq!(speed, target);
+    }
+
+    #[test]
+    fn ramp_approaches_target_without_overshoot() {
+        // 0.5 m/s^2 reaches 0.1 m/s in 0.2 s, four ticks
+        let target = SpeedData::new(0.1, 0.0, 0.5);
+        let ticks = ramp_ticks(SpeedData::ZERO, target, 6);
+        assert!((ticks[0].x - 0.025).abs() < EPS);
+        assert!(ticks[1].x < target.x);
+        for pair in ticks.windows(2) {
+            assert!(pair[1].x >= pair[0].x);
+            assert!(pair[1].x <= target.x && pair[1].z <= target.z);
+        }
+        assert!((ticks[3].x - target.x).abs() < EPS);
+        // Z takes the same time relative to the angular speed
+        assert!((ticks[0].z - 0.125).abs() < EPS);
+        assert!((ticks[3].z - target.z).abs() < EPS);
+        assert_eq!(ticks[5], ticks[4]);
+    }
+
+    #[test]
+    fn ramp_releases_at_decel_rate() {
+        // 1.0 m/s^2 stops 0.1 m/s in 0.1 s, two ticks
+        let ticks = ramp_ticks(SpeedData::new(0.1, 0.0, 0.0), SpeedData::ZERO, 3);
+        assert!((ticks[0].x - 0.05).abs() < EPS);
+        assert!(ticks[1].x.abs() < EPS);
+        assert!(ticks[2].x.abs() < EPS);
+    }
+
+    #[test]
+    fn modifiers_select_speed_mode() {
+        assert_eq!(
+            SpeedMode::from_modifiers(KeyModifiers::NONE),
+            SpeedMode::Normal
+        );
+        assert_eq!(
+            SpeedMode::from_modifiers(KeyModifiers::SHIFT),
+            SpeedMode::Turbo
+        );
+        assert_eq!(
+            SpeedMode::from_modifiers(KeyModifiers::CONTROL),
+            SpeedMode::Precision
+        );
+        assert_eq!(
+            SpeedMode::from_modifiers(KeyModifiers::SHIFT | KeyModifiers::CONTROL),
+            SpeedMode::Precision
+        );
+    }
+
+    #[test]
+    fn multiplier_scales_every_axis() {
+        let modifiers = SpeedModifiers::default();
+        let keys = [KeyCode::Char('w'), KeyCode::Char('d'), KeyCode::Char('q')];
+        let turbo = modifiers.multiplier(SpeedMode::Turbo);
+        let (speed, _) = speed_scaled(&KeyBindings::default(), &keys, turbo);
+        assert_eq!(speed, SpeedData::new(0.2, 0.2, 1.0));
+        let precision = modifiers.multiplier(SpeedMode::Precision);
+        let (speed, _) = speed_scaled(&KeyBindings::default(), &keys, precision);
+        assert_eq!(speed, SpeedData::new(0.025, 0.025, 0.125));
+    }
+
+    #[test]
+    fn interleaved_keys_keep_their_own_release_timeout() {
+        let ms = std::time::Duration::from_millis;
+        let (w, d) = (KeyCode::Char('w'), KeyCode::Char('d'));
+        let start = Instant::now();
+        let mut keys = HashMap::new();
+
+        // W is already repeating when D is pressed, then W repeats once more
+        keys.insert(w, KeyState::pressed_at(start));
+        keys.get_mut(&w)
+            .unwrap()
+            .repeated_at(start + ms(400), ms(100));
+        keys.insert(d, KeyState::pressed_at(start + ms(450)));
+        keys.get_mut(&w)
+            .unwrap()
+            .repeated_at(start + ms(480), ms(100));
+
+        // W's repeats stopped, D is still waiting for its first repeat
+        KeyboardInput::release_expired(&mut keys, start + ms(700));
+        assert!(!keys.contains_key(&w));
+        assert!(keys.contains_key(&d));
+
+        KeyboardInput::release_expired(&mut keys, start + ms(1000));
+        assert!(keys.is_empty());
+    }
+
+    #[test]
+    fn key_held_past_the_max_hold_is_released_as_stuck() {
+        let ms = std::time::Duration::from_millis;
+        let (w, a) = (KeyCode::Char('w'), KeyCode::Char('a'));
+        let start = Instant::now();
+        let mut keys = HashMap::new();
+
+        // W keeps repeating, as if the terminal never saw it released
+        keys.insert(w, KeyState::pressed_at(start));
+        keys.get_mut(&w)
+            .unwrap()
+            .repeated_at(start + ms(2950), ms(100));
+        keys.insert(a, KeyState::pressed_at(start + ms(2000)));
+
+        assert!(KeyboardInput::release_stuck(&mut keys, start + ms(2900), ms(3000)).is_empty());
+        let stuck = KeyboardInput::release_stuck(&mut keys, start + ms(3010), ms(3000));
+        assert_eq!(stuck.len(), 1);
+        assert_eq!(stuck[0].0, w);
+        assert!(keys.contains_key(&a) && !keys.contains_key(&w));
+    }
+
+    #[test]
+    fn exit_confirmation_window_runs_out() {
+        let ms = std::time::Duration::from_millis;
+        let start = Instant::now();
+        let window = Some(ms(2000));
+        assert!(KeyboardInput::exit_pending(
+            Some(start),
+            window,
+            start + ms(1500)
+        ));
+        assert!(!KeyboardInput::exit_pending(
+            Some(start),
+            window,
+            start + ms(2500)
+        ));
+        assert!(!KeyboardInput::exit_pending(None, window, start));
+        assert!(!KeyboardInput::exit_pending(Some(start), None, start));
+    }
+
+    #[test]
+    fn first_press_timeout_is_configurable() {
+        let ms = std::time::Duration::from_millis;
+        let start = Instant::now();
+        let key = KeyState::pressed_with_timeout(start, ms(200));
+        assert!(!key.is_released(start + ms(150)));
+        assert!(key.is_released(start + ms(250)));
+        assert!(!KeyState::pressed_at(start).is_released(start + ms(250)));
+    }
+
+    #[test]
+    fn zero_release_timeouts_are_rejected() {
+        for options in [
+            KeyboardOptions {
+                release_timeout: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+            KeyboardOptions {
+                first_press_timeout: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+        ] {
+            let error = KeyboardInput::with_options(0.1, 0.5, options)
+                .err()
+                .expect("zero timeout accepted");
+            assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[test]
+    fn invert_flips_only_the_selected_axis() {
+        let (speed, _) = speed_for(&['w', 'd', 'q']);
+        assert_eq!(speed, SpeedData::new(0.1, 0.1, 0.5));
+        let invert_x = AxisInvert {
+            x: true,
+            ..Default::default()
+        };
+        assert_eq!(invert_x.apply(speed), SpeedData::new(-0.1, 0.1, 0.5));
+        let invert_y = AxisInvert {
+            y: true,
+            ..Default::default()
+        };
+        assert_eq!(invert_y.apply(speed), SpeedData::new(0.1, -0.1, 0.5));
+        let invert_z = AxisInvert {
+            z: true,
+            ..Default::default()
+        };
+        assert_eq!(invert_z.apply(speed), SpeedData::new(0.1, 0.1, -0.5));
+        assert_eq!(AxisInvert::default().apply(speed), speed);
+    }
+
+    #[test]
+    fn speed_keys_step_within_their_range() {
+        let steps = SpeedSteps {
+            linear_step: 0.05,
+            angular_step: 0.1,
+            max_linear: 0.2,
+            max_angular: 1.0,
+        };
+        let bindings = KeyBindings::default();
+        let press = |key: char, speeds: (f32, f32)| {
+            steps.apply(KeyCode::Char(key), &bindings, speeds.0, speeds.1)
+        };
+
+        let (linear, angular) = press('+', (0.1, 0.5));
+        assert!((linear - 0.15).abs() < EPS && angular == 0.5);
+        let (linear, _) = press('+', (0.18, 0.5));
+        assert_eq!(linear, 0.2);
+        let (linear, _) = press('-', (0.07, 0.5));
+        assert_eq!(linear, 0.05);
+        let (_, angular) = press(']', (0.1, 0.95));
+        assert_eq!(angular, 1.0);
+        let (_, angular) = press('[', (0.1, 0.5));
+        assert!((angular - 0.4).abs() < EPS);
+
+        // Speeds from the config outside the range aren't pushed further out
+        assert_eq!(press('+', (0.3, 0.5)), (0.3, 0.5));
+        assert_eq!(press('-', (0.02, 0.5)), (0.02, 0.5));
+        // Other keys leave the speeds alone
+        assert_eq!(press('w', (0.1, 0.5)), (0.1, 0.5));
+    }
+
+    #[test]
+    fn key_speeds_give_the_per_axis_maximum() {
+        assert_eq!(
+            SpeedData::from_linear_angular((0.2, 0.8)),
+            SpeedData::new(0.2, 0.2, 0.8)
+        );
+    }
+
+    #[test]
+    fn default_bindings_are_distinct() {
+        let b = KeyBindings::default();
+        let keys = [
+            b.forward,
+            b.backward,
+            b.left,
+            b.right,
+            b.rotate_left,
+            b.rotate_right,
+            b.emergency_stop,
+            b.emergency_reset,
+            b.exit,
+            b.deadman,
+            b.linear_faster,
+            b.linear_slower,
+            b.angular_faster,
+            b.angular_slower,
+            b.pause,
+        ];
+        for (i, key) in keys.iter().enumerate() {
+            assert!(!keys[i + 1..].contains(key), "{:?} bound twice", key);
+        }
+        assert_eq!(b.pause, KeyCode::Char('z'));
+    }
+
+    #[test]
+    fn quick_second_exit_press_confirms() {
+        let ms = std::time::Duration::from_millis;
+        let window = Some(DEFAULT_CONFIRM_EXIT_WINDOW);
+        let start = Instant::now();
+        let mut presses = ExitPresses::default();
+        let mut armed_at = None;
+
+        // The first press arms the confirmation
+        assert!(presses.event(None, start));
+        assert!(!KeyboardInput::exit_pressed(&mut armed_at, window, start));
+        let mut key_state = KeyState::pressed_at(start);
+
+        // The second tap comes while the first is still in its first press window
+        assert!(!presses.event(Some(&key_state), start + ms(200)));
+        key_state.repeated_at(start + ms(200), DEFAULT_RELEASE_TIMEOUT);
+        assert_eq!(
+            presses.settled(start + ms(250), DEFAULT_RELEASE_TIMEOUT),
+            None
+        );
+        let tap_at = presses.settled(start + ms(300), DEFAULT_RELEASE_TIMEOUT);
+        assert_eq!(tap_at, Some(start + ms(200)));
+        assert!(KeyboardInput::exit_pressed(
+            &mut armed_at,
+            window,
+            tap_at.unwrap()
+        ));
+    }
+
+    #[test]
+    fn held_exit_key_does_not_confirm() {
+        let ms = std::time::Duration::from_millis;
+        let start = Instant::now();
+        let mut presses = ExitPresses::default();
+        assert!(presses.event(None, start));
+        let mut key_state = KeyState::pressed_at(start);
+
+        // The key repeat starts after its delay and goes on quickly
+        for at in [400, 433, 466, 500] {
+            assert!(!presses.event(Some(&key_state), start + ms(at)));
+            key_state.repeated_at(start + ms(at), DEFAULT_RELEASE_TIMEOUT);
+            assert_eq!(
+                presses.settled(start + ms(at + 20), DEFAULT_RELEASE_TIMEOUT),
+                None
+            );
+        }
+        assert_eq!(
+            presses.settled(start + ms(1000), DEFAULT_RELEASE_TIMEOUT),
+            None
+        );
+    }
+}
@@ -0,0 +1,127 @@
+// ============================================================================
+// Kinematics Module - Per-wheel speeds from a body velocity, for display only
+// ============================================================================
+//
+// The body frame is the one the keyboard commands in: x forward, y to the right,
+// z counter-clockwise (rotate left).
+// Wheel speeds are wheel angular velocities in rad/s, positive when the wheel
+// rolls the base forward.
+
+use super::keyboard_input::SpeedData;
+
+/// Wheel layout of the base
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BaseType {
+    /// Two driven wheels on a common axle
+    Differential,
+    /// Four mecanum wheels with rollers in X configuration
+    Mecanum,
+}
+
+/// Geometry needed to map a body velocity onto the wheels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaseGeometry {
+    pub base_type: BaseType,
+    /// Wheel radius in meters
+    pub wheel_radius: f32,
+    /// Distance between left and right wheel contact points in meters
+    pub track_width: f32,
+    /// Distance between front and rear axles in meters (mecanum only)
+    pub wheel_base: f32,
+}
+
+impl BaseGeometry {
+    /// Wheel speeds needed to follow `body`, as (wheel name, rad/s) pairs.
+    /// A differential base can't follow the Y component, it is ignored.
+    pub fn wheel_speeds(&self, body: SpeedData) -> Vec<(&'static str, f32)> {
+        let r = self.wheel_radius;
+        match self.base_type {
+            BaseType::Differential => {
+                let half_track = self.track_width / 2.0;
+                vec![
+                    ("L", (body.x - body.z * half_track) / r),
+                    ("R", (body.x + body.z * half_track) / r),
+                ]
+            }
+            BaseType::Mecanum => {
+                let k = (self.track_width + self.wheel_base) / 2.0;
+                vec![
+                    ("FL", (body.x + body.y - k * body.z) / r),
+                    ("FR", (body.x - body.y + k * body.z) / r),
+                    ("RL", (body.x - body.y - k * body.z) / r),
+                    ("RR", (body.x + body.y + k * body.z) / r),
+                ]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPS: f32 = 1e-5;
+
+    fn geometry(base_type: BaseType) -> BaseGeometry {
+        BaseGeometry {
+            base_type,
+            wheel_radius: 0.05,
+            track_width: 0.4,
+            wheel_base: 0.2,
+        }
+    }
+
+    fn speeds(base_type: BaseType, body: SpeedData) -> Vec<f32> {
+        geometry(base_type)
+            .wheel_speeds(body)
+            .into_iter()
+            .map(|(_, speed)| speed)
+            .collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < EPS, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn differential_forward_drives_both_wheels_equally() {
+        let wheels = speeds(BaseType::Differential, SpeedData::new(0.1, 0.0, 0.0));
+        assert_close(&wheels, &[2.0, 2.0]);
+    }
+
+    #[test]
+    fn differential_rotation_spins_wheels_in_opposite_directions() {
+        // 1 rad/s with 0.2 m half track is 0.2 m/s at the wheel, 4 rad/s
+        let wheels = speeds(BaseType::Differential, SpeedData::new(0.0, 0.0, 1.0));
+        assert_close(&wheels, &[-4.0, 4.0]);
+    }
+
+    #[test]
+    fn differential_ignores_lateral_speed() {
+        let wheels = speeds(BaseType::Differential, SpeedData::new(0.0, 0.3, 0.0));
+        assert_close(&wheels, &[0.0, 0.0]);
+    }
+
+    #[test]
+    fn mecanum_forward_drives_all_wheels_equally() {
+        let wheels = speeds(BaseType::Mecanum, SpeedData::new(0.1, 0.0, 0.0));
+        assert_close(&wheels, &[2.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn mecanum_strafe_right_uses_diagonal_pairs() {
+        let wheels = speeds(BaseType::Mecanum, SpeedData::new(0.0, 0.1, 0.0));
+        assert_close(&wheels, &[2.0, -2.0, -2.0, 2.0]);
+    }
+
+    #[test]
+    fn mecanum_rotation_spins_sides_in_opposite_directions() {
+        // (0.4 + 0.2) / 2 = 0.3 m lever, 1 rad/s -> 0.3 m/s -> 6 rad/s
+        let wheels = speeds(BaseType::Mecanum, SpeedData::new(0.0, 0.0, 1.0));
+        assert_close(&wheels, &[-6.0, 6.0, -6.0, 6.0]);
+    }
+}
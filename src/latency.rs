@@ -0,0 +1,93 @@
+// ============================================================================
+// Latency Module - Rolling round-trip time between commands and status frames
+// ============================================================================
+//
+// BaseStatus frames carry no reference to the command they follow, so a round
+// trip is measured from the oldest unanswered move command to the next status.
+// At the report rate that includes up to one report period of waiting.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Samples older than this are dropped; with none left there is no value
+pub const LATENCY_WINDOW: Duration = Duration::from_secs(1);
+/// Cap on the kept samples, enough for a smooth average at high report rates
+const MAX_SAMPLES: usize = 64;
+
+#[derive(Clone, Debug, Default)]
+pub struct LatencyTracker {
+    // Oldest command sent since the last status
+    unanswered: Option<Instant>,
+    // (receive time, round trip)
+    samples: VecDeque<(Instant, Duration)>,
+}
+
+impl LatencyTracker {
+    pub fn command_sent(&mut self, at: Instant) {
+        self.unanswered.get_or_insert(at);
+    }
+
+    pub fn status_received(&mut self, at: Instant) {
+        let Some(sent) = self.unanswered.take() else {
+            return;
+        };
+        if self.samples.len() >= MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples
+            .push_back((at, at.saturating_duration_since(sent)));
+    }
+
+    /// Mean round trip of the samples within `LATENCY_WINDOW` before `now`
+    pub fn average(&self, now: Instant) -> Option<Duration> {
+        let recent: Vec<Duration> = self
+            .samples
+            .iter()
+            .filter(|(at, _)| now.saturating_duration_since(*at) <= LATENCY_WINDOW)
+            .map(|(_, rtt)| *rtt)
+            .collect();
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().sum::<Duration>() / recent.len() as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn average_of_recent_round_trips() {
+        let start = Instant::now();
+        let mut tracker = LatencyTracker::default();
+        tracker.command_sent(start);
+        // A second command before the status doesn't restart the measurement
+        tracker.command_sent(start + Duration::from_millis(5));
+        tracker.status_received(start + Duration::from_millis(10));
+        tracker.command_sent(start + Duration::from_millis(20));
+        tracker.status_received(start + Duration::from_millis(50));
+        assert_eq!(
+            tracker.average(start + Duration::from_millis(50)),
+            Some(Duration::from_millis(20))
+        );
+    }
+
+    #[test]
+    fn status_without_command_is_no_sample() {
+        let start = Instant::now();
+        let mut tracker = LatencyTracker::default();
+        tracker.status_received(start);
+        assert_eq!(tracker.average(start), None);
+    }
+
+    #[test]
+    fn old_samples_expire() {
+        let start = Instant::now();
+        let mut tracker = LatencyTracker::default();
+        tracker.command_sent(start);
+        tracker.status_received(start + Duration::from_millis(12));
+        let later = start + Duration::from_millis(12) + LATENCY_WINDOW + Duration::from_millis(1);
+        assert_eq!(tracker.average(later), None);
+    }
+}
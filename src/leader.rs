@@ -0,0 +1,104 @@
+// ============================================================================
+// Leader Module - Velocity of a second base to follow
+// ============================================================================
+//
+// With --follow the leader's telemetry is read on a connection of its own.
+// The connection only ever receives, so the leader's control is never taken.
+// A velocity older than `LEADER_STALE_AFTER` isn't followed but stopped for.
+
+use futures_util::StreamExt;
+use prost::Message;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base_backend;
+use super::keyboard_input::SpeedData;
+use super::lock_recover::LockRecover;
+use super::log_buffer::LogBuffer;
+
+/// Delay between attempts to (re)connect to the --follow leader
+const LEADER_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Leader velocity older than this is not followed, the base is stopped instead
+const LEADER_STALE_AFTER: Duration = Duration::from_millis(500);
+
+/// Connection to the --follow leader
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LeaderLink {
+    #[default]
+    Connecting,
+    Connected,
+    Lost,
+}
+
+impl LeaderLink {
+    pub fn describe(self) -> &'static str {
+        match self {
+            LeaderLink::Connecting => "connecting...",
+            LeaderLink::Connected => "connected",
+            LeaderLink::Lost => "lost, reconnecting...",
+        }
+    }
+}
+
+/// What we know about the leader, updated by its receiver task
+#[derive(Clone, Debug, Default)]
+pub struct LeaderState {
+    pub link: LeaderLink,
+    /// Velocity from the leader's odometry and when it was received
+    pub velocity: Option<(SpeedData, Instant)>,
+}
+
+impl LeaderState {
+    pub fn fresh_velocity(&self) -> Option<SpeedData> {
+        self.velocity
+            .filter(|(_, at)| at.elapsed() <= LEADER_STALE_AFTER)
+            .map(|(speed, _)| speed)
+    }
+}
+
+/// Read the leader's telemetry from its own WebSocket. Read-only: nothing is
+/// ever sent, so we never take control of the leader.
+pub fn spawn_leader_receiver(
+    url: String,
+    leader: Arc<Mutex<LeaderState>>,
+    log: Arc<Mutex<LogBuffer>>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let mut ws = match tokio_tungstenite::connect_async(&url).await {
+                Ok((ws, _)) => ws,
+                Err(_) => {
+                    tokio::time::sleep(LEADER_RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+            leader.lock_recover().link = LeaderLink::Connected;
+            log.lock_recover()
+                .push(format!("Leader: connected to {}", url));
+
+            while let Some(Ok(msg)) = ws.next().await {
+                let tungstenite::Message::Binary(bytes) = msg else {
+                    continue;
+                };
+                let Ok(msg) = base_backend::ApiUp::decode(&bytes[..]) else {
+                    continue;
+                };
+                if let Some(base_backend::api_up::Status::BaseStatus(status)) = msg.status {
+                    if let Some(odometry) = status.estimated_odometry {
+                        let speed =
+                            SpeedData::new(odometry.speed_x, odometry.speed_y, odometry.speed_z);
+                        leader.lock_recover().velocity = Some((speed, Instant::now()));
+                    }
+                }
+            }
+
+            *leader.lock_recover() = LeaderState {
+                link: LeaderLink::Lost,
+                velocity: None,
+            };
+            log.lock_recover().push("Leader: connection lost");
+            tokio::time::sleep(LEADER_RETRY_INTERVAL).await;
+        }
+    });
+}
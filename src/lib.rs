@@ -0,0 +1,55 @@
+// ============================================================================
+// Robot Examples Library - The building blocks of the example binaries
+// ============================================================================
+//
+// `RobotClient` connects to a base, takes API control and sends moves, which
+// is all a program driving the base needs. `RobotConnection` adds what a long
+// running session needs on top: reconnects, the receiver keeping the shared
+// state and the command pipeline. The other modules are what the
+// base-advanced-control-websocket example is made of: the terminal UI,
+// keyboard and gamepad input and the speed shaping between them and the move
+// commands.
+
+// Protobuf generated code.
+pub mod base_backend {
+    include!(concat!(env!("OUT_DIR"), "/_.rs"));
+}
+
+pub mod accel_limit;
+pub mod breakaway;
+pub mod camera;
+pub mod client;
+pub mod config;
+pub mod connection;
+pub mod frame_rate;
+#[cfg(feature = "gilrs")]
+pub mod gamepad_input;
+pub mod headless;
+pub mod input_source;
+pub mod keyboard_input;
+pub mod kinematics;
+pub mod latency;
+pub mod leader;
+pub mod lock_recover;
+pub mod log_buffer;
+pub mod mirror;
+pub mod mock_robot;
+pub mod motion_queue;
+pub mod odometer;
+pub mod odometry_csv;
+pub mod protocol;
+pub mod receiver;
+pub mod robot_ui;
+pub mod script;
+pub mod session_log;
+pub mod session_state;
+pub mod smoothing;
+pub mod speed_history;
+pub mod status_json;
+pub mod status_line;
+pub mod units;
+
+pub use client::{ClientError, RobotClient};
+pub use connection::RobotConnection;
+pub use keyboard_input::{KeyboardInput, SpeedData};
+pub use robot_ui::RobotUi;
@@ -0,0 +1,55 @@
+// ============================================================================
+// Lock Recover Module - Keep going after a thread panicked holding a lock
+// ============================================================================
+//
+// A panic while a Mutex is held poisons it, and unwrapping every later lock
+// would take the UI and the control loop down with it. The state behind these
+// locks is plain values that stay usable, so the guard is recovered instead and
+// the recovery is remembered for the UI to report.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+static RECOVERED: AtomicBool = AtomicBool::new(false);
+
+pub trait LockRecover<T> {
+    /// Lock, recovering the guard of a poisoned mutex
+    fn lock_recover(&self) -> MutexGuard<'_, T>;
+}
+
+impl<T> LockRecover<T> for Mutex<T> {
+    fn lock_recover(&self) -> MutexGuard<'_, T> {
+        self.lock().unwrap_or_else(|poisoned: PoisonError<_>| {
+            RECOVERED.store(true, Ordering::Relaxed);
+            poisoned.into_inner()
+        })
+    }
+}
+
+/// True once any lock had to be recovered, the state may be inconsistent since
+pub fn recovered_from_poison() -> bool {
+    RECOVERED.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn poisoned_lock_is_recovered() {
+        let value = Arc::new(Mutex::new(1));
+        let writer = value.clone();
+        std::thread::spawn(move || {
+            let mut guard = writer.lock().unwrap();
+            *guard = 2;
+            panic!("poison the lock");
+        })
+        .join()
+        .unwrap_err();
+
+        assert!(value.is_poisoned());
+        assert_eq!(*value.lock_recover(), 2);
+        assert!(recovered_from_poison());
+    }
+}
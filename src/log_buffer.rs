@@ -0,0 +1,93 @@
+// ============================================================================
+// Log Buffer Module - Bounded history of log lines shown in the UI
+// ============================================================================
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of lines kept when no capacity is given
+pub const DEFAULT_LOG_CAPACITY: usize = 200;
+
+/// One line of the log with the time it was added
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Time since the buffer was created
+    pub elapsed: Duration,
+    pub message: String,
+}
+
+/// Ring buffer of log lines. Oldest entries are dropped once `capacity` is reached.
+#[derive(Clone, Debug)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    start: Instant,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+            start: Instant::now(),
+        }
+    }
+
+    pub fn push(&mut self, message: impl Into<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            elapsed: self.start.elapsed(),
+            message: message.into(),
+        });
+    }
+
+    /// Entries from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Default for LogBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(log: &LogBuffer) -> Vec<&str> {
+        log.iter().map(|entry| entry.message.as_str()).collect()
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_at_capacity() {
+        let mut log = LogBuffer::new(2);
+        log.push("one");
+        log.push("two");
+        log.push("three");
+        assert_eq!(messages(&log), ["two", "three"]);
+        assert_eq!(log.len(), 2);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_nothing() {
+        let mut log = LogBuffer::new(0);
+        log.push("dropped");
+        assert_eq!(log.iter().count(), 0);
+    }
+}
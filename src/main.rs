@@ -13,7 +13,7 @@ async fn main() {
             ServiceEvent::ServiceResolved(resolved) => {
                 println!("Found HexFellow Device: {:?}", resolved.get_addresses());
             }
-            other_event => {
+            _other_event => {
                 // println!("Received other event: {:?}", &other_event);
             }
         }
@@ -0,0 +1,65 @@
+// ============================================================================
+// Mirror Module - The robot's frames forwarded to a second WebSocket
+// ============================================================================
+//
+// With --mirror every frame received from the robot is also sent on to e.g. a
+// dashboard. Strictly passive: connection problems are logged and retried in
+// the background, frames arriving while it is down are dropped.
+
+use futures_util::SinkExt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::lock_recover::LockRecover;
+use super::log_buffer::LogBuffer;
+
+/// Frames buffered for the --mirror connection before new ones are dropped
+const MIRROR_QUEUE_CAPACITY: usize = 256;
+
+/// Delay between attempts to (re)connect the --mirror connection
+const MIRROR_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Forward the frames sent to the returned channel to a second WebSocket at `url`
+pub fn spawn_mirror(
+    url: String,
+    log: Arc<Mutex<LogBuffer>>,
+) -> tokio::sync::mpsc::Sender<tungstenite::Message> {
+    let (tx, mut rx) = tokio::sync::mpsc::channel(MIRROR_QUEUE_CAPACITY);
+    tokio::spawn(async move {
+        let mut last_error = None;
+        loop {
+            // Only live telemetry is mirrored, not what piled up while disconnected
+            while rx.try_recv().is_ok() {}
+
+            let mut ws = match tokio_tungstenite::connect_async(&url).await {
+                Ok((ws, _)) => ws,
+                Err(e) => {
+                    let error = e.to_string();
+                    if last_error.as_ref() != Some(&error) {
+                        log.lock_recover()
+                            .push(format!("Mirror: can't connect to {}: {}", url, error));
+                        last_error = Some(error);
+                    }
+                    tokio::time::sleep(MIRROR_RETRY_INTERVAL).await;
+                    continue;
+                }
+            };
+            last_error = None;
+            log.lock_recover()
+                .push(format!("Mirror: connected to {}", url));
+
+            while let Some(frame) = rx.recv().await {
+                if let Err(e) = ws.send(frame).await {
+                    log.lock_recover()
+                        .push(format!("Mirror: disconnected: {}", e));
+                    break;
+                }
+            }
+            if rx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(MIRROR_RETRY_INTERVAL).await;
+        }
+    });
+    tx
+}
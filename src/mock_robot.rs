@@ -0,0 +1,338 @@
+// ============================================================================
+// Mock Robot Module - A simulated base serving the robot's WebSocket API
+// ============================================================================
+//
+// `MockBase` behaves like the firmware as far as the examples can tell: the
+// first session to send `ApiControlInitialize(true)` holds control until it
+// releases it or disconnects, only its moves count, and the odometry follows
+// the commanded speed with a first order lag. A parking stop ignores moves and
+// lets the base coast to a standstill. `serve` puts it behind a listener, each
+// connection gets its own session id and BaseStatus reports at the rate it set.
+
+use futures_util::{SinkExt, StreamExt};
+use prost::Message;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::base_backend;
+use super::keyboard_input::SpeedData;
+use super::lock_recover::LockRecover;
+
+/// Protocol version the mock reports in every ApiUp
+pub const MOCK_PROTOCOL_MAJOR_VERSION: u32 = 1;
+/// Default time constant of the odometry following the commanded speed
+pub const DEFAULT_ODOMETRY_LAG: Duration = Duration::from_millis(150);
+/// Without a move from the holder for this long the base stops by itself
+pub const COMMAND_TIMEOUT: Duration = Duration::from_millis(500);
+/// Status rate until a session sets its ReportFrequency, like the 50 Hz default
+const DEFAULT_REPORT_INTERVAL: Duration = Duration::from_millis(20);
+/// Battery level reported, in thousandths
+const MOCK_BATTERY_THOUSANDTH: u32 = 800;
+
+/// State of the simulated base, shared by every connection
+#[derive(Clone, Debug)]
+pub struct MockBase {
+    // Session holding API control
+    holder: Option<u32>,
+    parking_stop: bool,
+    commanded: SpeedData,
+    odometry: SpeedData,
+    odometry_lag: Duration,
+    // Last move of the holder, for the command timeout
+    last_move: Option<Instant>,
+    last_step: Instant,
+    last_session: u32,
+}
+
+impl Default for MockBase {
+    fn default() -> Self {
+        Self::new(DEFAULT_ODOMETRY_LAG)
+    }
+}
+
+impl MockBase {
+    pub fn new(odometry_lag: Duration) -> Self {
+        Self {
+            holder: None,
+            parking_stop: false,
+            commanded: SpeedData::ZERO,
+            odometry: SpeedData::ZERO,
+            odometry_lag,
+            last_move: None,
+            last_step: Instant::now(),
+            last_session: 0,
+        }
+    }
+
+    /// Session id for a new connection
+    pub fn connect(&mut self) -> u32 {
+        self.last_session += 1;
+        self.last_session
+    }
+
+    /// A connection closed, its control is released
+    pub fn disconnect(&mut self, session_id: u32, now: Instant) {
+        if self.holder == Some(session_id) {
+            self.release(now);
+        }
+    }
+
+    /// Act on a message from `session_id`. Returns the report frequency if
+    /// the message sets one, which only applies to that session.
+    pub fn apply(
+        &mut self,
+        session_id: u32,
+        message: &base_backend::ApiDown,
+        now: Instant,
+    ) -> Option<base_backend::ReportFrequency> {
+        use base_backend::{api_down::Down, base_command::Command, simple_base_move_command};
+        match &message.down {
+            Some(Down::SetReportFrequency(frequency)) => {
+                base_backend::ReportFrequency::try_from(*frequency).ok()
+            }
+            Some(Down::BaseCommand(base_backend::BaseCommand {
+                command: Some(command),
+            })) => {
+                match command {
+                    Command::ApiControlInitialize(true) => {
+                        self.holder.get_or_insert(session_id);
+                    }
+                    Command::ApiControlInitialize(false) => self.disconnect(session_id, now),
+                    Command::SimpleMoveCommand(base_backend::SimpleBaseMoveCommand {
+                        command: Some(simple_base_move_command::Command::XyzSpeed(speed)),
+                    }) if self.holder == Some(session_id) && !self.parking_stop => {
+                        self.advance(now);
+                        self.commanded =
+                            SpeedData::new(speed.speed_x, speed.speed_y, speed.speed_z);
+                        self.last_move = Some(now);
+                    }
+                    // Moves of other sessions or during a parking stop are ignored
+                    _ => {}
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Start or end a simulated parking stop. It drops the commanded speed.
+    pub fn set_parking_stop(&mut self, parking_stop: bool, now: Instant) {
+        self.advance(now);
+        self.parking_stop = parking_stop;
+        if parking_stop {
+            self.commanded = SpeedData::ZERO;
+        }
+    }
+
+    /// Status as reported at `now`
+    // The public proto may have more fields than the ones set here
+    #[allow(clippy::needless_update)]
+    pub fn status(&mut self, now: Instant) -> base_backend::BaseStatus {
+        self.advance(now);
+        base_backend::BaseStatus {
+            api_control_initialized: self.holder.is_some(),
+            session_holder: self.holder.unwrap_or_default(),
+            parking_stop_detail: self.parking_stop.then(Default::default),
+            estimated_odometry: Some(base_backend::BaseEstimatedOdometry {
+                speed_x: self.odometry.x,
+                speed_y: self.odometry.y,
+                speed_z: self.odometry.z,
+                ..Default::default()
+            }),
+            battery_thousandth: MOCK_BATTERY_THOUSANDTH,
+            ..Default::default()
+        }
+    }
+
+    fn release(&mut self, now: Instant) {
+        self.advance(now);
+        self.holder = None;
+        self.commanded = SpeedData::ZERO;
+    }
+
+    // Move the odometry towards the commanded speed for the time since the last step
+    fn advance(&mut self, now: Instant) {
+        let dt = now.saturating_duration_since(self.last_step).as_secs_f32();
+        self.last_step = self.last_step.max(now);
+        if self
+            .last_move
+            .is_some_and(|at| now.saturating_duration_since(at) > COMMAND_TIMEOUT)
+        {
+            self.commanded = SpeedData::ZERO;
+            self.last_move = None;
+        }
+        let alpha = if self.odometry_lag.is_zero() {
+            1.0
+        } else {
+            1.0 - (-dt / self.odometry_lag.as_secs_f32()).exp()
+        };
+        let step = |odometry: f32, commanded: f32| odometry + (commanded - odometry) * alpha;
+        self.odometry = SpeedData::new(
+            step(self.odometry.x, self.commanded.x),
+            step(self.odometry.y, self.commanded.y),
+            step(self.odometry.z, self.commanded.z),
+        );
+    }
+}
+
+/// Status interval of a report frequency
+pub fn report_interval(frequency: base_backend::ReportFrequency) -> Duration {
+    use base_backend::ReportFrequency;
+    let hz = match frequency {
+        ReportFrequency::Rf1000Hz => 1000,
+        ReportFrequency::Rf500Hz => 500,
+        ReportFrequency::Rf250Hz => 250,
+        ReportFrequency::Rf100Hz => 100,
+        ReportFrequency::Rf50Hz => 50,
+        ReportFrequency::Rf1Hz => 1,
+    };
+    Duration::from_secs(1) / hz
+}
+
+/// Accept connections on `listener` for `base` until accepting fails
+pub async fn serve(listener: TcpListener, base: Arc<Mutex<MockBase>>) -> std::io::Result<()> {
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let base = base.clone();
+        tokio::spawn(async move {
+            if let Err(e) = run_session(stream, &base).await {
+                log::warn!(target: "mock", "session of {} ended: {}", peer, e);
+            }
+        });
+    }
+}
+
+async fn run_session(stream: TcpStream, base: &Mutex<MockBase>) -> Result<(), tungstenite::Error> {
+    let (mut sink, mut frames) = tokio_tungstenite::accept_async(stream).await?.split();
+    let session_id = base.lock_recover().connect();
+    log::info!(target: "mock", "session {} connected", session_id);
+    let mut report = tokio::time::interval(DEFAULT_REPORT_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            frame = frames.next() => match frame {
+                Some(Ok(tungstenite::Message::Binary(bytes))) => {
+                    match base_backend::ApiDown::decode(&bytes[..]) {
+                        Ok(message) => {
+                            let frequency = base.lock_recover().apply(session_id, &message, Instant::now());
+                            if let Some(frequency) = frequency {
+                                report = tokio::time::interval(report_interval(frequency));
+                            }
+                        }
+                        Err(e) => log::warn!(target: "mock", "undecodable message: {}", e),
+                    }
+                }
+                // Clients that exit without a close frame just disconnect
+                Some(Ok(tungstenite::Message::Close(_)))
+                | Some(Err(tungstenite::Error::Protocol(
+                    tungstenite::error::ProtocolError::ResetWithoutClosingHandshake,
+                )))
+                | None => break Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(e),
+            },
+            _ = report.tick() => {
+                let status = base.lock_recover().status(Instant::now());
+                #[allow(clippy::needless_update)]
+                let message = base_backend::ApiUp {
+                    status: Some(base_backend::api_up::Status::BaseStatus(status)),
+                    session_id,
+                    protocol_major_version: MOCK_PROTOCOL_MAJOR_VERSION,
+                    ..Default::default()
+                };
+                let frame = tungstenite::Message::Binary(message.encode_to_vec().into());
+                if let Err(e) = sink.send(frame).await {
+                    break Err(e);
+                }
+            }
+        }
+    };
+    base.lock_recover().disconnect(session_id, Instant::now());
+    log::info!(target: "mock", "session {} disconnected", session_id);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{create_close_msg, create_init_msg, create_move_msg};
+
+    fn init() -> base_backend::ApiDown {
+        create_init_msg()
+    }
+
+    #[test]
+    fn first_session_to_initialize_holds_control_until_it_releases() {
+        let now = Instant::now();
+        let mut base = MockBase::default();
+        let (first, second) = (base.connect(), base.connect());
+        assert!(!base.status(now).api_control_initialized);
+
+        base.apply(first, &init(), now);
+        base.apply(second, &init(), now);
+        let status = base.status(now);
+        assert!(status.api_control_initialized);
+        assert_eq!(status.session_holder, first);
+
+        let close = create_close_msg();
+        base.apply(first, &close, now);
+        assert!(!base.status(now).api_control_initialized);
+        base.apply(second, &init(), now);
+        assert_eq!(base.status(now).session_holder, second);
+        base.disconnect(second, now);
+        assert!(!base.status(now).api_control_initialized);
+    }
+
+    #[test]
+    fn odometry_lags_the_commanded_speed() {
+        let start = Instant::now();
+        let mut base = MockBase::new(Duration::from_millis(100));
+        let session = base.connect();
+        base.apply(session, &init(), start);
+        base.apply(session, &create_move_msg(0.5, 0.0, 0.0), start);
+
+        let odometry =
+            |status: base_backend::BaseStatus| status.estimated_odometry.unwrap().speed_x;
+        let early = odometry(base.status(start + Duration::from_millis(50)));
+        let later = odometry(base.status(start + Duration::from_millis(300)));
+        assert!(early > 0.1 && early < 0.3, "{}", early);
+        assert!(later > early && later < 0.5, "{}", later);
+    }
+
+    #[test]
+    fn moves_only_count_from_the_holder_outside_a_parking_stop() {
+        let now = Instant::now();
+        let mut base = MockBase::new(Duration::ZERO);
+        let (holder, other) = (base.connect(), base.connect());
+        base.apply(holder, &init(), now);
+        base.apply(other, &create_move_msg(0.3, 0.0, 0.0), now);
+        assert_eq!(base.commanded, SpeedData::ZERO);
+
+        base.set_parking_stop(true, now);
+        base.apply(holder, &create_move_msg(0.3, 0.0, 0.0), now);
+        assert!(base.status(now).parking_stop_detail.is_some());
+        assert_eq!(base.commanded, SpeedData::ZERO);
+
+        base.set_parking_stop(false, now);
+        base.apply(holder, &create_move_msg(0.3, 0.0, 0.0), now);
+        assert_eq!(base.commanded, SpeedData::new(0.3, 0.0, 0.0));
+        // The base stops by itself once the moves stop coming
+        let stale = now + COMMAND_TIMEOUT + Duration::from_millis(10);
+        assert_eq!(base.status(stale).estimated_odometry.unwrap().speed_x, 0.0);
+    }
+
+    #[test]
+    fn report_frequency_sets_the_status_interval() {
+        let mut base = MockBase::default();
+        let session = base.connect();
+        let frequency = base.apply(
+            session,
+            &crate::client::create_set_frequency_msg(base_backend::ReportFrequency::Rf100Hz),
+            Instant::now(),
+        );
+        assert_eq!(
+            frequency.map(report_interval),
+            Some(Duration::from_millis(10))
+        );
+    }
+}
@@ -0,0 +1,263 @@
+// ============================================================================
+// Motion Queue Module - Executes discrete open-loop moves one after another
+// ============================================================================
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Instant;
+
+use super::keyboard_input::SpeedData;
+
+/// A single discrete motion. Distances are in meters, angles in radians.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Step {
+    Forward(f32),
+    Backward(f32),
+    TurnLeft(f32),
+    TurnRight(f32),
+    StrafeLeft(f32),
+    StrafeRight(f32),
+}
+
+impl Step {
+    /// Total distance (m) or angle (rad) this step has to cover
+    fn amount(&self) -> f32 {
+        match *self {
+            Step::Forward(d)
+            | Step::Backward(d)
+            | Step::TurnLeft(d)
+            | Step::TurnRight(d)
+            | Step::StrafeLeft(d)
+            | Step::StrafeRight(d) => d,
+        }
+    }
+
+    /// Speed commanded while this step is running
+    fn command(&self, linear_speed: f32, angular_speed: f32) -> SpeedData {
+        match self {
+            Step::Forward(_) => SpeedData::new(linear_speed, 0.0, 0.0),
+            Step::Backward(_) => SpeedData::new(-linear_speed, 0.0, 0.0),
+            Step::TurnLeft(_) => SpeedData::new(0.0, 0.0, angular_speed),
+            Step::TurnRight(_) => SpeedData::new(0.0, 0.0, -angular_speed),
+            // Same sign convention as the A/D keys: right is +Y
+            Step::StrafeLeft(_) => SpeedData::new(0.0, -linear_speed, 0.0),
+            Step::StrafeRight(_) => SpeedData::new(0.0, linear_speed, 0.0),
+        }
+    }
+
+    /// Absolute rate (m/s or rad/s) at which this step covers its amount
+    fn rate(&self, linear_speed: f32, angular_speed: f32) -> f32 {
+        match self {
+            Step::Forward(_) | Step::Backward(_) | Step::StrafeLeft(_) | Step::StrafeRight(_) => {
+                linear_speed.abs()
+            }
+            Step::TurnLeft(_) | Step::TurnRight(_) => angular_speed.abs(),
+        }
+    }
+
+    /// Format a distance/angle in this step's unit
+    pub fn format_amount(&self, amount: f32) -> String {
+        match self {
+            Step::TurnLeft(_) | Step::TurnRight(_) => format!("{:.0}°", amount.to_degrees()),
+            _ => format!("{:.2} m", amount),
+        }
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Step::Forward(d) => write!(f, "Forward {:.2} m", d),
+            Step::Backward(d) => write!(f, "Backward {:.2} m", d),
+            Step::TurnLeft(a) => write!(f, "Turn Left {:.0}°", a.to_degrees()),
+            Step::TurnRight(a) => write!(f, "Turn Right {:.0}°", a.to_degrees()),
+            Step::StrafeLeft(d) => write!(f, "Strafe Left {:.2} m", d),
+            Step::StrafeRight(d) => write!(f, "Strafe Right {:.2} m", d),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ActiveStep {
+    step: Step,
+    // Distance/angle covered so far, integrated from the commanded speed
+    covered: f32,
+    last_tick: Instant,
+}
+
+/// Queue of discrete motions, executed open-loop by integrating the commanded
+/// velocity over time. There is no feedback from odometry, so the result is
+/// only as accurate as the base follows its commands.
+#[derive(Clone, Debug)]
+pub struct MotionQueue {
+    pending: VecDeque<Step>,
+    active: Option<ActiveStep>,
+    linear_speed: f32,
+    angular_speed: f32,
+}
+
+impl MotionQueue {
+    pub fn new(linear_speed: f32, angular_speed: f32) -> Self {
+        Self {
+            pending: VecDeque::new(),
+            active: None,
+            linear_speed,
+            angular_speed,
+        }
+    }
+
+    pub fn push(&mut self, step: Step) {
+        self.pending.push_back(step);
+    }
+
+    /// Drop the running step and everything queued behind it.
+    /// Returns true if anything was dropped.
+    pub fn abort(&mut self) -> bool {
+        let had_work = !self.is_idle();
+        self.pending.clear();
+        self.active = None;
+        had_work
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.active.is_none() && self.pending.is_empty()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Currently running step with the estimated distance/angle covered so far
+    pub fn current(&self) -> Option<(Step, f32)> {
+        self.active
+            .map(|a| (a.step, a.covered.min(a.step.amount())))
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item = &Step> {
+        self.pending.iter()
+    }
+
+    /// Advance the queue. Returns the speed to command, or None when the
+    /// queue is idle and manual control should be used instead.
+    pub fn tick(&mut self, now: Instant) -> Option<SpeedData> {
+        if self.active.is_none() {
+            let step = self.pending.pop_front()?;
+            self.active = Some(ActiveStep {
+                step,
+                covered: 0.0,
+                last_tick: now,
+            });
+        }
+
+        let active = self.active.as_mut()?;
+        let dt = now.duration_since(active.last_tick).as_secs_f32();
+        active.last_tick = now;
+        active.covered += active.step.rate(self.linear_speed, self.angular_speed) * dt;
+
+        if active.covered >= active.step.amount() {
+            // Step done, command a stop for this tick and pick up the next one later
+            self.active = None;
+            return Some(SpeedData::ZERO);
+        }
+        Some(active.step.command(self.linear_speed, self.angular_speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    const LINEAR: f32 = 0.1;
+    const ANGULAR: f32 = 0.5;
+
+    fn after(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn step_runs_until_its_distance_is_covered() {
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        queue.push(Step::Forward(0.05));
+        let start = Instant::now();
+        assert_eq!(queue.tick(start), Some(SpeedData::new(LINEAR, 0.0, 0.0)));
+        assert!(queue.is_running());
+        // 0.1 m/s covers the 0.05 m in 500 ms
+        assert_eq!(
+            queue.tick(after(start, 400)),
+            Some(SpeedData::new(LINEAR, 0.0, 0.0))
+        );
+        assert_eq!(queue.tick(after(start, 510)), Some(SpeedData::ZERO));
+        assert!(!queue.is_running());
+        assert!(queue.is_idle());
+        assert_eq!(queue.tick(after(start, 600)), None);
+    }
+
+    #[test]
+    fn turn_runs_until_its_angle_is_covered() {
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        queue.push(Step::TurnRight(std::f32::consts::FRAC_PI_2));
+        queue.push(Step::Backward(0.1));
+        let start = Instant::now();
+        assert_eq!(queue.tick(start), Some(SpeedData::new(0.0, 0.0, -ANGULAR)));
+        // pi/2 at 0.5 rad/s takes about 3.14 s
+        assert_eq!(
+            queue.tick(after(start, 3000)),
+            Some(SpeedData::new(0.0, 0.0, -ANGULAR))
+        );
+        assert_eq!(queue.tick(after(start, 3200)), Some(SpeedData::ZERO));
+        // The next step starts on the following tick
+        assert_eq!(
+            queue.tick(after(start, 3210)),
+            Some(SpeedData::new(-LINEAR, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn abort_clears_the_queue_and_tells_if_there_was_work() {
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        assert!(!queue.abort());
+
+        queue.push(Step::Forward(1.0));
+        assert!(!queue.is_running());
+        assert!(queue.abort());
+        assert!(queue.is_idle());
+
+        queue.push(Step::Forward(1.0));
+        queue.push(Step::TurnLeft(1.0));
+        queue.tick(Instant::now());
+        assert!(queue.is_running());
+        assert!(queue.abort());
+        assert!(!queue.is_running());
+        assert_eq!(queue.pending().count(), 0);
+        assert_eq!(queue.tick(Instant::now()), None);
+    }
+
+    #[test]
+    fn strafes_follow_the_sign_of_the_a_and_d_keys() {
+        // U queues a strafe left, which moves like A (-Y); O like D (+Y)
+        let start = Instant::now();
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        queue.push(Step::StrafeLeft(0.1));
+        assert_eq!(queue.tick(start), Some(SpeedData::new(0.0, -LINEAR, 0.0)));
+
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        queue.push(Step::StrafeRight(0.1));
+        assert_eq!(queue.tick(start), Some(SpeedData::new(0.0, LINEAR, 0.0)));
+    }
+
+    #[test]
+    fn current_step_reports_the_amount_covered() {
+        let mut queue = MotionQueue::new(LINEAR, ANGULAR);
+        assert_eq!(queue.current(), None);
+        queue.push(Step::StrafeRight(0.1));
+        let start = Instant::now();
+        queue.tick(start);
+        assert_eq!(queue.current(), Some((Step::StrafeRight(0.1), 0.0)));
+
+        queue.tick(after(start, 500));
+        let (step, covered) = queue.current().unwrap();
+        assert_eq!(step, Step::StrafeRight(0.1));
+        assert!((covered - 0.05).abs() < 1e-4, "{}", covered);
+    }
+}
@@ -0,0 +1,123 @@
+// ============================================================================
+// Odometer Module - Dead-reckoning distance from reported speeds
+// ============================================================================
+
+use std::time::{Duration, Instant};
+
+use super::keyboard_input::SpeedData;
+
+/// Gaps between samples longer than this are not integrated, so a pause in
+/// the status stream doesn't turn into a jump in distance.
+const MAX_SAMPLE_GAP: Duration = Duration::from_millis(500);
+
+/// Distance travelled per axis, integrated from the actual speed over time.
+/// This is an estimate: integration error accumulates and nothing corrects it.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Odometer {
+    /// Distance along X in meters
+    pub distance_x: f32,
+    /// Distance along Y in meters
+    pub distance_y: f32,
+    /// Rotation around Z in radians
+    pub rotation_z: f32,
+    last_sample: Option<Instant>,
+}
+
+impl Odometer {
+    /// Integrate one speed sample taken at `now`
+    pub fn record(&mut self, speed: SpeedData, now: Instant) {
+        if let Some(last) = self.last_sample {
+            let gap = now.duration_since(last);
+            if gap <= MAX_SAMPLE_GAP {
+                let dt = gap.as_secs_f32();
+                self.distance_x += speed.x * dt;
+                self.distance_y += speed.y * dt;
+                self.rotation_z += speed.z * dt;
+            }
+        }
+        self.last_sample = Some(now);
+    }
+
+    /// Forget the last sample time so the next sample starts a new segment
+    pub fn interrupt(&mut self) {
+        self.last_sample = None;
+    }
+
+    /// Zero all accumulators
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn speed_is_integrated_over_the_time_between_samples() {
+        let start = Instant::now();
+        let mut odometer = Odometer::default();
+        // The first sample only starts the segment
+        odometer.record(SpeedData::new(0.5, -0.2, 0.0), start);
+        assert_eq!(odometer.distance_x, 0.0);
+        for step in 1..=4 {
+            let now = start + Duration::from_millis(100 * step);
+            odometer.record(SpeedData::new(0.5, -0.2, 0.0), now);
+        }
+        assert!(close(odometer.distance_x, 0.2), "{:?}", odometer);
+        assert!(close(odometer.distance_y, -0.08), "{:?}", odometer);
+    }
+
+    #[test]
+    fn rotation_accumulates_with_its_sign() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut odometer = Odometer::default();
+        odometer.record(SpeedData::new(0.0, 0.0, 1.0), start);
+        odometer.record(SpeedData::new(0.0, 0.0, 1.0), at(250));
+        assert!(close(odometer.rotation_z, 0.25), "{:?}", odometer);
+        odometer.record(SpeedData::new(0.0, 0.0, -2.0), at(500));
+        assert!(close(odometer.rotation_z, -0.25), "{:?}", odometer);
+        assert_eq!(odometer.distance_x, 0.0);
+    }
+
+    #[test]
+    fn gaps_and_interruptions_are_not_integrated() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut odometer = Odometer::default();
+        odometer.record(SpeedData::new(1.0, 0.0, 0.0), start);
+        odometer.record(SpeedData::new(1.0, 0.0, 0.0), at(2000));
+        assert_eq!(odometer.distance_x, 0.0);
+
+        odometer.interrupt();
+        odometer.record(SpeedData::new(1.0, 0.0, 0.0), at(2100));
+        assert_eq!(odometer.distance_x, 0.0);
+        odometer.record(SpeedData::new(1.0, 0.0, 0.0), at(2200));
+        assert!(close(odometer.distance_x, 0.1), "{:?}", odometer);
+    }
+
+    #[test]
+    fn reset_zeroes_the_distances_and_starts_a_new_segment() {
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let mut odometer = Odometer::default();
+        odometer.record(SpeedData::new(1.0, 1.0, 1.0), start);
+        odometer.record(SpeedData::new(1.0, 1.0, 1.0), at(100));
+        odometer.reset();
+        assert_eq!(
+            (
+                odometer.distance_x,
+                odometer.distance_y,
+                odometer.rotation_z
+            ),
+            (0.0, 0.0, 0.0)
+        );
+        odometer.record(SpeedData::new(1.0, 1.0, 1.0), at(200));
+        assert_eq!(odometer.distance_x, 0.0);
+    }
+}
@@ -0,0 +1,120 @@
+// ============================================================================
+// Odometry CSV Module - Commanded against actual speed, one row per BaseStatus
+// ============================================================================
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::keyboard_input::SpeedData;
+
+/// Buffered rows are written out at least this often
+const CSV_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+const CSV_HEADER: &str = "timestamp_ms,target_x,target_y,target_z,actual_x,actual_y,actual_z";
+
+/// Writes the CSV. The actual columns are left empty for a status without odometry.
+#[derive(Debug)]
+pub struct OdometryLogger<W: Write = BufWriter<File>> {
+    writer: W,
+    start: Instant,
+    last_flush: Instant,
+    // Set by the first failed write, nothing is written after it
+    failed: bool,
+}
+
+impl OdometryLogger {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> OdometryLogger<W> {
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writeln!(writer, "{}", CSV_HEADER)?;
+        let now = Instant::now();
+        Ok(Self {
+            writer,
+            start: now,
+            last_flush: now,
+            failed: false,
+        })
+    }
+
+    /// Add a row. Only the first failing write is returned, so a full disk is
+    /// reported once and not for every status.
+    pub fn record(
+        &mut self,
+        now: Instant,
+        target: SpeedData,
+        actual: Option<SpeedData>,
+    ) -> io::Result<()> {
+        if self.failed {
+            return Ok(());
+        }
+        let result = self.write_row(now, target, actual);
+        self.failed = result.is_err();
+        result
+    }
+
+    fn write_row(
+        &mut self,
+        now: Instant,
+        target: SpeedData,
+        actual: Option<SpeedData>,
+    ) -> io::Result<()> {
+        let actual = actual.map_or_else(
+            || ",,".to_string(),
+            |a| format!("{:.4},{:.4},{:.4}", a.x, a.y, a.z),
+        );
+        writeln!(
+            self.writer,
+            "{},{:.4},{:.4},{:.4},{}",
+            now.duration_since(self.start).as_millis(),
+            target.x,
+            target.y,
+            target.z,
+            actual
+        )?;
+        if now.duration_since(self.last_flush) >= CSV_FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_follow_the_header() {
+        let mut logger = OdometryLogger::new(Vec::new()).unwrap();
+        let at = logger.start + Duration::from_millis(20);
+        logger
+            .record(
+                at,
+                SpeedData::new(0.1, 0.0, -0.5),
+                Some(SpeedData::new(0.09, 0.0, -0.45)),
+            )
+            .unwrap();
+        logger.record(at, SpeedData::ZERO, None).unwrap();
+
+        let text = String::from_utf8(logger.writer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                CSV_HEADER,
+                "20,0.1000,0.0000,-0.5000,0.0900,0.0000,-0.4500",
+                "20,0.0000,0.0000,0.0000,,,",
+            ]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+// ============================================================================
+// Protocol Module - Which protocol major versions of the robot are accepted
+// ============================================================================
+//
+// Every ApiUp carries the robot's protocol major version. The first one of a
+// connection decides it: a version outside the accepted set blocks move
+// commands until the operator acknowledges it.
+
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use super::robot_ui::ProtocolIndicator;
+
+/// Protocol major versions accepted when no `--accept-protocol` is given.
+pub const DEFAULT_ACCEPTABLE_PROTOCOL_MAJOR_VERSIONS: &str = "1";
+
+/// Set of protocol major versions this client is willing to talk to.
+/// Parsed from a comma separated list where each item is either a single
+/// version (`2`) or an inclusive range (`1-3`).
+#[derive(Clone, Debug)]
+pub struct AcceptedVersions(Vec<RangeInclusive<u32>>);
+
+impl AcceptedVersions {
+    pub fn contains(&self, version: u32) -> bool {
+        self.0.iter().any(|range| range.contains(&version))
+    }
+
+    /// Lowest version of the first item, e.g. 2 for `2-3,1`
+    pub fn first(&self) -> u32 {
+        self.0.first().map_or(0, |range| *range.start())
+    }
+}
+
+impl FromStr for AcceptedVersions {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ranges = Vec::new();
+        for item in s.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let parse = |v: &str| {
+                v.trim()
+                    .parse::<u32>()
+                    .map_err(|e| format!("invalid protocol version '{}': {}", v, e))
+            };
+            let range = match item.split_once('-') {
+                Some((start, end)) => parse(start)?..=parse(end)?,
+                None => parse(item)?..=parse(item)?,
+            };
+            if range.is_empty() {
+                return Err(format!("empty protocol version range '{}'", item));
+            }
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err("at least one protocol version must be accepted".to_string());
+        }
+        Ok(Self(ranges))
+    }
+}
+
+impl std::fmt::Display for AcceptedVersions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let items: Vec<String> = self
+            .0
+            .iter()
+            .map(|range| {
+                if range.start() == range.end() {
+                    range.start().to_string()
+                } else {
+                    format!("{}-{}", range.start(), range.end())
+                }
+            })
+            .collect();
+        write!(f, "{}", items.join(","))
+    }
+}
+
+/// Protocol major version of the current connection, decided by its first ApiUp
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProtocolStatus {
+    pub version: Option<u32>,
+    /// The version isn't accepted and the operator hasn't acknowledged it yet,
+    /// no move commands are sent meanwhile
+    pub blocked: bool,
+    /// Unaccepted version the operator chose to continue with
+    pub acknowledged: Option<u32>,
+}
+
+impl ProtocolStatus {
+    /// Take the version of the first ApiUp, later ones are ignored. Returns
+    /// true if it blocks moves.
+    pub fn check(&mut self, version: u32, accepted: &AcceptedVersions) -> bool {
+        if self.version.is_some() {
+            return false;
+        }
+        self.version = Some(version);
+        self.blocked = !accepted.contains(version) && self.acknowledged != Some(version);
+        self.blocked
+    }
+
+    pub fn acknowledge(&mut self) {
+        if self.blocked {
+            self.blocked = false;
+            self.acknowledged = self.version;
+        }
+    }
+
+    /// The next connection decides again
+    pub fn reset(&mut self) {
+        self.version = None;
+        self.blocked = false;
+    }
+
+    /// Title bar protocol display, once the robot reported its version
+    pub fn indicator(&self, accepted: &AcceptedVersions) -> Option<ProtocolIndicator> {
+        self.version.map(|version| ProtocolIndicator {
+            version,
+            expected: accepted.to_string(),
+            blocked: self.blocked,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges_and_single_versions_are_accepted() {
+        let accepted: AcceptedVersions = "1-3, 5".parse().unwrap();
+        for version in [1, 2, 3, 5] {
+            assert!(accepted.contains(version), "v{}", version);
+        }
+        for version in [0, 4, 6] {
+            assert!(!accepted.contains(version), "v{}", version);
+        }
+        assert_eq!(accepted.to_string(), "1-3,5");
+        assert_eq!(accepted.first(), 1);
+    }
+
+    #[test]
+    fn empty_items_in_a_list_are_skipped() {
+        let accepted: AcceptedVersions = "2,,4,".parse().unwrap();
+        assert_eq!(accepted.to_string(), "2,4");
+        assert!(!accepted.contains(3));
+    }
+
+    #[test]
+    fn empty_or_garbage_input_is_rejected() {
+        for input in ["", " , ", "one", "1-", "-2", "1-x", "3-1", "-1"] {
+            assert!(
+                input.parse::<AcceptedVersions>().is_err(),
+                "accepted '{}'",
+                input
+            );
+        }
+        let error = "3-1".parse::<AcceptedVersions>().unwrap_err();
+        assert!(
+            error.starts_with("empty protocol version range"),
+            "{}",
+            error
+        );
+    }
+}
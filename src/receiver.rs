@@ -0,0 +1,770 @@
+// ============================================================================
+// Receiver Module - The robot's frames turned into state for the control loop
+// ============================================================================
+//
+// A task per connection decodes the ApiUp frames and updates a `SharedState`
+// the control loop and the UI read from: control state, emergency stop,
+// odometry, battery and the messages shown to the operator. A connection that
+// ends or goes silent is marked disconnected, which the reconnect picks up.
+
+use futures_util::StreamExt;
+use log::{info, warn};
+use prost::Message;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::base_backend;
+use super::client::{describe_parking_stop, ControlStatus, WsStream};
+use super::keyboard_input::SpeedData;
+use super::latency::LatencyTracker;
+use super::lock_recover::LockRecover;
+use super::log_buffer::LogBuffer;
+use super::odometer::Odometer;
+use super::odometry_csv::OdometryLogger;
+use super::protocol::{AcceptedVersions, ProtocolStatus};
+use super::robot_ui::{ControlState, ErrorMessage, ErrorSeverity};
+use super::session_log::{Direction, SessionRecorder};
+use super::smoothing::SpeedFilter;
+use super::speed_history::SpeedHistory;
+use super::status_json::StatusJsonWriter;
+
+/// Time between keepalive pings sent to the robot
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Without any frame from the robot for this long the connection counts as
+/// dead and is re-established. The pongs keep a quiet but healthy link alive.
+pub const STALE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(6);
+
+/// With --debug-frames, how many bytes of an undecodable frame are dumped
+const DECODE_DUMP_MAX_BYTES: usize = 64;
+
+/// How to treat a `BaseStatus` that arrives while we hold control but
+/// without `estimated_odometry`.
+#[derive(Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+pub enum MissingOdometry {
+    /// Keep showing the last received values
+    Hold,
+    /// Keep the last values but mark them as stale
+    Stale,
+    /// Show zero speed
+    Zero,
+    /// Show no data, like while we don't have control
+    Clear,
+}
+
+/// State shared between the WebSocket receiver and the main loop
+#[derive(Clone, Default)]
+pub struct SharedState {
+    pub control_state: Arc<Mutex<ControlState>>,
+    pub odometry_data: Arc<Mutex<Option<SpeedData>>>,
+    // The actual speed as displayed, odometry_data smoothed
+    pub displayed_speed: Arc<Mutex<SpeedFilter>>,
+    pub odometry_stale: Arc<Mutex<bool>>,
+    pub emergency_stop: Arc<Mutex<bool>>,
+    pub error_message: Arc<Mutex<ErrorMessage>>,
+    // When the last BaseStatus arrived
+    pub last_status: Arc<Mutex<Option<Instant>>>,
+    pub odometer: Arc<Mutex<Odometer>>,
+    pub log: Arc<Mutex<LogBuffer>>,
+    // Move commands sent against the statuses coming back
+    pub latency: Arc<Mutex<LatencyTracker>>,
+    // Recent actual forward speeds for the sparkline
+    pub speed_history: Arc<Mutex<SpeedHistory>>,
+    // Speed of the last move command sent
+    pub commanded: Arc<Mutex<SpeedData>>,
+    // Charge level in percent from the last BaseStatus
+    pub battery: Arc<Mutex<Option<f32>>>,
+    pub protocol: Arc<Mutex<ProtocolStatus>>,
+    // Session id from the last ApiUp of the current connection
+    pub session_id: Arc<Mutex<Option<u32>>>,
+    // Status variants we don't handle that were already reported
+    pub unhandled_statuses: Arc<Mutex<HashSet<String>>>,
+    // Operator paused the command output, zero is sent meanwhile
+    pub paused: Arc<Mutex<bool>>,
+}
+
+/// Settings the WebSocket receiver needs to interpret incoming frames
+#[derive(Clone, Debug)]
+pub struct ReceiverOptions {
+    pub accepted_protocol_versions: AcceptedVersions,
+    pub missing_odometry: MissingOdometry,
+    pub debug_frames: bool,
+    /// Every binary frame received is also queued here for the mirror task
+    pub mirror: Option<tokio::sync::mpsc::Sender<tungstenite::Message>>,
+    /// Session log of --record, for both directions
+    pub recording: Option<Recording>,
+    /// --log-csv file, a row is added for every BaseStatus
+    pub odometry_csv: Option<Arc<Mutex<OdometryLogger>>>,
+    /// --json output, a line is written for every BaseStatus
+    pub status_json: Option<Arc<Mutex<StatusJsonWriter>>>,
+}
+
+impl ReceiverOptions {
+    /// Options without any of the outputs, which only the first base feeds
+    pub fn without_outputs(&self) -> Self {
+        Self {
+            mirror: None,
+            recording: None,
+            odometry_csv: None,
+            status_json: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Session log of --record. Write failures are reported in the log.
+#[derive(Clone, Debug)]
+pub struct Recording {
+    recorder: Arc<SessionRecorder>,
+    log: Arc<Mutex<LogBuffer>>,
+}
+
+impl Recording {
+    pub fn new(recorder: SessionRecorder, log: Arc<Mutex<LogBuffer>>) -> Self {
+        Self {
+            recorder: Arc::new(recorder),
+            log,
+        }
+    }
+
+    pub fn record<T: serde::Serialize>(&self, direction: Direction, message: &T) {
+        if let Err(e) = self.recorder.record(direction, message) {
+            self.log
+                .lock_recover()
+                .push(format!("Recording stopped: {}", e));
+        }
+    }
+}
+
+// Process base status messages and update state
+pub fn handle_base_status(
+    base_status: &base_backend::BaseStatus,
+    session_id: u32,
+    shared: &SharedState,
+    missing_odometry: MissingOdometry,
+) -> ControlState {
+    let now = Instant::now();
+    *shared.last_status.lock_recover() = Some(now);
+    let was_stopped = *shared.emergency_stop.lock_recover();
+    // 0 is what a base without a battery gauge sends
+    *shared.battery.lock_recover() =
+        (base_status.battery_thousandth > 0).then(|| base_status.battery_thousandth as f32 / 10.0);
+
+    let control = ControlStatus::new(base_status, session_id);
+    // Check for parking/emergency stop
+    if let Some(ref parking_detail) = base_status.parking_stop_detail {
+        if !was_stopped {
+            warn!(target: "control", "parking stop: {}", describe_parking_stop(parking_detail));
+        }
+        // The status panel puts "EMERGENCY STOP" in front of it
+        *shared.error_message.lock_recover() = ErrorMessage::with_severity(
+            describe_parking_stop(parking_detail),
+            ErrorSeverity::Error,
+        );
+    } else {
+        if was_stopped {
+            info!(target: "control", "parking stop cleared");
+        }
+        let mut err = shared.error_message.lock_recover();
+        // The sticky stop message is replaced once the stop is gone
+        if was_stopped {
+            *err = ErrorMessage::with_severity(
+                "Emergency stop cleared".to_string(),
+                ErrorSeverity::Info,
+            );
+        } else if err.expired() {
+            *err = ErrorMessage::default();
+        }
+    }
+    *shared.emergency_stop.lock_recover() = control.emergency_stop;
+    let session_holder = base_status.session_holder;
+    let state = control.state;
+    let previous = *shared.control_state.lock_recover();
+    if state != previous {
+        info!(
+            target: "control",
+            "{:?} -> {:?} (session {}, holder {})",
+            previous,
+            state,
+            session_id,
+            session_holder
+        );
+    }
+
+    // Update odometry data if we have control
+    if state == ControlState::CanMove {
+        if let Some(ref estimated_odometry) = base_status.estimated_odometry {
+            let speed = SpeedData::new(
+                estimated_odometry.speed_x,
+                estimated_odometry.speed_y,
+                estimated_odometry.speed_z,
+            );
+            *shared.odometry_data.lock_recover() = Some(speed);
+            shared.displayed_speed.lock_recover().update(speed);
+            *shared.odometry_stale.lock_recover() = false;
+            shared.odometer.lock_recover().record(speed, now);
+            shared.speed_history.lock_recover().push(speed.x);
+        } else {
+            shared.odometer.lock_recover().interrupt();
+            // We have control but this frame carries no odometry
+            match missing_odometry {
+                MissingOdometry::Hold => {}
+                MissingOdometry::Stale => *shared.odometry_stale.lock_recover() = true,
+                MissingOdometry::Zero => {
+                    *shared.odometry_data.lock_recover() = Some(SpeedData::ZERO);
+                    shared
+                        .displayed_speed
+                        .lock_recover()
+                        .update(SpeedData::ZERO);
+                    *shared.odometry_stale.lock_recover() = false;
+                }
+                MissingOdometry::Clear => clear_odometry(shared),
+            }
+        }
+    } else {
+        shared.odometer.lock_recover().interrupt();
+        // Speeds from when we last had control would only mislead
+        clear_odometry(shared);
+    }
+
+    state
+}
+
+// The Actual Speed panel shows no data until odometry arrives again
+fn clear_odometry(shared: &SharedState) {
+    *shared.odometry_data.lock_recover() = None;
+    shared.displayed_speed.lock_recover().clear();
+    *shared.odometry_stale.lock_recover() = false;
+}
+
+/// Update the shared state from one BaseStatus frame
+pub fn process_base_status(
+    base_status: &base_backend::BaseStatus,
+    session_id: u32,
+    shared: &SharedState,
+    options: &ReceiverOptions,
+) {
+    let state = handle_base_status(base_status, session_id, shared, options.missing_odometry);
+    *shared.control_state.lock_recover() = state;
+    let actual = base_status
+        .estimated_odometry
+        .as_ref()
+        .map(|odometry| SpeedData::new(odometry.speed_x, odometry.speed_y, odometry.speed_z));
+    let target = *shared.commanded.lock_recover();
+    if let Some(csv) = &options.odometry_csv {
+        if let Err(e) = csv.lock_recover().record(Instant::now(), target, actual) {
+            shared
+                .log
+                .lock_recover()
+                .push(format!("CSV logging stopped: {}", e));
+        }
+    }
+    if let Some(json) = &options.status_json {
+        let emergency_stop = *shared.emergency_stop.lock_recover();
+        let written =
+            json.lock_recover()
+                .record(Instant::now(), state, target, actual, emergency_stop);
+        if let Err(e) = written {
+            shared
+                .log
+                .lock_recover()
+                .push(format!("JSON output stopped: {}", e));
+        }
+    }
+    // Only show control loss message when actually losing control
+    if state == ControlState::InitializedButNotHold {
+        *shared.error_message.lock_recover() =
+            ErrorMessage::new("Control in hands of another user".to_string());
+    }
+}
+
+/// Spawn task to receive and process WebSocket messages. The task ends, after
+/// marking the state disconnected, once the connection closes or fails.
+pub fn spawn_websocket_receiver(
+    mut ws_stream: futures_util::stream::SplitStream<WsStream>,
+    shared: SharedState,
+    options: ReceiverOptions,
+    last_frame: Arc<Mutex<Instant>>,
+) -> tokio::task::JoinHandle<()> {
+    let receive = async move {
+        let mut reason = "closed by the robot".to_string();
+        while let Some(msg) = ws_stream.next().await {
+            let msg = match msg {
+                Ok(msg) => msg,
+                Err(e) => {
+                    reason = e.to_string();
+                    break;
+                }
+            };
+            *last_frame.lock_recover() = Instant::now();
+            match msg {
+                tungstenite::Message::Binary(bytes) => {
+                    // Best effort: if the mirror can't keep up the frame is dropped for it
+                    if let Some(mirror) = &options.mirror {
+                        mirror
+                            .try_send(tungstenite::Message::Binary(bytes.clone()))
+                            .ok();
+                    }
+                    process_frame(&bytes, &shared, &options);
+                }
+                // Not part of the protocol, but e.g. a proxy's error page is worth seeing
+                tungstenite::Message::Text(text) => {
+                    info!(target: "connection", "text frame: {}", text);
+                    let message = format!("Text from robot: {}", text);
+                    shared.log.lock_recover().push(message.clone());
+                    *shared.error_message.lock_recover() = ErrorMessage::new(message);
+                }
+                tungstenite::Message::Close(frame) => {
+                    reason = close_reason(frame.as_ref());
+                    break;
+                }
+                // Pings are answered by tungstenite, both only count as a sign of life
+                tungstenite::Message::Ping(_)
+                | tungstenite::Message::Pong(_)
+                | tungstenite::Message::Frame(_) => {}
+            }
+        }
+        mark_disconnected(&shared, &reason);
+    };
+    // Frames are logged in the span of the connection they arrive on
+    #[cfg(feature = "tracing")]
+    let receive = tracing::Instrument::in_current_span(receive);
+    tokio::spawn(receive)
+}
+
+/// Watch for frames from the robot. If none arrived for STALE_CONNECTION_TIMEOUT
+/// the receiver is stopped, which makes the control loop reconnect.
+pub fn spawn_keepalive(
+    receiver: tokio::task::AbortHandle,
+    last_frame: Arc<Mutex<Instant>>,
+    shared: SharedState,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(PING_INTERVAL).await;
+            let silent_for = last_frame.lock_recover().elapsed();
+            if silent_for > STALE_CONNECTION_TIMEOUT {
+                receiver.abort();
+                let reason = format!("no frame for {:.0} s", silent_for.as_secs_f32());
+                mark_disconnected(&shared, &reason);
+                break;
+            }
+        }
+    })
+}
+
+/// Handle one binary frame from the robot. A frame that doesn't decode is
+/// reported and skipped, the connection stays up.
+pub fn process_frame(bytes: &[u8], shared: &SharedState, options: &ReceiverOptions) {
+    let msg = match base_backend::ApiUp::decode(bytes) {
+        Ok(msg) => msg,
+        Err(e) => {
+            let mut message = format!("Decode error: {}", e);
+            if options.debug_frames {
+                message.push_str(&format!(
+                    " ({} bytes: {})",
+                    bytes.len(),
+                    hex_dump(bytes, DECODE_DUMP_MAX_BYTES)
+                ));
+            }
+            shared.log.lock_recover().push(message.clone());
+            *shared.error_message.lock_recover() = ErrorMessage::new(message);
+            return;
+        }
+    };
+    if let Some(recording) = &options.recording {
+        recording.record(Direction::Up, &msg);
+    }
+    if let Some(log) = msg.log {
+        let message = format!("Log: {:?}", log);
+        shared.log.lock_recover().push(message.clone());
+        *shared.error_message.lock_recover() = ErrorMessage::new(message);
+    }
+    let session_id = msg.session_id;
+    *shared.session_id.lock_recover() = Some(session_id);
+    let protocol_version = msg.protocol_major_version;
+    if shared
+        .protocol
+        .lock_recover()
+        .check(protocol_version, &options.accepted_protocol_versions)
+    {
+        let message = format!(
+            "Protocol version mismatch: got v{}, accepted {}. Moves blocked, Enter continues anyway",
+            protocol_version, options.accepted_protocol_versions
+        );
+        shared.log.lock_recover().push(message.clone());
+        *shared.error_message.lock_recover() =
+            ErrorMessage::with_severity(message, ErrorSeverity::Error);
+    }
+    match msg.status {
+        Some(base_backend::api_up::Status::BaseStatus(base_status)) => {
+            shared
+                .latency
+                .lock_recover()
+                .status_received(Instant::now());
+            process_base_status(&base_status, session_id, shared, options);
+        }
+        // Unreachable with a proto that only knows BaseStatus
+        #[allow(unreachable_patterns)]
+        Some(other) => {
+            let name = status_variant_name(&other);
+            if shared
+                .unhandled_statuses
+                .lock_recover()
+                .insert(name.clone())
+            {
+                let message = format!("Unhandled status from robot: {}", name);
+                shared.log.lock_recover().push(message.clone());
+                *shared.error_message.lock_recover() = ErrorMessage::new(message);
+            }
+        }
+        None => {}
+    }
+}
+
+// Why the robot closed the connection, from its close frame
+fn close_reason(frame: Option<&tungstenite::protocol::CloseFrame>) -> String {
+    match frame {
+        Some(frame) if !frame.reason.is_empty() => format!(
+            "closed by the robot: {} (code {})",
+            frame.reason,
+            u16::from(frame.code)
+        ),
+        Some(frame) => format!("closed by the robot (code {})", u16::from(frame.code)),
+        None => "closed by the robot".to_string(),
+    }
+}
+
+// Name of the oneof variant, e.g. "BaseStatus", without its contents
+fn status_variant_name(status: &base_backend::api_up::Status) -> String {
+    let debug = format!("{:?}", status);
+    debug
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Nothing can be controlled without a connection: forget control and show
+/// that a reconnect is under way
+pub fn mark_disconnected(shared: &SharedState, reason: &str) {
+    *shared.control_state.lock_recover() = ControlState::Uninitialized;
+    *shared.odometry_stale.lock_recover() = true;
+    shared.odometer.lock_recover().interrupt();
+    *shared.latency.lock_recover() = LatencyTracker::default();
+    *shared.battery.lock_recover() = None;
+    shared.protocol.lock_recover().reset();
+    *shared.session_id.lock_recover() = None;
+    warn!(target: "connection", "lost: {}", reason);
+    let message = format!("Connection lost ({}), reconnecting...", reason);
+    shared.log.lock_recover().push(message.clone());
+    *shared.error_message.lock_recover() = ErrorMessage::new(message);
+}
+
+// Space separated hex of the first `max` bytes, noting how many were left out
+fn hex_dump(bytes: &[u8], max: usize) -> String {
+    let mut dump = bytes
+        .iter()
+        .take(max)
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if bytes.len() > max {
+        dump.push_str(&format!(" ... (+{} more)", bytes.len() - max));
+    }
+    dump
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::gated_command;
+    use crate::keyboard_input::{KeyBindings, KeyState, KeyboardInput};
+    use crate::protocol::DEFAULT_ACCEPTABLE_PROTOCOL_MAJOR_VERSIONS;
+    use crossterm::event::KeyCode;
+    use std::collections::HashMap;
+
+    const SESSION_ID: u32 = 7;
+
+    // Target speed from scripted held keys, as the keyboard handler computes it
+    fn speed_for_keys(pressed: &[char]) -> SpeedData {
+        let now = Instant::now();
+        let keys: HashMap<KeyCode, KeyState> = pressed
+            .iter()
+            .map(|c| (KeyCode::Char(*c), KeyState::pressed_at(now)))
+            .collect();
+        KeyboardInput::target_speed(&keys, &KeyBindings::default(), false, 0.1, 0.5, 1.0).0
+    }
+
+    // Feed a status frame through the receiver's handler, then gate a command
+    // the way the control loop does
+    fn command_after_status(
+        shared: &SharedState,
+        status: base_backend::BaseStatus,
+        requested: SpeedData,
+    ) -> Option<SpeedData> {
+        let state = handle_base_status(&status, SESSION_ID, shared, MissingOdometry::Stale);
+        *shared.control_state.lock_recover() = state;
+        gated_command(
+            *shared.control_state.lock_recover(),
+            *shared.emergency_stop.lock_recover(),
+            requested,
+        )
+    }
+
+    fn status_with_control(parked: bool) -> base_backend::BaseStatus {
+        base_backend::BaseStatus {
+            api_control_initialized: true,
+            session_holder: SESSION_ID,
+            parking_stop_detail: parked.then(Default::default),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn held_keys_move_while_in_control() {
+        let shared = SharedState::default();
+        let forward = speed_for_keys(&['w']);
+        let command = command_after_status(&shared, status_with_control(false), forward);
+        assert_eq!(command, Some(forward));
+    }
+
+    #[test]
+    fn emergency_stop_blocks_movement_while_keys_are_held() {
+        let shared = SharedState::default();
+        let held = speed_for_keys(&['w', 'd', 'q']);
+        assert!(!held.is_zero(0.0));
+
+        command_after_status(&shared, status_with_control(false), held);
+        for _ in 0..3 {
+            let command = command_after_status(&shared, status_with_control(true), held);
+            assert!(
+                command.is_none_or(|speed| speed.is_zero(0.0)),
+                "moved during emergency stop: {:?}",
+                command
+            );
+        }
+        assert!(*shared.emergency_stop.lock_recover());
+    }
+
+    #[test]
+    fn emergency_stop_message_stays_until_the_stop_clears() {
+        let shared = SharedState::default();
+        handle_base_status(
+            &status_with_control(true),
+            SESSION_ID,
+            &shared,
+            MissingOdometry::Stale,
+        );
+        let message = shared.error_message.lock_recover().clone();
+        assert_eq!(message.severity, ErrorSeverity::Error);
+        assert!(!message.expired());
+
+        handle_base_status(
+            &status_with_control(false),
+            SESSION_ID,
+            &shared,
+            MissingOdometry::Stale,
+        );
+        let message = shared.error_message.lock_recover().clone();
+        assert_eq!(message.severity, ErrorSeverity::Info);
+        assert_eq!(message.message, "Emergency stop cleared");
+    }
+
+    fn receiver_options() -> ReceiverOptions {
+        ReceiverOptions {
+            accepted_protocol_versions: DEFAULT_ACCEPTABLE_PROTOCOL_MAJOR_VERSIONS.parse().unwrap(),
+            missing_odometry: MissingOdometry::Stale,
+            debug_frames: true,
+            mirror: None,
+            recording: None,
+            odometry_csv: None,
+            status_json: None,
+        }
+    }
+
+    fn status_frame() -> Vec<u8> {
+        // Only the status matters, whatever else the message carries
+        #[allow(clippy::needless_update)]
+        let up = base_backend::ApiUp {
+            status: Some(base_backend::api_up::Status::BaseStatus(
+                status_with_control(false),
+            )),
+            session_id: SESSION_ID,
+            protocol_major_version: 1,
+            ..Default::default()
+        };
+        up.encode_to_vec()
+    }
+
+    #[test]
+    fn odometry_is_only_shown_while_in_control() {
+        let shared = SharedState::default();
+        let moving = base_backend::BaseStatus {
+            estimated_odometry: Some(base_backend::BaseEstimatedOdometry {
+                speed_x: 0.2,
+                speed_y: 0.0,
+                speed_z: 0.1,
+            }),
+            ..status_with_control(false)
+        };
+        let expected = Some(SpeedData::new(0.2, 0.0, 0.1));
+        handle_base_status(&moving, SESSION_ID, &shared, MissingOdometry::Clear);
+        assert_eq!(*shared.odometry_data.lock_recover(), expected);
+        assert_eq!(shared.displayed_speed.lock_recover().value(), expected);
+
+        // Another session took over, its odometry isn't ours to show
+        let other_session = base_backend::BaseStatus {
+            session_holder: SESSION_ID + 1,
+            ..moving.clone()
+        };
+        handle_base_status(&other_session, SESSION_ID, &shared, MissingOdometry::Hold);
+        assert_eq!(*shared.odometry_data.lock_recover(), None);
+        assert_eq!(shared.displayed_speed.lock_recover().value(), None);
+
+        // In control again, but the frame has no odometry
+        handle_base_status(&moving, SESSION_ID, &shared, MissingOdometry::Clear);
+        handle_base_status(
+            &status_with_control(false),
+            SESSION_ID,
+            &shared,
+            MissingOdometry::Clear,
+        );
+        assert_eq!(*shared.odometry_data.lock_recover(), None);
+        assert!(!*shared.odometry_stale.lock_recover());
+    }
+
+    #[test]
+    fn battery_is_unknown_when_not_reported() {
+        let shared = SharedState::default();
+        let status = base_backend::BaseStatus {
+            battery_thousandth: 155,
+            ..status_with_control(false)
+        };
+        handle_base_status(&status, SESSION_ID, &shared, MissingOdometry::Hold);
+        assert_eq!(*shared.battery.lock_recover(), Some(15.5));
+        handle_base_status(
+            &status_with_control(false),
+            SESSION_ID,
+            &shared,
+            MissingOdometry::Hold,
+        );
+        assert_eq!(*shared.battery.lock_recover(), None);
+    }
+
+    #[test]
+    fn valid_frame_updates_state() {
+        let shared = SharedState::default();
+        process_frame(&status_frame(), &shared, &receiver_options());
+        assert_eq!(*shared.control_state.lock_recover(), ControlState::CanMove);
+    }
+
+    #[test]
+    fn session_id_is_known_until_disconnected() {
+        let shared = SharedState::default();
+        assert_eq!(*shared.session_id.lock_recover(), None);
+        process_frame(&status_frame(), &shared, &receiver_options());
+        assert_eq!(*shared.session_id.lock_recover(), Some(SESSION_ID));
+        mark_disconnected(&shared, "test");
+        assert_eq!(*shared.session_id.lock_recover(), None);
+    }
+
+    #[test]
+    fn close_frame_reason_is_shown() {
+        use tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+        let frame = CloseFrame {
+            code: CloseCode::Away,
+            reason: "maintenance".into(),
+        };
+        assert_eq!(
+            close_reason(Some(&frame)),
+            "closed by the robot: maintenance (code 1001)"
+        );
+        let frame = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        assert_eq!(
+            close_reason(Some(&frame)),
+            "closed by the robot (code 1000)"
+        );
+        assert_eq!(close_reason(None), "closed by the robot");
+    }
+
+    #[test]
+    fn status_variants_are_named_without_contents() {
+        let status = base_backend::api_up::Status::BaseStatus(status_with_control(true));
+        assert_eq!(status_variant_name(&status), "BaseStatus");
+
+        // The handled variant is never reported as unhandled
+        let shared = SharedState::default();
+        process_frame(&status_frame(), &shared, &receiver_options());
+        assert!(shared.unhandled_statuses.lock_recover().is_empty());
+    }
+
+    #[test]
+    fn protocol_mismatch_blocks_until_acknowledged() {
+        let shared = SharedState::default();
+        let options = ReceiverOptions {
+            accepted_protocol_versions: "2-3".parse().unwrap(),
+            ..receiver_options()
+        };
+        process_frame(&status_frame(), &shared, &options);
+        let protocol = *shared.protocol.lock_recover();
+        assert_eq!(protocol.version, Some(1));
+        assert!(protocol.blocked);
+        let message = shared.error_message.lock_recover().message.clone();
+        assert!(
+            message.starts_with("Protocol version mismatch"),
+            "{}",
+            message
+        );
+
+        shared.protocol.lock_recover().acknowledge();
+        process_frame(&status_frame(), &shared, &options);
+        assert!(!shared.protocol.lock_recover().blocked);
+        let indicator = shared
+            .protocol
+            .lock_recover()
+            .indicator(&options.accepted_protocol_versions)
+            .unwrap();
+        assert_eq!((indicator.version, indicator.expected.as_str()), (1, "2-3"));
+    }
+
+    #[test]
+    fn accepted_protocol_does_not_block() {
+        let shared = SharedState::default();
+        process_frame(&status_frame(), &shared, &receiver_options());
+        let protocol = *shared.protocol.lock_recover();
+        assert_eq!(protocol.version, Some(1));
+        assert!(!protocol.blocked);
+    }
+
+    #[test]
+    fn malformed_frames_are_reported_not_fatal() {
+        let shared = SharedState::default();
+        let frame = status_frame();
+        let truncated = &frame[..frame.len() - 1];
+        for bytes in [truncated, &[0xff, 0xff, 0xff][..], b"not protobuf at all"] {
+            process_frame(bytes, &shared, &receiver_options());
+            let message = shared.error_message.lock_recover().message.clone();
+            assert!(message.starts_with("Decode error"), "{}", message);
+            assert_eq!(
+                *shared.control_state.lock_recover(),
+                ControlState::Uninitialized
+            );
+        }
+        // The receiver keeps going after them
+        process_frame(&frame, &shared, &receiver_options());
+        assert_eq!(*shared.control_state.lock_recover(), ControlState::CanMove);
+    }
+
+    #[test]
+    fn emergency_stop_zeroes_even_if_control_is_still_reported() {
+        assert_eq!(
+            gated_command(ControlState::CanMove, true, speed_for_keys(&['w'])),
+            Some(SpeedData::ZERO)
+        );
+    }
+}
@@ -0,0 +1,1844 @@
+// ============================================================================
+// Robot UI Module - Handles terminal UI rendering with ratatui
+// ============================================================================
+
+use crossterm::{
+    event::KeyCode,
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Sparkline, Wrap},
+    Frame, Terminal,
+};
+use std::collections::HashMap;
+use std::io;
+use std::time::Instant;
+
+use super::camera::CameraMount;
+use super::frame_rate::FrameRate;
+use super::keyboard_input::KeyState;
+use super::keyboard_input::SpeedData;
+use super::keyboard_input::SpeedMode;
+use super::log_buffer::LogBuffer;
+use super::motion_queue::MotionQueue;
+use super::odometer::Odometer;
+use super::smoothing::SmoothingProfile;
+use super::speed_history::SpeedHistory;
+use super::units::UnitSystem;
+
+/// Control state of the robot
+#[derive(PartialEq, Clone, Copy, Debug, Default, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlState {
+    #[default]
+    Uninitialized,
+    InitializedButNotHold,
+    CanMove,
+}
+
+/// How much a status message matters, which sets its color and how long it stays
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorSeverity {
+    /// Feedback on something the operator did
+    Info,
+    #[default]
+    Warn,
+    /// Stays until replaced or cleared
+    Error,
+}
+
+impl ErrorSeverity {
+    /// How long a message is shown by default, None until replaced or cleared
+    pub fn default_expiry(self) -> Option<std::time::Duration> {
+        match self {
+            ErrorSeverity::Info => Some(std::time::Duration::from_secs(2)),
+            ErrorSeverity::Warn => Some(std::time::Duration::from_secs(3)),
+            ErrorSeverity::Error => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            ErrorSeverity::Info => "Info",
+            ErrorSeverity::Warn => "Warn",
+            ErrorSeverity::Error => "Error",
+        }
+    }
+}
+
+/// Error message with timestamp
+#[derive(Clone, Debug, Default)]
+pub struct ErrorMessage {
+    pub message: String,
+    pub timestamp: Option<std::time::Instant>,
+    pub severity: ErrorSeverity,
+    /// Time after which the message is cleared, None keeps it
+    pub expires_after: Option<std::time::Duration>,
+}
+
+impl ErrorMessage {
+    /// A warning, cleared after the warning expiry
+    pub fn new(message: String) -> Self {
+        Self::with_severity(message, ErrorSeverity::Warn)
+    }
+
+    /// A message expiring after the default of `severity`
+    pub fn with_severity(message: String, severity: ErrorSeverity) -> Self {
+        Self {
+            message,
+            timestamp: Some(std::time::Instant::now()),
+            severity,
+            expires_after: severity.default_expiry(),
+        }
+    }
+
+    /// Replace the expiry the severity set
+    pub fn expiring_after(mut self, expires_after: Option<std::time::Duration>) -> Self {
+        self.expires_after = expires_after;
+        self
+    }
+
+    /// True once the message's own expiry has passed
+    pub fn expired(&self) -> bool {
+        self.expires_after
+            .is_some_and(|expires_after| self.is_expired(expires_after))
+    }
+
+    pub fn is_expired(&self, duration: std::time::Duration) -> bool {
+        if let Some(ts) = self.timestamp {
+            ts.elapsed() > duration
+        } else {
+            false
+        }
+    }
+}
+
+/// Stage of taking control from another session
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Takeover {
+    /// Waiting for the operator to confirm
+    Confirm,
+    /// Sent, waiting for the base to hand over control
+    Requested,
+}
+
+/// Protocol version shown in the title bar
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProtocolIndicator {
+    pub version: u32,
+    /// Accepted versions, e.g. `1` or `1-3`
+    pub expected: String,
+    /// Not accepted and not acknowledged yet
+    pub blocked: bool,
+}
+
+/// State of follow-leader mode for the UI
+#[derive(Clone, Copy, Debug)]
+pub struct FollowIndicator {
+    /// Commands currently follow the leader
+    pub engaged: bool,
+    /// Short description of the leader connection, e.g. "connected"
+    pub link: &'static str,
+    /// Leader's last reported velocity, if still fresh
+    pub leader_speed: Option<SpeedData>,
+}
+
+/// One base of a session driving several, for the bases panel
+#[derive(Clone, Debug, PartialEq)]
+pub struct BaseSummary {
+    pub url: String,
+    pub control_state: ControlState,
+    pub emergency_stop: bool,
+    pub actual_speed: Option<SpeedData>,
+    /// The movement keys drive this base
+    pub active: bool,
+}
+
+/// Snapshot of everything the UI shows in one frame
+pub struct UiView<'a> {
+    pub control_state: ControlState,
+    pub target_speed: SpeedData,
+    pub actual_speed: Option<SpeedData>,
+    pub actual_speed_stale: bool,
+    pub pressed_keys: &'a HashMap<KeyCode, KeyState>,
+    pub error_message: &'a ErrorMessage,
+    pub emergency_stop: bool,
+    pub motion_queue: &'a MotionQueue,
+    pub odometer: Odometer,
+    pub smoothing: SmoothingProfile,
+    /// Velocity held by cruise control, if engaged
+    pub cruise: Option<SpeedData>,
+    pub log: &'a LogBuffer,
+    /// Estimated wheel speeds (name, rad/s) of the last command, when the
+    /// base geometry is known
+    pub wheel_speeds: Option<&'a [(&'static str, f32)]>,
+    /// Convey state with text markers and modifiers only, no colors
+    pub monochrome: bool,
+    /// Robot the demo is connected to, None in the preview
+    pub url: Option<&'a str>,
+    /// Session id the robot assigned, None until it did
+    pub session_id: Option<u32>,
+    /// Name of the active settings profile
+    pub profile: Option<&'a str>,
+    /// Camera orientation operator input is taken relative to
+    pub camera: CameraMount,
+    /// Progress of holding the exit key, when hold-to-exit is on and it is held
+    pub exit_hold_progress: Option<f32>,
+    /// Recent actual forward speeds, drawn under the actual speed
+    pub speed_history: &'a SpeedHistory,
+    /// Number of newest log entries scrolled past, 0 follows the log
+    pub log_scroll: usize,
+    /// Rolling average round trip to the robot, None without recent samples
+    pub latency: Option<std::time::Duration>,
+    /// Charge level in percent, None when the base doesn't report it
+    pub battery: Option<f32>,
+    /// Protocol version the robot reported, None until it did
+    pub protocol: Option<ProtocolIndicator>,
+    /// Taking control from another session, while that is in progress
+    pub takeover: Option<Takeover>,
+    /// Command output is paused, zero is sent whatever the input
+    pub paused: bool,
+    /// The exit key was pressed once, a second press exits
+    pub confirm_exit: bool,
+    /// Modifier currently scaling the movement keys
+    pub speed_mode: SpeedMode,
+    /// Whether the dead-man key is held, None when it isn't required
+    pub deadman: Option<bool>,
+    /// Per-axis maximum speeds; when given, speeds also show as a percentage of them
+    pub max_speed: Option<SpeedData>,
+    /// Units the speed panels are shown in
+    pub units: UnitSystem,
+    /// Speeds (m/s, rad/s) the movement keys map to, changed with the speed keys
+    pub key_speeds: (f32, f32),
+    /// Follow-leader mode, when a leader is configured
+    pub follow: Option<FollowIndicator>,
+    /// Idle for a while: draw everything at reduced intensity
+    pub dimmed: bool,
+    /// Key binding overlay over the panels, toggled with H or ?
+    pub show_help: bool,
+    /// Every base of the session when there are several, the other panels
+    /// show the active one. Empty with a single base.
+    pub bases: &'a [BaseSummary],
+}
+
+/// Smallest terminal the panels fit in: the fixed rows from the title to the
+/// status plus three log lines, and 80 columns for the side by side speed
+/// panels. Narrower only the ends of the longest key hints would be cut off.
+pub const MIN_TERMINAL_WIDTH: u16 = 80;
+pub const MIN_TERMINAL_HEIGHT: u16 = 36;
+
+/// Every default binding, as listed by the help overlay
+const HELP_BINDINGS: &[(&str, &str)] = &[
+    ("W/↑  S/↓", "Forward / backward"),
+    ("A/←  D/→", "Left / right"),
+    ("Q  E", "Rotate left / right"),
+    ("Shift  Ctrl", "Turbo / precision while held"),
+    ("+  -", "Linear key speed up / down"),
+    ("]  [", "Angular key speed up / down"),
+    ("I  K", "Step forward / backward"),
+    ("J  L", "Turn 90° left / right"),
+    ("U  O", "Strafe left / right"),
+    ("X", "Clear the queue, disengage cruise"),
+    ("V", "Cruise on / off"),
+    ("B", "Follow the leader on / off"),
+    ("F", "Next smoothing profile"),
+    ("M", "Next camera mount"),
+    ("P", "Next settings profile"),
+    ("N", "Next theme"),
+    ("Z", "Pause / resume, zero is sent while paused"),
+    ("0", "Reset the odometer"),
+    ("R", "Recover from a parking stop"),
+    ("T", "Take over control, twice to confirm"),
+    ("Enter", "Accept a protocol mismatch"),
+    ("PgUp  PgDn  End", "Scroll the log"),
+    ("Tab", "Dead-man key with --deadman, else the next base"),
+    ("Space", "Emergency stop"),
+    ("Backspace", "Release the emergency stop"),
+    ("G", "Stop, then release and exit"),
+    ("C", "Exit"),
+    ("H  ?", "Close this help"),
+];
+
+/// Color scheme of the UI
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Deserialize,
+    serde::Serialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Dark,
+    /// Dark text for terminals with a light background
+    Light,
+    /// Bright colors only, for projectors and poor displays
+    HighContrast,
+    /// No colors, state is conveyed by text markers and modifiers (like --no-color)
+    Monochrome,
+}
+
+impl ThemeName {
+    /// Palette to draw with. Monochrome drops the colors from the finished
+    /// frame, so it keeps the dark one.
+    pub fn theme(self) -> Theme {
+        match self {
+            ThemeName::Dark | ThemeName::Monochrome => Theme::DARK,
+            ThemeName::Light => Theme::LIGHT,
+            ThemeName::HighContrast => Theme::HIGH_CONTRAST,
+        }
+    }
+
+    /// Next theme, used by the runtime toggle key
+    pub fn next(self) -> Self {
+        match self {
+            ThemeName::Dark => ThemeName::Light,
+            ThemeName::Light => ThemeName::HighContrast,
+            ThemeName::HighContrast => ThemeName::Monochrome,
+            ThemeName::Monochrome => ThemeName::Dark,
+        }
+    }
+
+    /// Name as given to --theme
+    pub fn name(self) -> &'static str {
+        match self {
+            ThemeName::Dark => "dark",
+            ThemeName::Light => "light",
+            ThemeName::HighContrast => "high-contrast",
+            ThemeName::Monochrome => "monochrome",
+        }
+    }
+}
+
+/// Palette every panel is drawn with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Theme {
+    pub title: Color,
+    /// Values and other regular text
+    pub text: Color,
+    /// Labels in front of values
+    pub label: Color,
+    /// Hints, idle axes and stale values
+    pub muted: Color,
+    /// Current settings such as the smoothing profile
+    pub accent: Color,
+    /// Held keys and badges
+    pub active_fg: Color,
+    pub active_bg: Color,
+    pub ok: Color,
+    pub warn: Color,
+    pub error: Color,
+    /// Follow-leader mode
+    pub follow: Color,
+    pub border: Color,
+}
+
+impl Theme {
+    /// The original colors, for dark backgrounds
+    pub const DARK: Theme = Theme {
+        title: Color::Cyan,
+        text: Color::White,
+        label: Color::Gray,
+        muted: Color::DarkGray,
+        accent: Color::Cyan,
+        active_fg: Color::Black,
+        active_bg: Color::Green,
+        ok: Color::Green,
+        warn: Color::Yellow,
+        error: Color::Red,
+        follow: Color::Magenta,
+        border: Color::Reset,
+    };
+
+    pub const LIGHT: Theme = Theme {
+        title: Color::Blue,
+        text: Color::Black,
+        label: Color::DarkGray,
+        muted: Color::Gray,
+        accent: Color::Blue,
+        active_fg: Color::White,
+        active_bg: Color::Green,
+        ok: Color::Green,
+        // Yellow is unreadable on white, a dark orange from the 256 color palette
+        warn: Color::Indexed(130),
+        error: Color::Red,
+        follow: Color::Magenta,
+        border: Color::DarkGray,
+    };
+
+    pub const HIGH_CONTRAST: Theme = Theme {
+        title: Color::LightCyan,
+        text: Color::White,
+        label: Color::White,
+        muted: Color::Gray,
+        accent: Color::LightCyan,
+        active_fg: Color::Black,
+        active_bg: Color::LightGreen,
+        ok: Color::LightGreen,
+        warn: Color::LightYellow,
+        error: Color::LightRed,
+        follow: Color::LightMagenta,
+        border: Color::White,
+    };
+
+    /// Bordered panel in the theme's border color
+    fn block(&self) -> Block<'static> {
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(self.border))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::DARK
+    }
+}
+
+/// False when the environment asks for no colors (`NO_COLOR`) or the
+/// terminal can't show them (`TERM=dumb`)
+pub fn terminal_supports_color() -> bool {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    !no_color && !dumb
+}
+
+/// " (80%)" for `value` against `max`, nothing when no max is given. A zero
+/// max has no meaningful percentage and shows as "(-)".
+fn percent_of_max(value: f32, max: Option<f32>) -> String {
+    match max {
+        Some(max) if max.abs() > f32::EPSILON => {
+            format!(" ({:+.0}%)", value / max.abs() * 100.0)
+        }
+        Some(_) => " (-)".to_string(),
+        None => String::new(),
+    }
+}
+
+/// Where the main loop shows its state, drawn once per tick
+pub trait Ui {
+    /// Draw the complete UI from a view of the current state
+    fn draw(&mut self, view: &UiView) -> io::Result<()>;
+
+    /// Draw the screen shown while connecting to `url`, with the error of the
+    /// last failed attempt if there was one
+    fn draw_connection(&mut self, url: &str, error: Option<&str>) -> io::Result<()>;
+
+    /// Give the terminal back, e.g. before printing an error to it
+    fn cleanup(&mut self) -> io::Result<()>;
+
+    /// The last frame didn't fit and showed the too small warning instead,
+    /// the movement keys are ignored until it fits again
+    fn too_small(&self) -> bool {
+        false
+    }
+
+    /// Draw with `theme` from the next frame on
+    fn set_theme(&mut self, _theme: Theme) {}
+}
+
+/// Main UI Manager for robot control interface
+pub struct RobotUi {
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    theme: Theme,
+    // Set while the FPS indicator is shown
+    frame_rate: Option<FrameRate>,
+    too_small: bool,
+}
+
+impl RobotUi {
+    /// Initialize the UI terminal
+    pub fn new(theme: Theme) -> io::Result<Self> {
+        enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        let backend = CrosstermBackend::new(stdout);
+        let terminal = Terminal::new(backend)?;
+
+        Ok(Self {
+            terminal,
+            theme,
+            frame_rate: None,
+            too_small: false,
+        })
+    }
+
+    /// Show the rate of successful draws in the top right corner
+    pub fn set_show_fps(&mut self, show: bool) {
+        self.frame_rate = show.then(FrameRate::default);
+    }
+
+    /// FPS indicator on the top border of the title, `--` until two frames were drawn
+    fn render_fps(f: &mut Frame, fps: Option<f32>, theme: &Theme) {
+        let text = match fps {
+            Some(fps) => format!(" FPS: {:.0} ", fps),
+            None => " FPS: -- ".to_string(),
+        };
+        let area = f.area();
+        let width = (text.chars().count() as u16).min(area.width.saturating_sub(2));
+        let corner = Rect::new(
+            area.right().saturating_sub(width + 1),
+            area.y,
+            width,
+            area.height.min(1),
+        );
+        f.render_widget(
+            Paragraph::new(text).style(Style::default().fg(theme.muted)),
+            corner,
+        );
+    }
+
+    fn render_connection(f: &mut Frame, url: &str, error: Option<&str>, theme: &Theme) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Title
+                Constraint::Min(7),    // Connection
+            ])
+            .split(f.area());
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("Robot: ", Style::default().fg(theme.label)),
+                Span::styled(url.to_string(), Style::default().fg(theme.text)),
+            ]),
+            Line::from(""),
+        ];
+        match error {
+            Some(error) => {
+                lines.push(Line::from(Span::styled(
+                    format!("Connection failed: {}", error),
+                    Style::default()
+                        .fg(theme.error)
+                        .add_modifier(Modifier::BOLD),
+                )));
+                lines.push(Line::from(""));
+                lines.push(Line::from(vec![
+                    Span::styled("[R]", Style::default().fg(theme.ok)),
+                    Span::styled(" Retry   ", Style::default().fg(theme.text)),
+                    Span::styled("[Esc/C]", Style::default().fg(theme.error)),
+                    Span::styled(" Quit", Style::default().fg(theme.text)),
+                ]));
+            }
+            None => lines.push(Line::from(Span::styled(
+                "Connecting...",
+                Style::default().fg(theme.accent),
+            ))),
+        }
+
+        f.render_widget(
+            Self::render_title(theme, Some(url), None, None, None, false),
+            chunks[0],
+        );
+        f.render_widget(
+            Paragraph::new(lines)
+                .alignment(Alignment::Center)
+                .block(theme.block().title("Connection")),
+            chunks[1],
+        );
+    }
+
+    /// Whether the panels of `view` fit into `area` without being clipped
+    pub fn fits(area: Rect, view: &UiView) -> bool {
+        area.width >= MIN_TERMINAL_WIDTH && area.height >= Self::min_height(view)
+    }
+
+    // The dead-man line and the bases panel take more rows
+    fn min_height(view: &UiView) -> u16 {
+        MIN_TERMINAL_HEIGHT + u16::from(view.deadman.is_some()) + Self::bases_height(view)
+    }
+
+    // One line per base when driving several, as a bordered panel
+    fn bases_height(view: &UiView) -> u16 {
+        if view.bases.is_empty() {
+            0
+        } else {
+            view.bases.len() as u16 + 2
+        }
+    }
+
+    /// Render one frame. Independent of the terminal backend so it can also
+    /// be driven by a test backend.
+    pub fn render(f: &mut Frame, view: &UiView, theme: &Theme) {
+        let size = f.area();
+        if !Self::fits(size, view) {
+            Self::render_too_small(f, Self::min_height(view), theme);
+            return;
+        }
+        let bases_height = Self::bases_height(view);
+
+        // Create main layout
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Title
+                Constraint::Length(bases_height),
+                // Control hints, one more line for the dead-man state
+                Constraint::Length(if view.deadman.is_some() { 9 } else { 8 }),
+                Constraint::Length(12), // Speed displays
+                Constraint::Length(3),  // Odometer
+                Constraint::Length(4),  // Motion queue
+                Constraint::Length(3),  // Status
+                Constraint::Min(3),     // Log
+            ])
+            .split(size);
+
+        // Render each section
+        f.render_widget(
+            Self::render_title(
+                theme,
+                view.url,
+                view.session_id,
+                view.profile,
+                view.protocol.as_ref(),
+                view.monochrome,
+            ),
+            chunks[0],
+        );
+        if !view.bases.is_empty() {
+            f.render_widget(
+                Self::render_bases(theme, view.bases, view.units, view.monochrome),
+                chunks[1],
+            );
+        }
+        f.render_widget(
+            Self::render_controls(
+                theme,
+                view.pressed_keys,
+                view.speed_mode,
+                view.deadman,
+                view.monochrome,
+            ),
+            chunks[2],
+        );
+
+        // Speed displays
+        let speed_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(50), // Target speed
+                Constraint::Percentage(50), // Actual speed
+            ])
+            .split(chunks[3]);
+
+        f.render_widget(
+            Self::render_target_speed(
+                theme,
+                &view.target_speed,
+                view.smoothing,
+                view.cruise,
+                view.camera,
+                view.max_speed,
+                view.units,
+                view.key_speeds,
+                view.follow,
+                view.monochrome,
+            ),
+            speed_chunks[0],
+        );
+        let actual_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(5),    // Actual speed values
+                Constraint::Length(5), // Speed history
+            ])
+            .split(speed_chunks[1]);
+        f.render_widget(
+            Self::render_actual_speed(
+                theme,
+                &view.actual_speed,
+                view.actual_speed_stale,
+                view.max_speed,
+                view.units,
+            ),
+            actual_chunks[0],
+        );
+        f.render_widget(
+            Self::render_speed_history(theme, view.speed_history, view.max_speed.map(|m| m.x)),
+            actual_chunks[1],
+        );
+        match view.wheel_speeds {
+            Some(wheel_speeds) => {
+                let odometer_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                    .split(chunks[4]);
+                f.render_widget(
+                    Self::render_odometer(theme, &view.odometer),
+                    odometer_chunks[0],
+                );
+                f.render_widget(
+                    Self::render_wheel_speeds(theme, wheel_speeds),
+                    odometer_chunks[1],
+                );
+            }
+            None => f.render_widget(Self::render_odometer(theme, &view.odometer), chunks[4]),
+        }
+        f.render_widget(
+            Self::render_motion_queue(theme, view.motion_queue),
+            chunks[5],
+        );
+        f.render_widget(
+            Self::render_status(
+                theme,
+                view.control_state,
+                view.error_message,
+                view.emergency_stop,
+                view.exit_hold_progress,
+                view.latency,
+                view.battery,
+                view.takeover,
+                view.paused,
+                view.confirm_exit,
+                view.monochrome,
+            ),
+            chunks[6],
+        );
+        f.render_widget(
+            Self::render_log(
+                theme,
+                view.log,
+                view.log_scroll,
+                chunks[7].width,
+                chunks[7].height,
+            ),
+            chunks[7],
+        );
+
+        if view.show_help {
+            Self::render_help(f, theme);
+        }
+
+        // Dimming for idle kiosk displays works on the finished frame as well
+        if view.dimmed {
+            for cell in f.buffer_mut().content.iter_mut() {
+                if cell.fg == theme.text || cell.fg == theme.label || cell.fg == Color::Reset {
+                    cell.set_fg(theme.muted);
+                }
+                cell.modifier.insert(Modifier::DIM);
+            }
+        }
+
+        // Every cue that matters also has a marker or modifier, so the colors
+        // can simply be dropped from the finished frame
+        if view.monochrome {
+            for cell in f.buffer_mut().content.iter_mut() {
+                cell.set_fg(Color::Reset).set_bg(Color::Reset);
+            }
+        }
+    }
+
+    /// Shown instead of panels that would be clipped. The draw of every tick
+    /// picks up the terminal's new size, so the panels are back once it is
+    /// large enough.
+    fn render_too_small(f: &mut Frame, min_height: u16, theme: &Theme) {
+        let area = f.area();
+        let message = format!(
+            "Terminal too small (need at least {}x{}), movement keys ignored",
+            MIN_TERMINAL_WIDTH, min_height
+        );
+        let height = area.height.min(3);
+        let rows = Rect::new(
+            area.x,
+            area.y + (area.height - height) / 2,
+            area.width,
+            height,
+        );
+        f.render_widget(
+            Paragraph::new(message)
+                .style(Style::default().fg(theme.warn).add_modifier(Modifier::BOLD))
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            rows,
+        );
+    }
+
+    /// Help overlay centered over the panels, clipped to the terminal
+    fn render_help(f: &mut Frame, theme: &Theme) {
+        let key_width = HELP_BINDINGS
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let lines: Vec<Line> = HELP_BINDINGS
+            .iter()
+            .map(|(keys, action)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {:<width$}  ", keys, width = key_width),
+                        Style::default()
+                            .fg(theme.accent)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    Span::styled(action.to_string(), Style::default().fg(theme.text)),
+                ])
+            })
+            .collect();
+        let text_width = lines.iter().map(Line::width).max().unwrap_or(0) as u16;
+
+        let area = f.area();
+        let width = (text_width + 3).min(area.width);
+        let height = (lines.len() as u16 + 2).min(area.height);
+        let overlay = Rect::new(
+            area.x + (area.width - width) / 2,
+            area.y + (area.height - height) / 2,
+            width,
+            height,
+        );
+        f.render_widget(Clear, overlay);
+        f.render_widget(
+            Paragraph::new(lines).block(theme.block().title("Help")),
+            overlay,
+        );
+    }
+
+    /// Render the title bar
+    fn render_title(
+        theme: &Theme,
+        url: Option<&str>,
+        session_id: Option<u32>,
+        profile: Option<&str>,
+        protocol: Option<&ProtocolIndicator>,
+        monochrome: bool,
+    ) -> Paragraph<'static> {
+        let mut title = "Robot Base Advanced Control".to_string();
+        match (url, session_id) {
+            (Some(url), Some(session_id)) => {
+                title.push_str(&format!(" - {} (session {})", url, session_id))
+            }
+            (Some(url), None) => title.push_str(&format!(" - {} (connecting...)", url)),
+            (None, _) => {}
+        }
+        if let Some(profile) = profile {
+            title.push_str(&format!(" - profile: {}", profile));
+        }
+        if let Some(protocol) = protocol {
+            title.push_str(&format!(
+                " | Protocol v{} (expected v{})",
+                protocol.version, protocol.expected
+            ));
+        }
+
+        // An unaccepted version turns the title into a banner until acknowledged
+        if let Some(protocol) = protocol.filter(|protocol| protocol.blocked) {
+            let banner = format!(
+                "PROTOCOL MISMATCH: v{} (expected v{}), moves blocked - [Enter] to continue anyway",
+                protocol.version, protocol.expected
+            );
+            let style = if monochrome {
+                Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD)
+            };
+            return Paragraph::new(banner)
+                .style(style)
+                .alignment(Alignment::Center)
+                .block(theme.block().border_style(style));
+        }
+
+        Paragraph::new(title)
+            .style(
+                Style::default()
+                    .fg(theme.title)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .alignment(Alignment::Center)
+            .block(theme.block())
+    }
+
+    /// One line per base, the active one marked and highlighted
+    fn render_bases(
+        theme: &Theme,
+        bases: &[BaseSummary],
+        units: UnitSystem,
+        monochrome: bool,
+    ) -> Paragraph<'static> {
+        let lines: Vec<Line> = bases
+            .iter()
+            .map(|base| {
+                let (state, state_style) = match base.control_state {
+                    _ if base.emergency_stop => (
+                        "EMERGENCY STOP",
+                        Style::default()
+                            .fg(theme.error)
+                            .add_modifier(Modifier::BOLD),
+                    ),
+                    ControlState::Uninitialized => {
+                        ("Initializing", Style::default().fg(theme.accent))
+                    }
+                    ControlState::InitializedButNotHold => {
+                        ("NO CONTROL", Style::default().fg(theme.warn))
+                    }
+                    ControlState::CanMove => ("Ready", Style::default().fg(theme.ok)),
+                };
+                let speed = match base.actual_speed {
+                    Some(speed) => format!(
+                        "X {}  Y {}  Z {}",
+                        units.format_linear(speed.x),
+                        units.format_linear(speed.y),
+                        units.format_angular(speed.z)
+                    ),
+                    None => "no odometry".to_string(),
+                };
+                let (marker, url_style) = if base.active {
+                    let style = Style::default()
+                        .fg(theme.active_fg)
+                        .bg(theme.active_bg)
+                        .add_modifier(Modifier::BOLD);
+                    if monochrome {
+                        ("> ", style.add_modifier(Modifier::REVERSED))
+                    } else {
+                        ("> ", style)
+                    }
+                } else {
+                    ("  ", Style::default().fg(theme.label))
+                };
+                Line::from(vec![
+                    Span::styled(marker, Style::default().fg(theme.text)),
+                    Span::styled(base.url.clone(), url_style),
+                    Span::styled("  ", Style::default()),
+                    Span::styled(state, state_style),
+                    Span::styled(format!("  {}", speed), Style::default().fg(theme.text)),
+                ])
+            })
+            .collect();
+        Paragraph::new(lines).block(theme.block().title("Bases - [Tab] switches"))
+    }
+
+    /// Render keyboard controls with highlighting
+    fn render_controls(
+        theme: &Theme,
+        pressed_keys: &HashMap<KeyCode, KeyState>,
+        speed_mode: SpeedMode,
+        deadman: Option<bool>,
+        monochrome: bool,
+    ) -> Paragraph<'static> {
+        let active_style = |active: bool| {
+            if active {
+                let style = Style::default()
+                    .fg(theme.active_fg)
+                    .bg(theme.active_bg)
+                    .add_modifier(Modifier::BOLD);
+                if monochrome {
+                    style.add_modifier(Modifier::REVERSED)
+                } else {
+                    style
+                }
+            } else {
+                Style::default().fg(theme.label)
+            }
+        };
+        let key_style = |key: KeyCode| active_style(pressed_keys.contains_key(&key));
+
+        let mut controls = vec![
+            Line::from(vec![
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("W", key_style(crossterm::event::KeyCode::Char('w'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("↑", key_style(crossterm::event::KeyCode::Up)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Forward  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("S", key_style(crossterm::event::KeyCode::Char('s'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("↓", key_style(crossterm::event::KeyCode::Down)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Backward", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("A", key_style(crossterm::event::KeyCode::Char('a'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("←", key_style(crossterm::event::KeyCode::Left)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Left     ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("D", key_style(crossterm::event::KeyCode::Char('d'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("→", key_style(crossterm::event::KeyCode::Right)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Right", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("Q", key_style(crossterm::event::KeyCode::Char('q'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Rotate Left  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("E", key_style(crossterm::event::KeyCode::Char('e'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Rotate Right  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("Shift", active_style(speed_mode == SpeedMode::Turbo)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Turbo  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("Ctrl", active_style(speed_mode == SpeedMode::Precision)),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Precision", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("I", key_style(crossterm::event::KeyCode::Char('i'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("K", key_style(crossterm::event::KeyCode::Char('k'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Step Fwd/Back  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("J", key_style(crossterm::event::KeyCode::Char('j'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("L", key_style(crossterm::event::KeyCode::Char('l'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Turn 90°  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("U", key_style(crossterm::event::KeyCode::Char('u'))),
+                Span::styled("/", Style::default().fg(theme.text)),
+                Span::styled("O", key_style(crossterm::event::KeyCode::Char('o'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Strafe L/R  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("X", key_style(crossterm::event::KeyCode::Char('x'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Clear Queue", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("0", key_style(crossterm::event::KeyCode::Char('0'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Reset Odometer  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("R", key_style(crossterm::event::KeyCode::Char('r'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Recover  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("V", key_style(crossterm::event::KeyCode::Char('v'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Cruise  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("G", key_style(crossterm::event::KeyCode::Char('g'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Stop & Exit  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("P", key_style(crossterm::event::KeyCode::Char('p'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Profile  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("Z", key_style(crossterm::event::KeyCode::Char('z'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Pause  ", Style::default().fg(theme.text)),
+                Span::styled("[C]", Style::default().fg(theme.error)),
+                Span::styled(" Exit  ", Style::default().fg(theme.text)),
+                Span::styled("[", Style::default().fg(theme.text)),
+                Span::styled("H", key_style(crossterm::event::KeyCode::Char('h'))),
+                Span::styled("]", Style::default().fg(theme.text)),
+                Span::styled(" Help", Style::default().fg(theme.text)),
+            ]),
+            Line::from(vec![Span::styled("Due to terminal limitations, the vehicle will still move slightly even when the keys are released.", Style::default().fg(theme.text))]),
+        ];
+        if let Some(engaged) = deadman {
+            let (state, state_style) = if engaged {
+                ("ENGAGED", active_style(true))
+            } else {
+                (
+                    "released, hold to move",
+                    Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+                )
+            };
+            controls.insert(
+                controls.len() - 1,
+                Line::from(vec![
+                    Span::styled("[", Style::default().fg(theme.text)),
+                    Span::styled("Tab", active_style(engaged)),
+                    Span::styled("]", Style::default().fg(theme.text)),
+                    Span::styled(" Dead-man: ", Style::default().fg(theme.text)),
+                    Span::styled(state, state_style),
+                ]),
+            );
+        }
+
+        Paragraph::new(controls)
+            .block(theme.block().title("Keyboard Controls"))
+            .alignment(Alignment::Left)
+    }
+
+    /// Render target speed display
+    #[allow(clippy::too_many_arguments)]
+    fn render_target_speed(
+        theme: &Theme,
+        speed: &SpeedData,
+        smoothing: SmoothingProfile,
+        cruise: Option<SpeedData>,
+        camera: CameraMount,
+        max_speed: Option<SpeedData>,
+        units: UnitSystem,
+        key_speeds: (f32, f32),
+        follow: Option<FollowIndicator>,
+        monochrome: bool,
+    ) -> Paragraph<'static> {
+        // Axes being commanded get a bright label, idle ones a dim one
+        let axis_style = |value: f32| {
+            if value != 0.0 {
+                Style::default().fg(theme.ok).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(theme.muted)
+            }
+        };
+
+        let mut lines = vec![
+            Line::from(vec![
+                Span::styled("X: ", axis_style(speed.x)),
+                Span::styled(
+                    format!(
+                        "{}{}",
+                        units.format_linear(speed.x),
+                        percent_of_max(speed.x, max_speed.map(|m| m.x))
+                    ),
+                    Style::default().fg(theme.text),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Y: ", axis_style(speed.y)),
+                Span::styled(
+                    format!(
+                        "{}{}",
+                        units.format_linear(speed.y),
+                        percent_of_max(speed.y, max_speed.map(|m| m.y))
+                    ),
+                    Style::default().fg(theme.text),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Z: ", axis_style(speed.z)),
+                Span::styled(
+                    format!(
+                        "{}{}",
+                        units.format_angular(speed.z),
+                        percent_of_max(speed.z, max_speed.map(|m| m.z))
+                    ),
+                    Style::default().fg(theme.text),
+                ),
+            ]),
+            Line::from(vec![
+                Span::styled("Smoothing: ", Style::default().fg(theme.label)),
+                Span::styled(smoothing.name(), Style::default().fg(theme.accent)),
+                Span::styled(" ([F] to cycle)", Style::default().fg(theme.muted)),
+            ]),
+            Line::from(vec![
+                Span::styled("Camera: ", Style::default().fg(theme.label)),
+                Span::styled(
+                    format!("{}°", camera.degrees()),
+                    Style::default().fg(theme.accent),
+                ),
+                Span::styled(" ([M] to cycle)", Style::default().fg(theme.muted)),
+            ]),
+            Line::from(vec![
+                Span::styled("Key speed: ", Style::default().fg(theme.label)),
+                Span::styled(
+                    format!(
+                        "{}  {}",
+                        units.format_linear(key_speeds.0).trim_start_matches('+'),
+                        units.format_angular(key_speeds.1).trim_start_matches('+')
+                    ),
+                    Style::default().fg(theme.accent),
+                ),
+                Span::styled(" ([-/+] [[/]])", Style::default().fg(theme.muted)),
+            ]),
+        ];
+        if let Some(held) = cruise {
+            let badge_style = Style::default()
+                .fg(theme.active_fg)
+                .bg(theme.warn)
+                .add_modifier(Modifier::BOLD);
+            lines.push(Line::from(vec![
+                Span::styled(
+                    " CRUISE ",
+                    if monochrome {
+                        badge_style.add_modifier(Modifier::REVERSED)
+                    } else {
+                        badge_style
+                    },
+                ),
+                Span::styled(
+                    format!(" X {:+.3}  Y {:+.3}  Z {:+.3}", held.x, held.y, held.z),
+                    Style::default().fg(theme.warn),
+                ),
+            ]));
+        }
+        if let Some(follow) = follow {
+            let mut spans = if follow.engaged {
+                let badge_style = Style::default()
+                    .fg(theme.active_fg)
+                    .bg(theme.follow)
+                    .add_modifier(Modifier::BOLD);
+                vec![Span::styled(
+                    " FOLLOW ",
+                    if monochrome {
+                        badge_style.add_modifier(Modifier::REVERSED)
+                    } else {
+                        badge_style
+                    },
+                )]
+            } else {
+                vec![Span::styled(
+                    "Follow: off ([B] to engage)",
+                    Style::default().fg(theme.muted),
+                )]
+            };
+            spans.push(Span::styled(
+                format!(" leader {}", follow.link),
+                Style::default().fg(theme.follow),
+            ));
+            if let Some(leader) = follow.leader_speed {
+                spans.push(Span::styled(
+                    format!(
+                        " X {:+.3}  Y {:+.3}  Z {:+.3}",
+                        leader.x, leader.y, leader.z
+                    ),
+                    Style::default().fg(theme.text),
+                ));
+            }
+            lines.push(Line::from(spans));
+        }
+
+        Paragraph::new(lines)
+            .block(theme.block().title("Target Speed"))
+            .alignment(Alignment::Left)
+    }
+
+    /// Render actual speed display. Stale values are dimmed and flagged.
+    fn render_actual_speed(
+        theme: &Theme,
+        speed: &Option<SpeedData>,
+        stale: bool,
+        max_speed: Option<SpeedData>,
+        units: UnitSystem,
+    ) -> Paragraph<'static> {
+        let value_style = if stale {
+            Style::default().fg(theme.muted)
+        } else {
+            Style::default().fg(theme.text)
+        };
+        let mut lines = if let Some(s) = speed {
+            vec![
+                Line::from(vec![
+                    Span::styled("X: ", Style::default().fg(theme.label)),
+                    Span::styled(
+                        format!(
+                            "{}{}",
+                            units.format_linear(s.x),
+                            percent_of_max(s.x, max_speed.map(|m| m.x))
+                        ),
+                        value_style,
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Y: ", Style::default().fg(theme.label)),
+                    Span::styled(
+                        format!(
+                            "{}{}",
+                            units.format_linear(s.y),
+                            percent_of_max(s.y, max_speed.map(|m| m.y))
+                        ),
+                        value_style,
+                    ),
+                ]),
+                Line::from(vec![
+                    Span::styled("Z: ", Style::default().fg(theme.label)),
+                    Span::styled(
+                        format!(
+                            "{}{}",
+                            units.format_angular(s.z),
+                            percent_of_max(s.z, max_speed.map(|m| m.z))
+                        ),
+                        value_style,
+                    ),
+                ]),
+            ]
+        } else {
+            vec![Line::from(vec![Span::styled(
+                "No data",
+                Style::default().fg(theme.label),
+            )])]
+        };
+        if stale {
+            lines.push(Line::from(vec![Span::styled(
+                "(no odometry)",
+                Style::default().fg(theme.warn),
+            )]));
+        }
+
+        Paragraph::new(lines)
+            .block(theme.block().title("Actual Speed"))
+            .alignment(Alignment::Left)
+    }
+
+    /// Render the most recent log lines that fit in `height` rows, newest at the bottom
+    /// Render the newest log entries that fit, leaving out the `scroll` newest.
+    /// Long entries wrap, so an entry can take several rows.
+    fn render_log(
+        theme: &Theme,
+        log: &LogBuffer,
+        scroll: usize,
+        width: u16,
+        height: u16,
+    ) -> Paragraph<'static> {
+        let rows = height.saturating_sub(2) as usize;
+        let columns = (width.saturating_sub(2) as usize).max(1);
+        let mut used = 0;
+        let mut lines: Vec<Line> = Vec::new();
+        for entry in log.iter().rev().skip(scroll) {
+            let stamp = format!("[{:>9.3}s] ", entry.elapsed.as_secs_f32());
+            let length = stamp.chars().count() + entry.message.chars().count();
+            let entry_rows = length.div_ceil(columns).max(1);
+            if used + entry_rows > rows {
+                break;
+            }
+            used += entry_rows;
+            lines.push(Line::from(vec![
+                Span::styled(stamp, Style::default().fg(theme.muted)),
+                Span::styled(entry.message.clone(), Style::default().fg(theme.text)),
+            ]));
+        }
+        lines.reverse();
+
+        let title = if scroll > 0 {
+            format!("Log (scrolled back {}, PgDn/End to follow)", scroll)
+        } else {
+            "Log (PgUp to scroll back)".to_string()
+        };
+        Paragraph::new(lines)
+            .block(theme.block().title(title))
+            .alignment(Alignment::Left)
+            .wrap(Wrap { trim: false })
+    }
+
+    /// Sparkline of the actual forward speed magnitude, scaled to `max_speed`
+    /// when known and to the largest sample otherwise
+    fn render_speed_history(
+        theme: &Theme,
+        history: &SpeedHistory,
+        max_speed: Option<f32>,
+    ) -> Sparkline<'static> {
+        // Sparklines are unsigned, plot mm/s of either direction
+        let data: Vec<u64> = history
+            .iter()
+            .map(|x| (x.abs() * 1000.0).round() as u64)
+            .collect();
+        let sparkline = Sparkline::default()
+            .block(theme.block().title("Forward Speed History"))
+            .style(Style::default().fg(theme.accent))
+            .data(&data);
+        match max_speed {
+            Some(max) => sparkline.max((max.abs() * 1000.0).round() as u64),
+            None => sparkline,
+        }
+    }
+
+    /// Render the dead-reckoning odometer
+    fn render_odometer(theme: &Theme, odometer: &Odometer) -> Paragraph<'static> {
+        let line = Line::from(vec![
+            Span::styled("X: ", Style::default().fg(theme.label)),
+            Span::styled(
+                format!("{:+.3} m   ", odometer.distance_x),
+                Style::default().fg(theme.text),
+            ),
+            Span::styled("Y: ", Style::default().fg(theme.label)),
+            Span::styled(
+                format!("{:+.3} m   ", odometer.distance_y),
+                Style::default().fg(theme.text),
+            ),
+            Span::styled("Z: ", Style::default().fg(theme.label)),
+            Span::styled(
+                format!(
+                    "{:+.3} rad ({:+.1}°)",
+                    odometer.rotation_z,
+                    odometer.rotation_z.to_degrees()
+                ),
+                Style::default().fg(theme.text),
+            ),
+        ]);
+
+        Paragraph::new(line)
+            .block(theme.block().title("Odometer (estimate)"))
+            .alignment(Alignment::Left)
+    }
+
+    /// Render the per-wheel speeds of the last command
+    fn render_wheel_speeds(
+        theme: &Theme,
+        wheel_speeds: &[(&'static str, f32)],
+    ) -> Paragraph<'static> {
+        let spans: Vec<Span> = wheel_speeds
+            .iter()
+            .flat_map(|(name, speed)| {
+                [
+                    Span::styled(format!("{}: ", name), Style::default().fg(theme.label)),
+                    Span::styled(format!("{:+.2}   ", speed), Style::default().fg(theme.text)),
+                ]
+            })
+            .collect();
+
+        Paragraph::new(Line::from(spans))
+            .block(
+                theme
+                    .block()
+                    .title("Wheel Speeds rad/s (commanded, estimate)"),
+            )
+            .alignment(Alignment::Left)
+    }
+
+    /// Render the discrete motion queue: running step on top, queued steps below
+    fn render_motion_queue(theme: &Theme, motion_queue: &MotionQueue) -> Paragraph<'static> {
+        let current = match motion_queue.current() {
+            Some((step, covered)) => Line::from(vec![
+                Span::styled("Running: ", Style::default().fg(theme.label)),
+                Span::styled(
+                    format!("{} (est. {})", step, step.format_amount(covered)),
+                    Style::default().fg(theme.ok).add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            None => Line::from(vec![Span::styled(
+                "Running: -",
+                Style::default().fg(theme.label),
+            )]),
+        };
+
+        let queued: Vec<String> = motion_queue
+            .pending()
+            .map(|step| step.to_string())
+            .collect();
+        let queued = if queued.is_empty() {
+            "-".to_string()
+        } else {
+            queued.join(" → ")
+        };
+
+        let lines = vec![
+            current,
+            Line::from(vec![
+                Span::styled("Queued: ", Style::default().fg(theme.label)),
+                Span::styled(queued, Style::default().fg(theme.text)),
+            ]),
+        ];
+
+        Paragraph::new(lines)
+            .block(theme.block().title("Motion Queue"))
+            .alignment(Alignment::Left)
+    }
+
+    /// Render status bar with state-based styling
+    #[allow(clippy::too_many_arguments)]
+    fn render_status(
+        theme: &Theme,
+        control_state: ControlState,
+        error_message: &ErrorMessage,
+        emergency_stop: bool,
+        exit_hold_progress: Option<f32>,
+        latency: Option<std::time::Duration>,
+        battery: Option<f32>,
+        takeover: Option<Takeover>,
+        paused: bool,
+        confirm_exit: bool,
+        monochrome: bool,
+    ) -> Paragraph<'static> {
+        let has_error = !error_message.message.is_empty();
+
+        let (status_text, status_style, border_style) = if emergency_stop {
+            (
+                format!("EMERGENCY STOP: {}", error_message.message),
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD),
+                Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD),
+            )
+        } else if has_error {
+            // A message is colored by its severity, whatever the control state
+            let style = match error_message.severity {
+                ErrorSeverity::Info => Style::default().fg(theme.accent),
+                ErrorSeverity::Warn => Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+                ErrorSeverity::Error => Style::default()
+                    .fg(theme.error)
+                    .add_modifier(Modifier::BOLD),
+            };
+            let border_style = match error_message.severity {
+                ErrorSeverity::Info => Style::default(),
+                ErrorSeverity::Warn | ErrorSeverity::Error => style,
+            };
+            (
+                format!(
+                    "{}: {}",
+                    error_message.severity.label(),
+                    error_message.message
+                ),
+                style,
+                border_style,
+            )
+        } else {
+            match control_state {
+                ControlState::Uninitialized => (
+                    "Status: Initializing...".to_string(),
+                    Style::default().fg(theme.accent),
+                    Style::default(),
+                ),
+                ControlState::InitializedButNotHold => (
+                    "Status: NO CONTROL".to_string(),
+                    Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+                    Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+                ),
+                ControlState::CanMove => (
+                    "Status: Ready to Move".to_string(),
+                    Style::default().fg(theme.ok).add_modifier(Modifier::BOLD),
+                    Style::default(),
+                ),
+            }
+        };
+
+        // A pending takeover replaces the no-control status until it ends
+        let (status_text, status_style) = match takeover {
+            _ if emergency_stop => (status_text, status_style),
+            Some(Takeover::Confirm) => (
+                "Take control from the other session? [T] confirm, any other key cancels"
+                    .to_string(),
+                Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+            ),
+            Some(Takeover::Requested) => (
+                "Takeover requested, waiting for control...".to_string(),
+                Style::default().fg(theme.accent),
+            ),
+            None => (status_text, status_style),
+        };
+
+        // Pausing is only outranked by an emergency stop
+        let (status_text, status_style) = if paused && !emergency_stop {
+            (
+                "PAUSED: sending zero, [Z] resumes".to_string(),
+                Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            (status_text, status_style)
+        };
+
+        let (status_text, status_style) = if confirm_exit {
+            (
+                "Press [C] again to confirm exit".to_string(),
+                Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+            )
+        } else {
+            (status_text, status_style)
+        };
+
+        // A partial exit hold takes over the status line while the key is down
+        let (status_text, status_style) = match exit_hold_progress {
+            Some(progress) => (
+                format!("Hold [C] to exit... {:.0}%", progress * 100.0),
+                Style::default().fg(theme.warn).add_modifier(Modifier::BOLD),
+            ),
+            None => (status_text, status_style),
+        };
+
+        // Without colors, prefix the severity and use modifiers to set it apart
+        let (status_text, status_style) = if monochrome {
+            let (marker, modifier) = if emergency_stop {
+                ("[ESTOP]", Modifier::BOLD | Modifier::REVERSED)
+            } else if has_error {
+                match error_message.severity {
+                    ErrorSeverity::Info => ("[INFO]", Modifier::empty()),
+                    ErrorSeverity::Warn => ("[WARN]", Modifier::BOLD | Modifier::UNDERLINED),
+                    ErrorSeverity::Error => ("[ERROR]", Modifier::BOLD | Modifier::UNDERLINED),
+                }
+            } else if control_state == ControlState::InitializedButNotHold {
+                ("[WARN]", Modifier::BOLD | Modifier::UNDERLINED)
+            } else if control_state == ControlState::CanMove {
+                ("[OK]", Modifier::BOLD)
+            } else {
+                ("[INIT]", Modifier::empty())
+            };
+            (
+                format!("{} {}", marker, status_text),
+                status_style.add_modifier(modifier),
+            )
+        } else {
+            (status_text, status_style)
+        };
+
+        let rtt = match latency {
+            Some(latency) => format!(" RTT: {} ms ", latency.as_millis()),
+            None => " RTT: -- ".to_string(),
+        };
+        let status_block = theme
+            .block()
+            .title("Robot Status")
+            .title_top(Line::from(rtt).right_aligned())
+            .title_bottom(Self::render_battery(theme, battery, monochrome));
+        let status_block = if has_error
+            || emergency_stop
+            || control_state == ControlState::InitializedButNotHold
+        {
+            status_block.border_style(border_style)
+        } else {
+            status_block
+        };
+
+        Paragraph::new(status_text)
+            .style(status_style)
+            .alignment(Alignment::Center)
+            .block(status_block)
+    }
+
+    /// Charge level, green from 50%, yellow from 20% and red below. Always
+    /// shown, as N/A when unknown, so the layout doesn't change.
+    fn render_battery(theme: &Theme, battery: Option<f32>, monochrome: bool) -> Line<'static> {
+        let Some(percent) = battery else {
+            return Line::from(" Battery: N/A ");
+        };
+        let style = if monochrome {
+            if percent < 20.0 {
+                Style::default().add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                Style::default()
+            }
+        } else if percent >= 50.0 {
+            Style::default().fg(theme.ok)
+        } else if percent >= 20.0 {
+            Style::default().fg(theme.warn)
+        } else {
+            Style::default()
+                .fg(theme.error)
+                .add_modifier(Modifier::BOLD)
+        };
+        Line::from(Span::styled(format!(" Battery: {:.0}% ", percent), style))
+    }
+}
+
+impl Ui for RobotUi {
+    fn draw(&mut self, view: &UiView) -> io::Result<()> {
+        let theme = &self.theme;
+        let fps = self.frame_rate.as_ref().map(FrameRate::rate);
+        let mut too_small = false;
+        self.terminal.draw(|f| {
+            too_small = !Self::fits(f.area(), view);
+            Self::render(f, view, theme);
+            if let Some(fps) = fps {
+                Self::render_fps(f, fps, theme);
+            }
+        })?;
+        self.too_small = too_small;
+        if let Some(frame_rate) = &mut self.frame_rate {
+            frame_rate.record(Instant::now());
+        }
+        Ok(())
+    }
+
+    fn draw_connection(&mut self, url: &str, error: Option<&str>) -> io::Result<()> {
+        self.terminal
+            .draw(|f| Self::render_connection(f, url, error, &self.theme))?;
+        Ok(())
+    }
+
+    /// Leave raw mode and the alternate screen
+    fn cleanup(&mut self) -> io::Result<()> {
+        disable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
+        Ok(())
+    }
+
+    fn too_small(&self) -> bool {
+        self.too_small
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+    }
+}
+
+impl Drop for RobotUi {
+    fn drop(&mut self) {
+        let _ = self.cleanup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use ratatui::{backend::TestBackend, buffer::Buffer};
+
+    /// Render a frame of `width` x `height` from a view in the ready state,
+    /// after `adjust` changed it
+    fn render(width: u16, height: u16, adjust: impl FnOnce(&mut UiView)) -> (Buffer, bool) {
+        let pressed_keys = HashMap::new();
+        let error_message = ErrorMessage::default();
+        let motion_queue = MotionQueue::new(0.1, 0.5);
+        let log = LogBuffer::default();
+        let speed_history = SpeedHistory::default();
+        let mut view = UiView {
+            control_state: ControlState::CanMove,
+            target_speed: SpeedData::ZERO,
+            actual_speed: Some(SpeedData::ZERO),
+            actual_speed_stale: false,
+            pressed_keys: &pressed_keys,
+            error_message: &error_message,
+            emergency_stop: false,
+            motion_queue: &motion_queue,
+            odometer: Odometer::default(),
+            smoothing: SmoothingProfile::None,
+            cruise: None,
+            log: &log,
+            wheel_speeds: None,
+            monochrome: false,
+            url: Some("ws://localhost:8439"),
+            session_id: None,
+            profile: None,
+            camera: CameraMount::default(),
+            exit_hold_progress: None,
+            speed_history: &speed_history,
+            log_scroll: 0,
+            latency: None,
+            battery: None,
+            protocol: None,
+            takeover: None,
+            paused: false,
+            confirm_exit: false,
+            speed_mode: SpeedMode::default(),
+            deadman: None,
+            max_speed: None,
+            units: UnitSystem::Si,
+            key_speeds: (0.1, 0.5),
+            follow: None,
+            dimmed: false,
+            show_help: false,
+            bases: &[],
+        };
+        adjust(&mut view);
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        let frame = terminal
+            .draw(|f| RobotUi::render(f, &view, &Theme::DARK))
+            .unwrap();
+        let fits = RobotUi::fits(frame.area, &view);
+        (terminal.backend().buffer().clone(), fits)
+    }
+
+    fn screen_text(buffer: &Buffer) -> String {
+        let width = buffer.area.width as usize;
+        let symbols: Vec<&str> = buffer.content.iter().map(|cell| cell.symbol()).collect();
+        symbols
+            .chunks(width)
+            .map(|row| row.concat())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Position of the first cell of `needle`, searched row by row
+    fn find(buffer: &Buffer, needle: &str) -> (u16, u16) {
+        let text = screen_text(buffer);
+        let (y, line) = text
+            .lines()
+            .enumerate()
+            .find(|(_, line)| line.contains(needle))
+            .unwrap_or_else(|| panic!("'{}' not found in\n{}", needle, text));
+        let x = line[..line.find(needle).unwrap()].chars().count();
+        (x as u16, y as u16)
+    }
+
+    #[test]
+    fn idle_dimming_mutes_plain_text_and_dims_every_cell() {
+        let (bright, _) = render(100, 40, |_| {});
+        let (dimmed, _) = render(100, 40, |view| view.dimmed = true);
+        assert!(bright
+            .content
+            .iter()
+            .all(|cell| !cell.modifier.contains(Modifier::DIM)));
+        assert!(dimmed
+            .content
+            .iter()
+            .all(|cell| cell.modifier.contains(Modifier::DIM)));
+        // Same content, only the style changes
+        assert_eq!(screen_text(&bright), screen_text(&dimmed));
+
+        let (x, y) = find(&bright, "Smoothing: ");
+        assert_eq!(bright[(x, y)].fg, Theme::DARK.label);
+        assert_eq!(dimmed[(x, y)].fg, Theme::DARK.muted);
+        // Colored cues keep their color at reduced intensity
+        let (x, y) = find(&bright, "Robot Base Advanced Control");
+        assert_eq!(dimmed[(x, y)].fg, bright[(x, y)].fg);
+    }
+
+    #[test]
+    fn commanded_axes_get_a_bright_label() {
+        let (buffer, _) = render(100, 40, |view| {
+            view.target_speed = SpeedData::new(0.1, 0.0, -0.5)
+        });
+        let (x, y) = find(&buffer, "Target Speed");
+        // The title starts in the first inner column, the axis labels are below it
+        let label = |row: u16| &buffer[(x, y + row)];
+        assert_eq!(label(1).symbol(), "X");
+        assert_eq!(label(1).fg, Theme::DARK.ok);
+        assert!(label(1).modifier.contains(Modifier::BOLD));
+        assert_eq!(label(2).symbol(), "Y");
+        assert_eq!(label(2).fg, Theme::DARK.muted);
+        assert!(!label(2).modifier.contains(Modifier::BOLD));
+        assert_eq!(label(3).symbol(), "Z");
+        assert_eq!(label(3).fg, Theme::DARK.ok);
+    }
+
+    #[test]
+    fn percentages_follow_the_given_maximum() {
+        let (buffer, _) = render(100, 40, |view| {
+            view.target_speed = SpeedData::new(0.1, 0.0, 0.0);
+            view.max_speed = Some(SpeedData::from_linear_angular((0.2, 0.5)));
+        });
+        assert!(screen_text(&buffer).contains("(+50%)"));
+    }
+
+    #[test]
+    fn theme_toggle_visits_every_theme() {
+        let mut theme = ThemeName::default();
+        for _ in ThemeName::value_variants() {
+            theme = theme.next();
+            let parsed = ThemeName::from_str(theme.name(), false).unwrap();
+            assert_eq!(parsed, theme);
+        }
+        assert_eq!(theme, ThemeName::default());
+    }
+
+    #[test]
+    fn too_small_terminal_warns_and_ignores_the_movement_keys() {
+        let (buffer, fits) = render(79, 20, |_| {});
+        assert!(!fits);
+        let text = screen_text(&buffer);
+        assert!(
+            text.contains("Terminal too small (need at least 80x36), movement keys ignored"),
+            "{}",
+            text
+        );
+        assert!(!text.contains("Target Speed"), "{}", text);
+
+        let (buffer, fits) = render(MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT, |_| {});
+        assert!(fits);
+        assert!(!screen_text(&buffer).contains("Terminal too small"));
+    }
+
+    #[test]
+    fn dead_man_line_raises_the_minimum_height() {
+        let (buffer, fits) = render(MIN_TERMINAL_WIDTH, MIN_TERMINAL_HEIGHT, |view| {
+            view.deadman = Some(false)
+        });
+        assert!(!fits);
+        assert!(screen_text(&buffer).contains("need at least 80x37"));
+    }
+}
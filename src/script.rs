@@ -0,0 +1,220 @@
+// ============================================================================
+// Script Module - Predefined maneuvers read from a text file
+// ============================================================================
+//
+// One command per line:
+//
+//   move 0.1 0 0 2.0   # x y z speed for 2 seconds
+//   wait 1.0           # stand still for a second
+//
+// Blank lines and everything after `#` are ignored. Speeds are in m/s and
+// rad/s, the same as the keyboard commands.
+
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::keyboard_input::SpeedData;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScriptStep {
+    Move {
+        speed: SpeedData,
+        duration: Duration,
+    },
+    Wait(Duration),
+}
+
+impl ScriptStep {
+    fn duration(&self) -> Duration {
+        match *self {
+            ScriptStep::Move { duration, .. } | ScriptStep::Wait(duration) => duration,
+        }
+    }
+
+    fn command(&self) -> SpeedData {
+        match *self {
+            ScriptStep::Move { speed, .. } => speed,
+            ScriptStep::Wait(_) => SpeedData::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct ActiveStep {
+    step: ScriptStep,
+    elapsed: Duration,
+    // None while paused, the time in between doesn't count
+    last_tick: Option<Instant>,
+}
+
+/// Runs the steps of a script one after another, timed by the main loop's ticks
+#[derive(Clone, Debug)]
+pub struct ScriptRunner {
+    steps: Vec<ScriptStep>,
+    next: usize,
+    active: Option<ActiveStep>,
+}
+
+impl ScriptRunner {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse a whole script, failing on the first invalid line
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut steps = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let step = parse_step(line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", number + 1, e),
+                )
+            })?;
+            steps.push(step);
+        }
+        Ok(Self {
+            steps,
+            next: 0,
+            active: None,
+        })
+    }
+
+    pub fn step_count(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.active.is_none() && self.next >= self.steps.len()
+    }
+
+    /// Stop the clock of the running step, e.g. while control is lost. The
+    /// step resumes where it was at the next tick.
+    pub fn pause(&mut self) {
+        if let Some(active) = &mut self.active {
+            active.last_tick = None;
+        }
+    }
+
+    /// Advance the script. Returns the speed to command, or None once every
+    /// step has run.
+    pub fn tick(&mut self, now: Instant) -> Option<SpeedData> {
+        if self.active.is_none() {
+            let step = *self.steps.get(self.next)?;
+            self.next += 1;
+            self.active = Some(ActiveStep {
+                step,
+                elapsed: Duration::ZERO,
+                last_tick: Some(now),
+            });
+        }
+
+        let active = self.active.as_mut()?;
+        if let Some(last_tick) = active.last_tick {
+            active.elapsed += now.duration_since(last_tick);
+        }
+        active.last_tick = Some(now);
+
+        if active.elapsed >= active.step.duration() {
+            // Step done, command a stop for this tick and start the next one later
+            self.active = None;
+            return Some(SpeedData::ZERO);
+        }
+        Some(active.step.command())
+    }
+}
+
+fn parse_step(line: &str) -> Result<ScriptStep, String> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or_default();
+    let values = words
+        .map(|word| {
+            word.parse::<f32>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .ok_or_else(|| format!("'{}' is not a number", word))
+        })
+        .collect::<Result<Vec<f32>, String>>()?;
+    let seconds = |value: f32| {
+        Duration::try_from_secs_f32(value)
+            .map_err(|_| format!("duration {} must not be negative", value))
+    };
+    match (command, values.as_slice()) {
+        ("move", &[x, y, z, duration]) => Ok(ScriptStep::Move {
+            speed: SpeedData::new(x, y, z),
+            duration: seconds(duration)?,
+        }),
+        ("move", _) => Err("expected 'move x y z duration_seconds'".to_string()),
+        ("wait", &[duration]) => Ok(ScriptStep::Wait(seconds(duration)?)),
+        ("wait", _) => Err("expected 'wait duration_seconds'".to_string()),
+        _ => Err(format!("unknown command '{}'", command)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_moves_waits_and_comments() {
+        let runner =
+            ScriptRunner::parse("# square\nmove 0.1 0 0 2.0\n\nwait 1  # settle\n").unwrap();
+        assert_eq!(
+            runner.steps,
+            [
+                ScriptStep::Move {
+                    speed: SpeedData::new(0.1, 0.0, 0.0),
+                    duration: Duration::from_secs(2),
+                },
+                ScriptStep::Wait(Duration::from_secs(1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_line_is_reported_with_its_number() {
+        for script in [
+            "wait 1\nmove 0.1 0 2.0\n",
+            "wait 1\nmove 0.1 0 0 fast\n",
+            "wait 1\nspin 1\n",
+            "wait 1\nwait -1\n",
+        ] {
+            let error = ScriptRunner::parse(script).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert!(error.to_string().starts_with("line 2:"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn steps_run_for_their_duration_with_a_stop_in_between() {
+        let mut runner = ScriptRunner::parse("move 0.1 0 0 1.0\nwait 0.5\n").unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        assert_eq!(runner.tick(at(0)), Some(SpeedData::new(0.1, 0.0, 0.0)));
+        assert_eq!(runner.tick(at(900)), Some(SpeedData::new(0.1, 0.0, 0.0)));
+        assert_eq!(runner.tick(at(1000)), Some(SpeedData::ZERO));
+        assert_eq!(runner.tick(at(1100)), Some(SpeedData::ZERO));
+        assert!(!runner.is_finished());
+        assert_eq!(runner.tick(at(1600)), Some(SpeedData::ZERO));
+        assert!(runner.is_finished());
+        assert_eq!(runner.tick(at(1700)), None);
+    }
+
+    #[test]
+    fn paused_time_does_not_count() {
+        let mut runner = ScriptRunner::parse("move 0 0 0.5 1.0\n").unwrap();
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+
+        runner.tick(at(0));
+        runner.tick(at(600));
+        runner.pause();
+        assert_eq!(runner.tick(at(5000)), Some(SpeedData::new(0.0, 0.0, 0.5)));
+        assert_eq!(runner.tick(at(5400)), Some(SpeedData::ZERO));
+    }
+}
@@ -0,0 +1,209 @@
+// ============================================================================
+// Session Log Module - Every message sent and received, one JSON line each
+// ============================================================================
+//
+// A line looks like
+//
+//   {"direction":"down","timestamp_ms":1520,"message":{...}}
+//
+// with `down` for messages sent to the robot and `up` for received ones. The
+// timestamp is monotonic, counted from the start of the recording.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::base_backend;
+use super::lock_recover::LockRecover;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Sent to the robot
+    Down,
+    /// Received from the robot
+    Up,
+}
+
+/// One line of a session log
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Record<T> {
+    pub direction: Direction,
+    pub timestamp_ms: u64,
+    pub message: T,
+}
+
+/// Appends records to a session log. Shared between the sending and the
+/// receiving side of the connection.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    // None once a write failed, the rest of the session isn't recorded
+    file: Mutex<Option<LineWriter<File>>>,
+    start: Instant,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file: Mutex::new(Some(LineWriter::new(file))),
+            start: Instant::now(),
+        })
+    }
+
+    /// Append `message`. The first failing write is returned and stops the
+    /// recording, so a full disk is reported once and not for every frame.
+    pub fn record<T: Serialize>(&self, direction: Direction, message: &T) -> io::Result<()> {
+        let mut file = self.file.lock_recover();
+        let Some(writer) = file.as_mut() else {
+            return Ok(());
+        };
+        let record = Record {
+            direction,
+            timestamp_ms: self.start.elapsed().as_millis() as u64,
+            message,
+        };
+        let result = serde_json::to_writer(&mut *writer, &record)
+            .map_err(io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"));
+        if result.is_err() {
+            *file = None;
+        }
+        result
+    }
+}
+
+/// Read all records of a session log, failing on the first malformed line
+pub fn read_records<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<Record<T>>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut records = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", number + 1, e),
+            )
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Outgoing messages of a --record session log, with their time since the first one
+pub fn load_replay(path: &Path) -> Result<Vec<(Duration, base_backend::ApiDown)>, String> {
+    let records = read_records::<serde_json::Value>(path)
+        .map_err(|e| format!("can't read {}: {}", path.display(), e))?;
+    let mut messages = Vec::new();
+    for record in records {
+        if record.direction != Direction::Down {
+            continue;
+        }
+        let message = serde_json::from_value(record.message).map_err(|e| {
+            format!(
+                "{}: bad outgoing message at {} ms: {}",
+                path.display(),
+                record.timestamp_ms,
+                e
+            )
+        })?;
+        messages.push((Duration::from_millis(record.timestamp_ms), message));
+    }
+    let Some(&(first, _)) = messages.first() else {
+        return Err(format!(
+            "{} contains no outgoing messages to replay",
+            path.display()
+        ));
+    };
+    for (at, _) in &mut messages {
+        *at = at.saturating_sub(first);
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{create_move_msg, move_speed};
+    use crate::keyboard_input::SpeedData;
+
+    #[test]
+    fn recorded_messages_read_back_in_order() {
+        let path = std::env::temp_dir().join(format!("session-{}.jsonl", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(Direction::Down, &"init").unwrap();
+        recorder.record(Direction::Up, &"status").unwrap();
+        drop(recorder);
+
+        let records = read_records::<String>(&path);
+        std::fs::remove_file(&path).ok();
+        let records = records.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].direction, Direction::Down);
+        assert_eq!(records[0].message, "init");
+        assert_eq!(records[1].direction, Direction::Up);
+        assert!(records[0].timestamp_ms <= records[1].timestamp_ms);
+    }
+
+    #[test]
+    fn malformed_line_is_reported_with_its_number() {
+        let path = std::env::temp_dir().join(format!("session-bad-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"direction\":\"down\",\"timestamp_ms\":0,\"message\":\"init\"}\nnot json\n",
+        )
+        .unwrap();
+        let result = read_records::<String>(&path);
+        std::fs::remove_file(&path).ok();
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("line 2:"), "{}", error);
+    }
+
+    #[test]
+    fn replay_loads_outgoing_messages_relative_to_the_first() {
+        let path = std::env::temp_dir().join(format!("replay-{}.jsonl", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let recorder = SessionRecorder::create(&path).unwrap();
+        recorder.record(Direction::Up, &"status").unwrap();
+        recorder
+            .record(Direction::Down, &create_move_msg(0.1, 0.0, 0.0))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        recorder
+            .record(Direction::Down, &create_move_msg(0.0, 0.0, 0.0))
+            .unwrap();
+        drop(recorder);
+        let loaded = load_replay(&path);
+        std::fs::remove_file(&path).ok();
+
+        let messages = loaded.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, Duration::ZERO);
+        assert!(messages[1].0 >= Duration::from_millis(20));
+        assert_eq!(
+            move_speed(&messages[0].1),
+            Some(SpeedData::new(0.1, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn replay_without_outgoing_messages_is_an_error() {
+        let path = std::env::temp_dir().join(format!("replay-up-{}.jsonl", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"direction\":\"up\",\"timestamp_ms\":0,\"message\":{}}\n",
+        )
+        .unwrap();
+        let loaded = load_replay(&path);
+        std::fs::remove_file(&path).ok();
+        assert!(loaded.unwrap_err().contains("no outgoing messages"));
+    }
+}
@@ -0,0 +1,116 @@
+// ============================================================================
+// Session State Module - Runtime choices remembered from the last run
+// ============================================================================
+//
+// The speeds of the movement keys and the theme picked with the theme key are
+// saved on a clean exit to session.toml in the platform config directory and
+// loaded at startup, below the command line and the --config file. A state
+// file that is missing or doesn't parse is the same as none.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::robot_ui::ThemeName;
+
+/// Directory under the platform config directory the state file is kept in
+const APP_DIR: &str = "robot-examples";
+const STATE_FILE: &str = "session.toml";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    /// Speed of the X/Y movement keys in m/s
+    pub linear_speed: Option<f32>,
+    /// Speed of the rotation keys in rad/s
+    pub angular_speed: Option<f32>,
+    pub theme: Option<ThemeName>,
+}
+
+/// Where the state file lives: the config directory `directories` picks for
+/// the application, e.g. `~/.config/robot-examples` on Linux. None when the
+/// platform has no home directory.
+#[cfg(feature = "directories")]
+pub fn session_state_path() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", APP_DIR)?;
+    Some(dirs.config_dir().join(STATE_FILE))
+}
+
+/// Where the state file lives: `$XDG_CONFIG_HOME` or `~/.config` on Linux,
+/// `~/Library/Application Support` on macOS and `%APPDATA%` on Windows. None
+/// when the environment names no such directory.
+#[cfg(not(feature = "directories"))]
+pub fn session_state_path() -> Option<PathBuf> {
+    let non_empty = |name: &str| std::env::var_os(name).filter(|value| !value.is_empty());
+    let config_dir = if cfg!(windows) {
+        non_empty("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+    }?;
+    Some(config_dir.join(APP_DIR).join(STATE_FILE))
+}
+
+/// The state saved at `path`. Anything unreadable, and speeds that aren't
+/// positive finite numbers, are left at None.
+pub fn load_session_state(path: &Path) -> SessionState {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return SessionState::default();
+    };
+    let state: SessionState = toml::from_str(&text).unwrap_or_default();
+    let speed = |speed: Option<f32>| speed.filter(|speed| speed.is_finite() && *speed > 0.0);
+    SessionState {
+        linear_speed: speed(state.linear_speed),
+        angular_speed: speed(state.angular_speed),
+        theme: state.theme,
+    }
+}
+
+/// Write `state` to `path`, creating its directory if needed
+pub fn save_session_state(path: &Path, state: &SessionState) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = toml::to_string(state).map_err(io::Error::other)?;
+    std::fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_state_is_loaded_back() {
+        let path = std::env::temp_dir()
+            .join(format!("robot-session-{}", std::process::id()))
+            .join(STATE_FILE);
+        let state = SessionState {
+            linear_speed: Some(0.25),
+            angular_speed: Some(0.75),
+            theme: Some(ThemeName::Light),
+        };
+        save_session_state(&path, &state).unwrap();
+        assert_eq!(load_session_state(&path), state);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn missing_or_corrupt_state_falls_back_to_defaults() {
+        let missing = std::env::temp_dir().join("robot-session-missing.toml");
+        assert_eq!(load_session_state(&missing), SessionState::default());
+
+        let path =
+            std::env::temp_dir().join(format!("robot-session-bad-{}.toml", std::process::id()));
+        std::fs::write(&path, "linear_speed = \"fast\"\n[[[").unwrap();
+        assert_eq!(load_session_state(&path), SessionState::default());
+
+        std::fs::write(&path, "linear_speed = -1.0\nangular_speed = 0.5\n").unwrap();
+        let state = load_session_state(&path);
+        assert_eq!(state.linear_speed, None);
+        assert_eq!(state.angular_speed, Some(0.5));
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,156 @@
+// ============================================================================
+// Smoothing Module - Selectable filters applied to the commanded speed
+// ============================================================================
+
+use super::keyboard_input::SpeedData;
+
+/// Time constant of the light EMA filter, in seconds
+const LIGHT_EMA_TIME_CONSTANT: f32 = 0.1;
+/// Acceleration limits of the ramp-limited profile
+const RAMP_LINEAR_ACCEL: f32 = 0.5; // m/s^2
+const RAMP_ANGULAR_ACCEL: f32 = 2.0; // rad/s^2
+/// Below this distance to the target the filters snap onto it, so that a
+/// commanded stop ends up exactly at zero instead of decaying forever
+const SNAP_EPSILON: f32 = 1e-3;
+
+/// How the commanded speed follows the keyboard target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SmoothingProfile {
+    /// Send the target as is, most responsive
+    #[default]
+    None,
+    /// Exponential moving average, takes the edge off key presses
+    Light,
+    /// Acceleration limited ramp, smooth enough for video recording
+    Ramp,
+}
+
+impl SmoothingProfile {
+    /// Next profile, used by the runtime toggle key
+    pub fn next(self) -> Self {
+        match self {
+            SmoothingProfile::None => SmoothingProfile::Light,
+            SmoothingProfile::Light => SmoothingProfile::Ramp,
+            SmoothingProfile::Ramp => SmoothingProfile::None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SmoothingProfile::None => "None",
+            SmoothingProfile::Light => "Light EMA",
+            SmoothingProfile::Ramp => "Ramp Limited",
+        }
+    }
+
+    /// Compute the next commanded speed from the previous one, the target
+    /// and the time `dt` (seconds) since the previous one was computed
+    pub fn apply(self, previous: SpeedData, target: SpeedData, dt: f32) -> SpeedData {
+        match self {
+            SmoothingProfile::None => target,
+            SmoothingProfile::Light => ema(previous, target, dt),
+            SmoothingProfile::Ramp => ramp_limited(previous, target, dt),
+        }
+    }
+}
+
+fn snap(value: f32, target: f32) -> f32 {
+    if (value - target).abs() < SNAP_EPSILON {
+        target
+    } else {
+        value
+    }
+}
+
+fn ema(previous: SpeedData, target: SpeedData, dt: f32) -> SpeedData {
+    let alpha = 1.0 - (-dt / LIGHT_EMA_TIME_CONSTANT).exp();
+    let step = |prev: f32, target: f32| snap(prev + (target - prev) * alpha, target);
+    SpeedData::new(
+        step(previous.x, target.x),
+        step(previous.y, target.y),
+        step(previous.z, target.z),
+    )
+}
+
+fn ramp_limited(previous: SpeedData, target: SpeedData, dt: f32) -> SpeedData {
+    let step = |prev: f32, target: f32, accel: f32| {
+        let max_delta = accel * dt;
+        prev + (target - prev).clamp(-max_delta, max_delta)
+    };
+    SpeedData::new(
+        step(previous.x, target.x, RAMP_LINEAR_ACCEL),
+        step(previous.y, target.y, RAMP_LINEAR_ACCEL),
+        step(previous.z, target.z, RAMP_ANGULAR_ACCEL),
+    )
+}
+
+/// Exponential moving average of the actual speed reported by the base, for
+/// display only. `alpha` is the weight of each new sample, 1.0 shows the raw
+/// samples.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SpeedFilter {
+    pub alpha: f32,
+    value: Option<SpeedData>,
+}
+
+impl SpeedFilter {
+    pub fn new(alpha: f32) -> Self {
+        Self { alpha, value: None }
+    }
+
+    /// Feed a sample and return the filtered speed. The first sample is taken as is.
+    pub fn update(&mut self, sample: SpeedData) -> SpeedData {
+        let step = |previous: f32, sample: f32| previous + (sample - previous) * self.alpha;
+        let filtered = match self.value {
+            Some(previous) => SpeedData::new(
+                step(previous.x, sample.x),
+                step(previous.y, sample.y),
+                step(previous.z, sample.z),
+            ),
+            None => sample,
+        };
+        self.value = Some(filtered);
+        filtered
+    }
+
+    /// Filtered speed, None before the first sample
+    pub fn value(&self) -> Option<SpeedData> {
+        self.value
+    }
+
+    /// Forget the filtered speed, the next sample is taken as is
+    pub fn clear(&mut self) {
+        self.value = None;
+    }
+}
+
+impl Default for SpeedFilter {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alpha_one_passes_samples_through() {
+        let mut filter = SpeedFilter::new(1.0);
+        for sample in [0.1, -0.3, 0.25] {
+            let speed = SpeedData::new(sample, 0.0, sample);
+            assert_eq!(filter.update(speed), speed);
+        }
+    }
+
+    #[test]
+    fn smaller_alpha_moves_part_of_the_way() {
+        let mut filter = SpeedFilter::new(0.25);
+        assert_eq!(filter.value(), None);
+        filter.update(SpeedData::ZERO);
+        let filtered = filter.update(SpeedData::new(0.4, 0.0, 0.0));
+        assert!((filtered.x - 0.1).abs() < 1e-6);
+        assert_eq!(filter.value(), Some(filtered));
+    }
+}
@@ -0,0 +1,63 @@
+// ============================================================================
+// Speed History Module - Recent actual forward speeds for the sparkline
+// ============================================================================
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Window shown when none is configured
+pub const DEFAULT_SPEED_HISTORY_WINDOW: Duration = Duration::from_secs(5);
+
+/// Bounded history of `estimated_odometry.speed_x`, one sample per BaseStatus
+#[derive(Clone, Debug)]
+pub struct SpeedHistory {
+    samples: VecDeque<f32>,
+    capacity: usize,
+}
+
+impl SpeedHistory {
+    /// History covering `window` at `report_hz` statuses per second
+    pub fn new(window: Duration, report_hz: u32) -> Self {
+        let capacity = ((window.as_secs_f32() * report_hz as f32).ceil() as usize).max(1);
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, speed_x: f32) {
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(speed_x);
+    }
+
+    /// Samples from oldest to newest
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+impl Default for SpeedHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_SPEED_HISTORY_WINDOW, 50)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity_follows_window_and_rate() {
+        let mut history = SpeedHistory::new(Duration::from_millis(100), 50);
+        for i in 0..10 {
+            history.push(i as f32);
+        }
+        // 0.1 s at 50 Hz is 5 samples, the newest are kept
+        assert_eq!(
+            history.iter().collect::<Vec<_>>(),
+            [5.0, 6.0, 7.0, 8.0, 9.0]
+        );
+    }
+}
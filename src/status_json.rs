@@ -0,0 +1,114 @@
+// ============================================================================
+// Status JSON Module - One JSON object per BaseStatus, for jq or a dashboard
+// ============================================================================
+//
+// A line looks like
+//
+//   {"timestamp_ms":1520,"control_state":"can_move","target":{...},"actual":{...},"emergency_stop":false}
+//
+// with `actual` null for a status without odometry. Every line is flushed as
+// soon as it is written.
+
+use serde::Serialize;
+use std::io::{self, Write};
+use std::time::Instant;
+
+use super::keyboard_input::SpeedData;
+use super::robot_ui::ControlState;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct StatusSnapshot {
+    pub timestamp_ms: u64,
+    pub control_state: ControlState,
+    /// Speed of the last move command sent
+    pub target: SpeedData,
+    pub actual: Option<SpeedData>,
+    pub emergency_stop: bool,
+}
+
+/// Writes the snapshots. Timestamps are monotonic, counted from its creation.
+#[derive(Debug)]
+pub struct StatusJsonWriter<W: Write = io::Stdout> {
+    out: W,
+    start: Instant,
+    // Set by the first failed write, nothing is written after it
+    failed: bool,
+}
+
+impl StatusJsonWriter {
+    pub fn stdout() -> Self {
+        Self::new(io::stdout())
+    }
+}
+
+impl<W: Write> StatusJsonWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            start: Instant::now(),
+            failed: false,
+        }
+    }
+
+    /// Write a line for a status received at `now`. Only the first failing
+    /// write is returned, like a closed pipe it would fail for every status.
+    pub fn record(
+        &mut self,
+        now: Instant,
+        control_state: ControlState,
+        target: SpeedData,
+        actual: Option<SpeedData>,
+        emergency_stop: bool,
+    ) -> io::Result<()> {
+        if self.failed {
+            return Ok(());
+        }
+        let snapshot = StatusSnapshot {
+            timestamp_ms: now.saturating_duration_since(self.start).as_millis() as u64,
+            control_state,
+            target,
+            actual,
+            emergency_stop,
+        };
+        let result = serde_json::to_writer(&mut self.out, &snapshot)
+            .map_err(io::Error::from)
+            .and_then(|()| self.out.write_all(b"\n"))
+            .and_then(|()| self.out.flush());
+        self.failed = result.is_err();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn one_compact_object_per_line() {
+        let mut writer = StatusJsonWriter::new(Vec::new());
+        let at = writer.start + Duration::from_millis(20);
+        writer
+            .record(
+                at,
+                ControlState::CanMove,
+                SpeedData::new(0.5, 0.0, 0.0),
+                Some(SpeedData::new(0.25, 0.0, 0.0)),
+                false,
+            )
+            .unwrap();
+        writer
+            .record(at, ControlState::Uninitialized, SpeedData::ZERO, None, true)
+            .unwrap();
+
+        let text = String::from_utf8(writer.out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"timestamp_ms":20,"control_state":"can_move","target":{"x":0.5,"y":0.0,"z":0.0},"actual":{"x":0.25,"y":0.0,"z":0.0},"emergency_stop":false}"#,
+                r#"{"timestamp_ms":20,"control_state":"uninitialized","target":{"x":0.0,"y":0.0,"z":0.0},"actual":null,"emergency_stop":true}"#,
+            ]
+        );
+    }
+}
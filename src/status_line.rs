@@ -0,0 +1,124 @@
+// ============================================================================
+// Status Line Module - One line summary in a file, for tmux / status bars
+// ============================================================================
+
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::keyboard_input::SpeedData;
+use super::robot_ui::ControlState;
+
+/// The file is rewritten at most this often
+const STATUS_LINE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Format the summary, e.g. `READY cmd x+0.10 y+0.00 z+0.00 | act x+0.09 y+0.00 z+0.00`
+pub fn format_status_line(
+    control_state: ControlState,
+    emergency_stop: bool,
+    commanded: SpeedData,
+    actual: Option<SpeedData>,
+) -> String {
+    let state = if emergency_stop {
+        "ESTOP"
+    } else {
+        match control_state {
+            ControlState::Uninitialized => "INIT",
+            ControlState::InitializedButNotHold => "NO CONTROL",
+            ControlState::CanMove => "READY",
+        }
+    };
+    let speed = |s: SpeedData| format!("x{:+.2} y{:+.2} z{:+.2}", s.x, s.y, s.z);
+    let actual = actual.map_or_else(|| "-".to_string(), speed);
+    format!("{} cmd {} | act {}", state, speed(commanded), actual)
+}
+
+/// Writes the status line to a file, replacing it atomically so a reader
+/// never sees a partially written line
+pub struct StatusLineFile {
+    path: PathBuf,
+    tmp_path: PathBuf,
+    last_written: Option<Instant>,
+}
+
+impl StatusLineFile {
+    pub fn new(path: PathBuf) -> Self {
+        let mut tmp_path = path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        Self {
+            path,
+            tmp_path: tmp_path.into(),
+            last_written: None,
+        }
+    }
+
+    /// Write the line from `line`, unless the file was written less than
+    /// `STATUS_LINE_INTERVAL` ago
+    pub fn update(&mut self, now: Instant, line: impl FnOnce() -> String) -> io::Result<()> {
+        if self
+            .last_written
+            .is_some_and(|last| now.duration_since(last) < STATUS_LINE_INTERVAL)
+        {
+            return Ok(());
+        }
+        self.last_written = Some(now);
+        std::fs::write(&self.tmp_path, line() + "\n")?;
+        std::fs::rename(&self.tmp_path, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("{}-{}.txt", name, std::process::id()))
+    }
+
+    #[test]
+    fn line_is_replaced_through_a_temp_file() {
+        let path = temp_path("status-line");
+        let mut file = StatusLineFile::new(path.clone());
+        file.update(Instant::now(), || "READY".to_string()).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "READY\n");
+        // The temp file is renamed over the target, nothing is left behind
+        assert!(!file.tmp_path.exists(), "{}", file.tmp_path.display());
+        assert_eq!(
+            file.tmp_path,
+            temp_path("status-line").with_extension("txt.tmp")
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writes_are_throttled() {
+        let path = temp_path("status-line-throttle");
+        let mut file = StatusLineFile::new(path.clone());
+        let start = Instant::now();
+        file.update(start, || "first".to_string()).unwrap();
+        let mut formatted = false;
+        file.update(start + Duration::from_millis(200), || {
+            formatted = true;
+            "second".to_string()
+        })
+        .unwrap();
+        assert!(!formatted, "throttled updates don't format the line");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "first\n");
+
+        file.update(start + STATUS_LINE_INTERVAL, || "third".to_string())
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "third\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn emergency_stop_overrides_the_control_state() {
+        let line = format_status_line(
+            ControlState::CanMove,
+            true,
+            SpeedData::new(0.1, 0.0, -0.5),
+            None,
+        );
+        assert_eq!(line, "ESTOP cmd x+0.10 y+0.00 z-0.50 | act -");
+    }
+}
@@ -0,0 +1,54 @@
+// ============================================================================
+// Units Module - Units speeds are displayed in
+// ============================================================================
+//
+// Only the display converts. Everything sent to or received from the base,
+// and all control math, stays in m/s and rad/s.
+
+/// Units for the speed panels
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum UnitSystem {
+    /// m/s and rad/s, as the base reports them
+    #[default]
+    Si,
+    /// km/h and deg/s
+    KmhDeg,
+}
+
+impl UnitSystem {
+    /// Linear speed given in m/s, with the unit suffix
+    pub fn format_linear(self, meters_per_second: f32) -> String {
+        match self {
+            UnitSystem::Si => format!("{:+.3} m/s", meters_per_second),
+            UnitSystem::KmhDeg => format!("{:+.2} km/h", meters_per_second * 3.6),
+        }
+    }
+
+    /// Angular speed given in rad/s, with the unit suffix
+    pub fn format_angular(self, radians_per_second: f32) -> String {
+        match self {
+            UnitSystem::Si => format!("{:+.3} rad/s", radians_per_second),
+            UnitSystem::KmhDeg => format!("{:+.1} deg/s", radians_per_second.to_degrees()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn si_shows_values_unchanged() {
+        assert_eq!(UnitSystem::Si.format_linear(0.1), "+0.100 m/s");
+        assert_eq!(UnitSystem::Si.format_angular(-0.5), "-0.500 rad/s");
+    }
+
+    #[test]
+    fn kmh_deg_converts() {
+        assert_eq!(UnitSystem::KmhDeg.format_linear(1.0), "+3.60 km/h");
+        assert_eq!(
+            UnitSystem::KmhDeg.format_angular(std::f32::consts::FRAC_PI_2),
+            "+90.0 deg/s"
+        );
+    }
+}
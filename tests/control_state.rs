@@ -0,0 +1,126 @@
+// ============================================================================
+// Control State - The client's view of API control against the mock robot
+// ============================================================================
+//
+// Sessions connect to a mock base served on a local port and follow the
+// ControlState and emergency stop their statuses report, as the examples do.
+
+use futures_util::StreamExt;
+use prost::Message;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use robot_examples::base_backend;
+use robot_examples::client::{gated_command, ControlStatus};
+use robot_examples::lock_recover::LockRecover;
+use robot_examples::mock_robot::{serve, MockBase};
+use robot_examples::robot_ui::ControlState;
+use robot_examples::{RobotClient, SpeedData};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn start_mock() -> (String, Arc<Mutex<MockBase>>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let base = Arc::new(Mutex::new(MockBase::new(Duration::ZERO)));
+    tokio::spawn(serve(listener, base.clone()));
+    (url, base)
+}
+
+/// Receive statuses until one gives `want`, returning the session id it was
+/// sent to and the status
+async fn wait_for(
+    client: &mut RobotClient,
+    want: ControlStatus,
+) -> (u32, base_backend::BaseStatus) {
+    let wait = async {
+        loop {
+            let message = client.recv().await.unwrap();
+            if let Some(base_backend::api_up::Status::BaseStatus(status)) = message.status {
+                if ControlStatus::new(&status, message.session_id) == want {
+                    return (message.session_id, status);
+                }
+            }
+        }
+    };
+    tokio::time::timeout(TIMEOUT, wait)
+        .await
+        .unwrap_or_else(|_| panic!("no status gave {:?}", want))
+}
+
+fn control(state: ControlState, emergency_stop: bool) -> ControlStatus {
+    ControlStatus {
+        state,
+        emergency_stop,
+    }
+}
+
+#[tokio::test]
+async fn initializing_session_takes_control() {
+    let (url, _base) = start_mock().await;
+
+    // A session that never initializes sees the base uninitialized
+    let (mut observer, _) = tokio_tungstenite::connect_async(&url).await.unwrap();
+    let first = tokio::time::timeout(TIMEOUT, observer.next())
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    let message = base_backend::ApiUp::decode(&first.into_data()[..]).unwrap();
+    let Some(base_backend::api_up::Status::BaseStatus(status)) = message.status else {
+        panic!("no status in {:?}", message);
+    };
+    assert_eq!(
+        ControlStatus::new(&status, message.session_id),
+        control(ControlState::Uninitialized, false)
+    );
+
+    let mut client = RobotClient::connect(&url).await.unwrap();
+    let (session_id, status) = wait_for(&mut client, control(ControlState::CanMove, false)).await;
+    assert_eq!(status.session_holder, session_id);
+    client.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn second_session_does_not_hold_control() {
+    let (url, _base) = start_mock().await;
+    let mut holder = RobotClient::connect(&url).await.unwrap();
+    let (holder_id, _) = wait_for(&mut holder, control(ControlState::CanMove, false)).await;
+
+    let mut other = RobotClient::connect(&url).await.unwrap();
+    let waiting = control(ControlState::InitializedButNotHold, false);
+    let (other_id, status) = wait_for(&mut other, waiting).await;
+    assert_ne!(other_id, holder_id);
+    assert_eq!(status.session_holder, holder_id);
+    let requested = SpeedData::new(0.2, 0.0, 0.0);
+    assert_eq!(gated_command(waiting.state, false, requested), None);
+
+    // Released control goes to the next session asking for it
+    holder.close().await.unwrap();
+    tokio::time::timeout(TIMEOUT, other.wait_for_control())
+        .await
+        .unwrap()
+        .unwrap();
+    other.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn parking_stop_sets_the_emergency_stop_until_it_clears() {
+    let (url, base) = start_mock().await;
+    let mut client = RobotClient::connect(&url).await.unwrap();
+    wait_for(&mut client, control(ControlState::CanMove, false)).await;
+
+    base.lock_recover().set_parking_stop(true, Instant::now());
+    let stopped = control(ControlState::InitializedButNotHold, true);
+    let (_, status) = wait_for(&mut client, stopped).await;
+    assert!(status.parking_stop_detail.is_some());
+    let requested = SpeedData::new(0.2, 0.0, 0.0);
+    assert_eq!(
+        gated_command(ControlState::CanMove, stopped.emergency_stop, requested),
+        Some(SpeedData::ZERO)
+    );
+
+    base.lock_recover().set_parking_stop(false, Instant::now());
+    wait_for(&mut client, control(ControlState::CanMove, false)).await;
+    client.close().await.unwrap();
+}